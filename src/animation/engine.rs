@@ -1,5 +1,6 @@
 use super::AnimationConfig;
 use crate::border_manager::Border;
+use crate::colors::space::ColorSpace;
 use crate::colors::ColorImpl;
 use crate::core::animation::parse_duration_str;
 use crate::core::animation::AnimationEasing;
@@ -16,6 +17,7 @@ pub struct AnimationEngine {
     pub kind: AnimationKind,
     pub duration: f32,
     pub easing: AnimationEasing,
+    pub interpolation: ColorSpace,
 }
 
 impl AnimationEngine {
@@ -68,18 +70,15 @@ impl AnimationEngine {
 
         border.animation_manager.progress.angle = 360.0 * y_coord;
 
-        // Calculate the center point of the window
-        let center_x = border.window_rect.width() / 2;
-        let center_y = border.window_rect.height() / 2;
+        // Rotate each color around its own center, so a conic gradient sweeps around the border
+        let window_rect = border.window_rect.into();
+        for color in [&border.active_color, &border.inactive_color] {
+            let center = color.center(&window_rect);
+            let transform =
+                Matrix3x2::rotation(border.animation_manager.progress.angle, center.x, center.y);
 
-        let transform = Matrix3x2::rotation(
-            border.animation_manager.progress.angle,
-            center_x as f32,
-            center_y as f32,
-        );
-
-        border.active_color.set_transform(&transform);
-        border.inactive_color.set_transform(&transform);
+            color.set_transform(&transform);
+        }
     }
 
     fn animate_fade(&self, border: &mut Border, elapsed_time: &StdDuration) {
@@ -111,6 +110,8 @@ impl AnimationEngine {
                 .fade
                 .clamp(Self::MINIMUM_PROGRESS, Self::MAXIMUM_PROGRESS);
 
+            border.active_color.reset_brush_color();
+            border.inactive_color.reset_brush_color();
             border.active_color.set_opacity(final_opacity);
             border
                 .inactive_color
@@ -139,6 +140,20 @@ impl AnimationEngine {
             }
         };
 
+        // Two solid colors are mixed directly, so the border passes through the colors in
+        // between instead of showing both at once.
+        if let (false, Some(active), Some(inactive)) = (
+            border.animation_manager.flags.fade_to_visible,
+            border.active_color.solid_color(),
+            border.inactive_color.solid_color(),
+        ) {
+            let color = self.interpolation.mix(&inactive, &active, y_coord);
+            border.active_color.set_brush_color(&color);
+            border.active_color.set_opacity(Self::MAXIMUM_PROGRESS);
+            border.inactive_color.set_opacity(Self::MINIMUM_PROGRESS);
+            return;
+        }
+
         let (new_active_opacity, new_inactive_opacity) =
             if border.animation_manager.flags.fade_to_visible {
                 if border.is_window_active {
@@ -167,24 +182,29 @@ impl TryFrom<AnimationConfig> for AnimationEngine {
             AnimationKind::Fade => 200.0,
         };
 
-        // Parse easing, using a default value if not provided or invalid.
-        let easing = AnimationEasing::from_str(value.easing.clone().unwrap_or_default().as_str())
-            .unwrap_or_default();
+        // Parse easing, using the default value if not provided.
+        let easing = match value.easing {
+            Some(ref easing) => AnimationEasing::from_str(easing).map_err(|err| anyhow!(err))?,
+            None => AnimationEasing::default(),
+        };
 
         // Parse or default the duration.
         let duration = match value.duration {
             Some(Duration::Number(value)) => value,
-            Some(Duration::Text(ref value)) => {
-                parse_duration_str(value).unwrap_or(default_duration)
-            } // Default to 1.0 if parsing fails
+            Some(Duration::Text(ref value)) => parse_duration_str(value)?,
             None => default_duration, // Default duration
         };
 
+        // Animations mix in OKLab unless told otherwise, which keeps e.g. blue to yellow from
+        // passing through grey.
+        let interpolation = value.interpolation.unwrap_or(ColorSpace::Oklab);
+
         // Return the constructed Animation struct.
         Ok(AnimationEngine {
             kind,
             duration,
             easing,
+            interpolation,
         })
     }
 }
@@ -1,21 +1,34 @@
+use crate::colors::space::ColorSpace;
+use crate::core::animation::AnimationEasing;
+use crate::core::animation::AnimationKind;
 use crate::core::duration::Duration;
 use schema_jsonrs::JsonSchema;
 use serde::Deserialize;
+use serde::Serialize;
 
 pub mod engine;
 pub mod manager;
 pub mod wrapper;
 
-#[derive(Debug, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
 pub struct AnimationsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub active: Option<Vec<AnimationConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub inactive: Option<Vec<AnimationConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub fps: Option<i32>,
 }
 
-#[derive(Clone, PartialEq, Debug, Deserialize, JsonSchema)]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct AnimationConfig {
+    #[schemars(with = "AnimationKind")]
     pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub duration: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<AnimationEasing>")]
     pub easing: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpolation: Option<ColorSpace>,
 }
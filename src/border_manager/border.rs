@@ -8,7 +8,7 @@ use crate::core::app_state::APP_STATE;
 use crate::core::rect::Rect;
 use crate::error::LogIfErr;
 use crate::user_config::BorderStyle;
-use crate::user_config::WindowRuleConfig;
+use crate::user_config::EffectiveRule;
 use crate::windows_api::PointerConversion;
 use crate::windows_api::ToWideString;
 use crate::windows_api::WindowsApi;
@@ -17,12 +17,14 @@ use crate::windows_api::WM_APP_HIDECLOAKED;
 use crate::windows_api::WM_APP_LOCATIONCHANGE;
 use crate::windows_api::WM_APP_MINIMIZEEND;
 use crate::windows_api::WM_APP_MINIMIZESTART;
+use crate::windows_api::WM_APP_RELOADCONFIG;
 use crate::windows_api::WM_APP_REORDER;
 use crate::windows_api::WM_APP_SHOWUNCLOAKED;
 use crate::windows_api::WM_APP_TIMER;
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result as AnyResult;
+use log::Level;
 use std::thread;
 use std::time;
 use std::time::Instant;
@@ -75,6 +77,7 @@ use windows::Win32::UI::WindowsAndMessaging::SM_CXVIRTUALSCREEN;
 use windows::Win32::UI::WindowsAndMessaging::SWP_HIDEWINDOW;
 use windows::Win32::UI::WindowsAndMessaging::SWP_SHOWWINDOW;
 use windows::Win32::UI::WindowsAndMessaging::WM_CREATE;
+use windows::Win32::UI::WindowsAndMessaging::WM_DISPLAYCHANGE;
 use windows::Win32::UI::WindowsAndMessaging::WM_NCDESTROY;
 use windows::Win32::UI::WindowsAndMessaging::WM_PAINT;
 use windows::Win32::UI::WindowsAndMessaging::WM_QUIT;
@@ -116,6 +119,9 @@ pub struct Border {
     pub unminimize_delay: u64,
     pub pause: bool,
     pub current_dpi: f32,
+    pub window_rule: EffectiveRule,
+    /// Which state predicates the window passed when its rule was last resolved.
+    pub window_state_key: Vec<bool>,
 }
 
 impl Border {
@@ -144,7 +150,7 @@ impl Border {
         let window_rule = WindowsApi::get_window_rule(handle);
 
         // Handle border creation based on the rule's enabled status.
-        match window_rule.match_window.enabled {
+        match window_rule.enabled {
             Some(false) => {
                 info!(
                     "Border creation is disabled for window: {:?}",
@@ -183,17 +189,16 @@ impl Border {
         }
 
         // Retrieve the window's specific rule configuration.
-        let window_rule = WindowsApi::get_window_rule(handle);
+        let identity = WindowsApi::get_window_identity_for_rules(handle);
+        let window_rule = WindowsApi::get_window_rule_for(handle, &identity);
 
         // Determine if border creation should proceed based on the window rule's enabled status.
-        match window_rule.match_window.enabled {
+        match window_rule.enabled {
             // If border creation is explicitly disabled, log and exit.
             Some(false) => {
-                let window_title =
-                    WindowsApi::get_process_name(handle).unwrap_or("unknown".to_string());
                 info!(
                     "border creation is disabled for window: {} ({:?})",
-                    window_title,
+                    identity,
                     handle.as_hwnd()
                 );
             }
@@ -206,6 +211,34 @@ impl Border {
         }
     }
 
+    /// Creates a border for a window that may have been left without one because a rule
+    /// testing its state disabled it, e.g. while it was fullscreen.
+    pub fn show_for_window_state(handle: isize) {
+        if !WindowsApi::is_window_visible_on_screen(handle)
+            || !WindowsApi::is_window_top_level(handle)
+        {
+            return;
+        }
+
+        // Resolving the rule means collecting the window's identity, which is too slow to do on
+        // every move, so only do it once the state crosses a threshold that some rule tests.
+        let state = WindowsApi::get_window_state(handle);
+        if !APP_STATE
+            .rule_engine
+            .read()
+            .unwrap()
+            .update_borderless_state(handle, &state)
+        {
+            return;
+        }
+
+        let window_rule = WindowsApi::get_window_rule(handle);
+
+        if window_rule.is_enabled(|| WindowsApi::has_filtered_style(handle)) {
+            Border::create(handle, window_rule);
+        }
+    }
+
     pub fn hide(handle: isize) -> bool {
         let _ = std::thread::spawn(move || {
             if let Some(border) = window_border(handle) {
@@ -222,12 +255,18 @@ impl Border {
         true
     }
 
-    pub fn create(tracking_window: isize, window_rule: WindowRuleConfig) {
-        debug!(
-            "creating border for: {} ({:?})",
-            WindowsApi::get_process_name(tracking_window).unwrap_or("unknown".to_string()),
-            tracking_window.as_hwnd()
-        );
+    pub fn create(tracking_window: isize, window_rule: EffectiveRule) {
+        debug!("creating border for: {:?}", tracking_window.as_hwnd());
+
+        // The full identity includes the app user model id and parent process, which are slow to
+        // look up, so only collect it when tracing.
+        if log_enabled!(Level::Trace) {
+            trace!(
+                "identity of {:?}: {}",
+                tracking_window.as_hwnd(),
+                WindowsApi::get_window_identity(tracking_window)
+            );
+        }
 
         std::thread::spawn(move || {
             let mut borders_hashmap = window_borders();
@@ -260,7 +299,7 @@ impl Border {
         });
     }
 
-    pub fn create_border_window(&mut self, window_rule: &WindowRuleConfig) -> AnyResult<()> {
+    pub fn create_border_window(&mut self, window_rule: &EffectiveRule) -> AnyResult<()> {
         let title = format!(
             "tacky-border | {} | {:?}",
             WindowsApi::get_window_title(self.tracking_window).unwrap_or_default(),
@@ -376,45 +415,9 @@ impl Border {
         Ok(())
     }
 
-    fn load_from_config(&mut self, window_rule: &WindowRuleConfig) -> AnyResult<()> {
-        let config = (*APP_STATE.config.read().unwrap()).clone();
-        let global = &config.global_rule;
-
-        let config_width = window_rule
-            .match_window
-            .border_width
-            .unwrap_or(config.global_rule.border_width);
-        let config_offset = window_rule
-            .match_window
-            .border_offset
-            .unwrap_or(config.global_rule.border_offset);
-
-        let config_active = window_rule
-            .match_window
-            .active_color
-            .as_ref()
-            .unwrap_or(&global.active_color);
-
-        let config_inactive = window_rule
-            .match_window
-            .inactive_color
-            .as_ref()
-            .unwrap_or(&global.inactive_color);
-
-        let animations_config = window_rule
-            .match_window
-            .animations
-            .as_ref()
-            .unwrap_or(&global.animations);
-
-        let config_style = window_rule
-            .match_window
-            .border_style
-            .as_ref()
-            .unwrap_or(&global.border_style);
-
-        self.active_color = config_active.to_color()?;
-        self.inactive_color = config_inactive.to_color()?;
+    fn load_from_config(&mut self, window_rule: &EffectiveRule) -> AnyResult<()> {
+        self.active_color = window_rule.active_color.to_color()?;
+        self.inactive_color = window_rule.inactive_color.to_color()?;
 
         self.current_dpi = match WindowsApi::get_dpi_for_window(self.tracking_window) as f32 {
             0.0 => {
@@ -424,32 +427,104 @@ impl Border {
             valid_dpi => valid_dpi,
         };
 
-        self.width = (config_width as f32 * self.current_dpi / 96.0).round() as i32;
-        self.style = config_style.clone();
+        self.width = (window_rule.border_width as f32 * self.current_dpi / 96.0).round() as i32;
+        self.style = window_rule.border_style.clone();
         // self.border_radius =
         //     config_radius.to_radius(self.border_width, self.current_dpi, self.tracking_window);
-        self.offset = config_offset;
+        self.offset = window_rule.border_offset;
 
-        self.animation_manager = AnimationManager::try_from(animations_config.clone())?;
+        self.animation_manager = AnimationManager::try_from(window_rule.animations.clone())?;
 
         let available_windows = WindowsApi::collect_window_handles().unwrap_or_default();
 
         self.initialize_delay = match available_windows.contains(&self.tracking_window) {
             true => 0,
-            false => window_rule
-                .match_window
-                .initialize_delay
-                .unwrap_or(global.initialize_delay),
+            false => window_rule.initialize_delay,
         };
 
-        self.unminimize_delay = window_rule
-            .match_window
-            .unminimize_delay
-            .unwrap_or(global.unminimize_delay);
+        self.unminimize_delay = window_rule.unminimize_delay;
+        self.window_rule = window_rule.clone();
 
         Ok(())
     }
 
+    /// Applies a changed window rule to the running border, updating only what differs from
+    /// the current rule instead of recreating the border window.
+    fn apply_window_rule(&mut self, window_rule: EffectiveRule) -> AnyResult<()> {
+        let changes = window_rule.changes_from(&self.window_rule);
+
+        // Build everything that can fail first so a bad rule leaves the border untouched.
+        let colors = match changes.colors {
+            true => Some((
+                window_rule.active_color.to_color()?,
+                window_rule.inactive_color.to_color()?,
+            )),
+            false => None,
+        };
+        let animation_manager = match changes.animations {
+            true => Some(AnimationManager::try_from(window_rule.animations.clone())?),
+            false => None,
+        };
+
+        if let Some(mut animation_manager) = animation_manager {
+            self.animation_manager
+                .kill_timer(self.border_window)
+                .log_if_err();
+
+            // Keep the current progress so running animations don't jump back to the start.
+            animation_manager.progress = self.animation_manager.progress.clone();
+            animation_manager.flags = self.animation_manager.flags.clone();
+            self.animation_manager = animation_manager;
+
+            self.active_color.set_transform(&Matrix3x2::identity());
+            self.inactive_color.set_transform(&Matrix3x2::identity());
+        }
+
+        if let Some((active_color, inactive_color)) = colors {
+            self.active_color = active_color;
+            self.inactive_color = inactive_color;
+
+            if let Some(render_target) = self.render_target.clone() {
+                self.create_brushes(&render_target);
+            }
+        }
+
+        if changes.shape {
+            self.width = (window_rule.border_width as f32 * self.current_dpi / 96.0).round() as i32;
+            self.style = window_rule.border_style.clone();
+            self.offset = window_rule.border_offset;
+
+            let border_radius =
+                self.style
+                    .to_radius(self.width, self.current_dpi, self.tracking_window);
+            self.rounded_rect.radiusX = border_radius;
+            self.rounded_rect.radiusY = border_radius;
+        }
+
+        self.unminimize_delay = window_rule.unminimize_delay;
+        self.window_rule = window_rule;
+
+        // Hidden or minimized borders pick up the new rule once they are shown again.
+        if self.pause {
+            return Ok(());
+        }
+
+        if changes.colors || changes.animations {
+            self.update_color(Some(0)).log_if_err();
+        }
+
+        if changes.shape {
+            self.update_window_rect().log_if_err();
+            self.update_position(None).log_if_err();
+        }
+
+        self.animation_manager
+            .set_timer(self.border_window)
+            .log_if_err();
+
+        self.render()
+    }
+
     fn create_render_resources(&mut self) -> AnyResult<()> {
         let render_target_properties = D2D1_RENDER_TARGET_PROPERTIES {
             r#type: D2D1_RENDER_TARGET_TYPE_DEFAULT,
@@ -467,11 +542,6 @@ impl Border {
             presentOptions: D2D1_PRESENT_OPTIONS_RETAIN_CONTENTS | D2D1_PRESENT_OPTIONS_IMMEDIATELY,
         };
 
-        let brush_properties = D2D1_BRUSH_PROPERTIES {
-            opacity: 1.0,
-            transform: Matrix3x2::identity(),
-        };
-
         let border_radius =
             self.style
                 .to_radius(self.width, self.current_dpi, self.tracking_window);
@@ -491,12 +561,7 @@ impl Border {
 
             render_target.SetAntialiasMode(D2D1_ANTIALIAS_MODE_PER_PRIMITIVE);
 
-            self.active_color
-                .to_d2d1_brush(&render_target, &self.window_rect.into(), &brush_properties)
-                .log_if_err();
-            self.inactive_color
-                .to_d2d1_brush(&render_target, &self.window_rect.into(), &brush_properties)
-                .log_if_err();
+            self.create_brushes(&render_target);
 
             self.render_target = Some(render_target);
         }
@@ -504,6 +569,20 @@ impl Border {
         Ok(())
     }
 
+    fn create_brushes(&mut self, render_target: &ID2D1HwndRenderTarget) {
+        let brush_properties = D2D1_BRUSH_PROPERTIES {
+            opacity: 1.0,
+            transform: Matrix3x2::identity(),
+        };
+
+        self.active_color
+            .to_d2d1_brush(render_target, &self.window_rect.into(), &brush_properties)
+            .log_if_err();
+        self.inactive_color
+            .to_d2d1_brush(render_target, &self.window_rect.into(), &brush_properties)
+            .log_if_err();
+    }
+
     fn update_window_rect(&mut self) -> AnyResult<()> {
         if let Err(e) = WindowsApi::dwm_get_window_attribute::<RECT>(
             self.tracking_window,
@@ -535,8 +614,7 @@ impl Border {
         .with_context(|| {
             format!(
                 "Failed to set position for window: {} ({:?})",
-                WindowsApi::get_process_name(self.tracking_window)
-                    .unwrap_or_else(|_| "unknown".to_string()),
+                WindowsApi::get_window_identity(self.tracking_window),
                 self.tracking_window
             )
         })
@@ -576,27 +654,17 @@ impl Border {
         } else {
             (&mut self.inactive_color, &mut self.active_color)
         };
+        // A fade that was cut short may have left a solid brush on a color in between.
+        top_color.reset_brush_color();
+        bottom_color.reset_brush_color();
         top_color.set_opacity(1.0);
         bottom_color.set_opacity(0.0);
     }
 
     fn update_width_radius(&mut self) {
-        let window_rule = WindowsApi::get_window_rule(self.tracking_window);
-        let config = (*APP_STATE.config.read().unwrap()).clone();
-        let global = &config.global_rule;
-
-        let width_config = window_rule
-            .match_window
-            .border_width
-            .unwrap_or(global.border_width);
-        let style_config = window_rule
-            .match_window
-            .border_style
-            .as_ref()
-            .unwrap_or(&global.border_style);
-
-        self.width = (width_config as f32 * self.current_dpi / 96.0).round() as i32;
-        self.style = style_config.clone();
+        self.width =
+            (self.window_rule.border_width as f32 * self.current_dpi / 96.0).round() as i32;
+        self.style = self.window_rule.border_style.clone();
     }
 
     fn current_animations(&self) -> &AnimationEngineVec {
@@ -644,9 +712,7 @@ impl Border {
             render_target.Clear(None);
 
             if bottom_color.get_opacity() > Some(0.0) {
-                if let Color::Gradient(gradient) = bottom_color {
-                    gradient.update_start_end_points(&self.window_rect.into());
-                }
+                bottom_color.update_brush_geometry(&self.window_rect.into());
 
                 match bottom_color.get_brush() {
                     Some(id2d1_brush) => self.draw_rectangle(render_target, id2d1_brush),
@@ -655,9 +721,7 @@ impl Border {
             }
 
             if top_color.get_opacity() > Some(0.0) {
-                if let Color::Gradient(gradient) = top_color {
-                    gradient.update_start_end_points(&self.window_rect.into());
-                }
+                top_color.update_brush_geometry(&self.window_rect.into());
 
                 match top_color.get_brush() {
                     Some(id2d1_brush) => self.draw_rectangle(render_target, id2d1_brush),
@@ -725,6 +789,42 @@ impl Border {
         WindowsApi::post_quit_message(0);
     }
 
+    /// Resolves the window rule again if the window's state changed in a way that some rule
+    /// tests. Returns `false` if the new rule disables the border, which is then destroyed.
+    fn update_window_rule_for_state(&mut self) -> bool {
+        let rule_engine = APP_STATE.rule_engine.read().unwrap();
+        if !rule_engine.uses_window_state() {
+            return true;
+        }
+
+        let state_key = rule_engine.state_key(&WindowsApi::get_window_state(self.tracking_window));
+        drop(rule_engine);
+
+        if state_key == self.window_state_key {
+            return true;
+        }
+        self.window_state_key = state_key;
+
+        let window_rule = WindowsApi::get_window_rule(self.tracking_window);
+
+        if window_rule.enabled == Some(false) {
+            debug!(
+                "border is disabled for {:?} in its current state",
+                self.tracking_window
+            );
+            self.destroy();
+            return false;
+        }
+
+        if window_rule != self.window_rule {
+            self.apply_window_rule(window_rule)
+                .context("could not apply the window rule for the new window state")
+                .log_if_err();
+        }
+
+        true
+    }
+
     pub fn destroy(&self) {
         WindowsApi::destroy_window(self.border_window)
             .context("destroy_border_for_window")
@@ -758,10 +858,6 @@ impl Border {
                     should_render |= true;
                 }
 
-                let update_pos_flags =
-                    (!WindowsApi::is_window_visible(self.border_window)).then_some(SWP_SHOWWINDOW);
-                self.update_position(update_pos_flags).log_if_err();
-
                 let new_dpi = match WindowsApi::get_dpi_for_window(self.tracking_window) as f32 {
                     0.0 => {
                         error!("received invalid dpi of 0 from GetDpiForWindow");
@@ -777,6 +873,16 @@ impl Border {
                     should_render |= true;
                 }
 
+                // Like the DPI, the monitor's overrides and state-dependent rules can change
+                // as the window moves, so they are applied before the border is repositioned.
+                if !self.update_window_rule_for_state() {
+                    return LRESULT(0);
+                }
+
+                let update_pos_flags =
+                    (!WindowsApi::is_window_visible(self.border_window)).then_some(SWP_SHOWWINDOW);
+                self.update_position(update_pos_flags).log_if_err();
+
                 if should_render {
                     self.render().log_if_err();
                 }
@@ -786,6 +892,11 @@ impl Border {
                 // For apps like firefox, when you hover over a tab, a popup window spawns that
                 // changes the z-order and causes the border to sit under the tracking window. To
                 // remedy that, we just re-update the position/z-order when windows are reordered.
+                // A window that was made topmost may also need a different rule.
+                if !self.update_window_rule_for_state() {
+                    return LRESULT(0);
+                }
+
                 self.update_position(None).log_if_err();
             }
             // EVENT_SYSTEM_FOREGROUND
@@ -906,9 +1017,27 @@ impl Border {
                     self.render().log_if_err();
                 }
             }
+            // Sent by border_manager::update_borders after the config was reloaded
+            WM_APP_RELOADCONFIG => {
+                let window_rule = WindowsApi::get_window_rule(self.tracking_window);
+
+                // The predicates may have changed, so the next location change resolves again.
+                self.window_state_key.clear();
+
+                if window_rule != self.window_rule {
+                    self.apply_window_rule(window_rule)
+                        .context("could not apply the reloaded config")
+                        .log_if_err();
+                }
+            }
             WM_PAINT => {
                 let _ = unsafe { ValidateRect(Some(window), None) };
             }
+            WM_DISPLAYCHANGE => {
+                // Monitors may have been added, removed or reordered, which changes their indices.
+                WindowsApi::forget_monitors();
+                return unsafe { DefWindowProcW(window, message, wparam, lparam) };
+            }
             WM_NCDESTROY => {
                 unsafe { SetWindowLongPtrW(window, GWLP_USERDATA, 0) };
                 self.exit_border_thread();
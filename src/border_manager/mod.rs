@@ -3,6 +3,7 @@ mod border;
 use crate::core::app_state::APP_STATE;
 use crate::error::LogIfErr;
 use crate::windows_api::WindowsApi;
+use crate::windows_api::WM_APP_RELOADCONFIG;
 use anyhow::Context;
 use anyhow::Result as AnyResult;
 pub use border::Border;
@@ -13,6 +14,8 @@ use std::collections::HashMap;
 use std::sync::MutexGuard;
 use windows::core::w;
 use windows::Win32::Foundation::GetLastError;
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Foundation::WPARAM;
 use windows::Win32::UI::WindowsAndMessaging::LoadCursorW;
 use windows::Win32::UI::WindowsAndMessaging::RegisterClassW;
 use windows::Win32::UI::WindowsAndMessaging::IDC_ARROW;
@@ -54,26 +57,38 @@ pub fn register_border_class() -> AnyResult<()> {
     Ok(())
 }
 
-pub fn destroy_all_borders() -> AnyResult<()> {
-    let mut borders = window_borders();
-    info!("destroying all borders...");
+/// Applies the current config to every window without recreating borders that stay enabled.
+///
+/// Existing borders re-resolve their window rule and update in place; border windows are only
+/// created or destroyed for windows whose enablement changed.
+pub fn update_borders() {
+    info!("updating borders...");
 
-    for (_, border) in borders.iter() {
-        WindowsApi::destroy_window(border.border_window)
-            .context("reload_borders")
-            .log_if_err();
-    }
-
-    borders.clear();
-    drop(borders);
+    let borders: Vec<Border> = window_borders().values().cloned().collect();
 
-    Ok(())
-}
+    for border in borders {
+        let window_rule = WindowsApi::get_window_rule(border.tracking_window);
 
-pub fn reload_borders() {
-    if destroy_all_borders().is_ok() {
-        info!("reloading borders...");
+        if !window_rule.is_enabled(|| WindowsApi::has_filtered_style(border.tracking_window)) {
+            debug!(
+                "border is no longer enabled for {:?}",
+                border.tracking_window
+            );
+            border.destroy();
+            continue;
+        }
 
-        WindowsApi::process_window_handles(&Border::create).log_if_err();
+        WindowsApi::post_message_w(
+            Some(border.border_window()),
+            WM_APP_RELOADCONFIG,
+            WPARAM(0),
+            LPARAM(0),
+        )
+        .context("update_borders")
+        .log_if_err();
     }
+
+    // Border::create skips windows that already have a border, so this only picks up windows
+    // that were previously disabled.
+    WindowsApi::process_window_handles(&Border::create).log_if_err();
 }
@@ -0,0 +1,764 @@
+use crate::config_backup;
+use crate::config_backup::backup_file;
+use crate::config_loader::serialize_document;
+use crate::config_loader::ConfigLayers;
+use crate::config_migration::CONFIG_VERSION;
+use crate::config_schema::config_schema;
+use crate::config_schema::config_schema_string;
+use crate::config_validator::ConfigReport;
+use crate::diagnostics::Diagnostic;
+use crate::rule_engine::RuleEngine;
+use crate::user_config::ConfigFormat;
+use crate::user_config::MatchKind;
+use crate::user_config::UserConfig;
+use crate::user_config::CONFIG_FORMAT;
+use crate::windows_api::WindowsApi;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result as AnyResult;
+use std::fs::copy;
+use std::fs::read_to_string;
+use std::fs::write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Schema file checked or written by `schema --check` and `schema --write` by default.
+const SCHEMA_FILE: &str = "schema.json";
+
+/// Every match kind, in the order `inspect` prints them.
+const INSPECTED_KINDS: [MatchKind; 7] = [
+    MatchKind::Title,
+    MatchKind::Class,
+    MatchKind::Process,
+    MatchKind::ProcessFullName,
+    MatchKind::ExePath,
+    MatchKind::AppUserModelId,
+    MatchKind::ParentProcess,
+];
+
+const USAGE: &str = "usage: tacky-borders [--config <path>] [<command>]
+
+options:
+  --config <path>  config file to use, or the directory to look for one in
+  -h, --help       print this help and exit
+
+commands:
+  check            validate the config file, print every problem found and exit
+  lint, --lint     only report rules and keybindings that never have any effect
+  inspect          list the open windows with everything window rules can match on, and
+                   which rules match them
+  schema           print the JSON schema of the config file
+  dump-config      print the config as it is loaded, with includes, variables and defaults
+                   applied
+  convert          convert the config file to another format
+  migrate          upgrade the config file to the current version of the format
+  init             write the default config file
+
+schema options:
+  --check [<file>]  exit with an error if <file> (default: schema.json) is out of date
+  --write [<file>]  write the schema to <file> (default: schema.json)
+
+convert options:
+  --to <format>      format to convert to: json, jsonc, yaml, toml or json5, if enabled in this
+                     build
+  --write            write the converted config next to the config file instead of printing
+                     it, keeping the old file as a .bak file
+
+migrate options:
+  --write            save the migrated config, keeping the old file as a .bak file
+
+init options:
+  --format <format>  format of the config file: json, jsonc, yaml, toml or json5, if enabled
+                     in this build (default: the first enabled format that allows comments)
+  --force            replace an existing config file, keeping it as a .bak file
+
+Without a command, tacky-borders starts normally.";
+
+/// What the application was asked to do on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Start the border application.
+    Run,
+    /// Validate the config file and exit.
+    Check,
+    /// Report settings that never have any effect and exit.
+    Lint,
+    /// List the open windows with what rules can match on and exit.
+    Inspect,
+    /// Print, check or write the JSON schema of the config and exit.
+    Schema(SchemaOutput),
+    /// Print the resolved config and exit.
+    DumpConfig,
+    /// Convert the config file to another format and exit.
+    Convert { format: ConfigFormat, write: bool },
+    /// Migrate the config file to the current version of the format and exit.
+    Migrate { write: bool },
+    /// Write the default config file and exit.
+    Init {
+        format: Option<ConfigFormat>,
+        force: bool,
+    },
+    /// Print usage and exit.
+    Help,
+}
+
+/// What to do with the generated schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaOutput {
+    /// Print it.
+    Print,
+    /// Compare it with a schema file.
+    Check(PathBuf),
+    /// Write it to a schema file.
+    Write(PathBuf),
+}
+
+/// Parsed command line arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    /// Config file or directory given with `--config`.
+    pub config: Option<PathBuf>,
+    pub command: Command,
+}
+
+impl Args {
+    pub fn from_args<I>(args: I) -> AnyResult<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        let mut config = None;
+        let mut command = None;
+        let mut format = None;
+        let mut force = false;
+        let mut write = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow!("`--config` expects a path"))?;
+                    config = Some(PathBuf::from(path));
+                }
+                "-h" | "--help" => command = Some(Command::Help),
+                // `--check` predates the `check` command and is kept for existing scripts.
+                "check" | "--check" if command.is_none() => command = Some(Command::Check),
+                "lint" | "--lint" if command.is_none() => command = Some(Command::Lint),
+                "inspect" if command.is_none() => command = Some(Command::Inspect),
+                "schema" if command.is_none() => {
+                    command = Some(Command::Schema(SchemaOutput::Print))
+                }
+                "--check" | "--write" if matches!(command, Some(Command::Schema(_))) => {
+                    let file = PathBuf::from(SCHEMA_FILE);
+                    command = Some(Command::Schema(match arg.as_str() {
+                        "--check" => SchemaOutput::Check(file),
+                        _ => SchemaOutput::Write(file),
+                    }));
+                }
+                "dump-config" if command.is_none() => command = Some(Command::DumpConfig),
+                "convert" if command.is_none() => {
+                    command = Some(Command::Convert {
+                        format: ConfigFormat::None,
+                        write: false,
+                    })
+                }
+                "--write" if matches!(command, Some(Command::Convert { .. })) => write = true,
+                "migrate" if command.is_none() => command = Some(Command::Migrate { write: false }),
+                "--write" if matches!(command, Some(Command::Migrate { .. })) => write = true,
+                "--to" if matches!(command, Some(Command::Convert { .. })) => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| anyhow!("`--to` expects a format"))?;
+                    format = Some(parse_format(&name)?);
+                }
+                "init" if command.is_none() => {
+                    command = Some(Command::Init {
+                        format: None,
+                        force: false,
+                    })
+                }
+                "--force" if matches!(command, Some(Command::Init { .. })) => force = true,
+                "--format" if matches!(command, Some(Command::Init { .. })) => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| anyhow!("`--format` expects a format"))?;
+                    format = Some(parse_format(&name)?);
+                }
+                _ => {
+                    if let Some(path) = arg.strip_prefix("--config=") {
+                        config = Some(PathBuf::from(path));
+                    } else if let (Some(name), Some(Command::Init { .. })) =
+                        (arg.strip_prefix("--format="), &command)
+                    {
+                        format = Some(parse_format(name)?);
+                    } else if let (Some(name), Some(Command::Convert { .. })) =
+                        (arg.strip_prefix("--to="), &command)
+                    {
+                        format = Some(parse_format(name)?);
+                    } else if let Some(Command::Schema(
+                        SchemaOutput::Check(file) | SchemaOutput::Write(file),
+                    )) = &mut command
+                    {
+                        if arg.starts_with('-') {
+                            return Err(anyhow!("unknown argument `{arg}`"));
+                        }
+                        *file = PathBuf::from(arg);
+                    } else {
+                        return Err(anyhow!("unknown argument `{arg}`"));
+                    }
+                }
+            }
+        }
+
+        let command = match command {
+            Some(Command::Init { .. }) => Command::Init { format, force },
+            Some(Command::Migrate { .. }) => Command::Migrate { write },
+            Some(Command::Convert { .. }) => Command::Convert {
+                format: format.ok_or_else(|| anyhow!("`convert` expects `--to <format>`"))?,
+                write,
+            },
+            Some(command) => command,
+            None => Command::Run,
+        };
+
+        Ok(Self { config, command })
+    }
+}
+
+fn parse_format(name: &str) -> AnyResult<ConfigFormat> {
+    match ConfigFormat::from_extension(name) {
+        ConfigFormat::None => Err(anyhow!(
+            "unsupported format `{name}`; it may not be enabled in this build"
+        )),
+        format => Ok(format),
+    }
+}
+
+/// Handles command line modes that don't start the border application.
+///
+/// None of these install hooks, create windows or touch the Direct2D factory, so they can run
+/// without a desktop session (e.g. to validate configs in CI).
+///
+/// Returns the exit code the process should exit with, or `None` if the application should
+/// start normally.
+pub fn run<I>(args: I) -> Option<i32>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::from_args(args);
+
+    if let Ok(Args {
+        config: Some(config),
+        ..
+    }) = &args
+    {
+        UserConfig::set_config_path(config.clone());
+    }
+
+    if matches!(
+        args,
+        Ok(Args {
+            command: Command::Run,
+            ..
+        })
+    ) {
+        return None;
+    }
+
+    // Release builds use the GUI subsystem, so attach to the terminal before printing.
+    #[cfg(windows)]
+    let _ = WindowsApi::attach_parent_console();
+
+    let result = match args.map(|args| args.command) {
+        Ok(Command::Check) => check(false),
+        Ok(Command::Lint) => check(true),
+        Ok(Command::Inspect) => inspect(),
+        Ok(Command::Schema(output)) => schema(output),
+        Ok(Command::DumpConfig) => dump_config(),
+        Ok(Command::Convert { format, write }) => convert(format, write),
+        Ok(Command::Migrate { write }) => migrate(write),
+        Ok(Command::Init { format, force }) => init(format, force),
+        Ok(Command::Help | Command::Run) => {
+            println!("{USAGE}");
+            Ok(0)
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            Ok(2)
+        }
+    };
+
+    let exit_code = result.unwrap_or_else(|err| {
+        eprintln!("error: {err:#}");
+        2
+    });
+
+    Some(exit_code)
+}
+
+/// Validates the config file and prints every diagnostic, or with `lint_only` only the lints
+/// (and any errors that kept the config from being linted).
+///
+/// Exits with `1` if any problem was found so it can be used to lint configs in scripts.
+fn check(lint_only: bool) -> AnyResult<i32> {
+    let config_file = UserConfig::get_config_file()?;
+    let config_format = ConfigFormat::from_path(&config_file);
+    let contents = read_to_string(&config_file)
+        .with_context(|| format!("failed to read config file: {}", config_file.display()))?;
+
+    *CONFIG_FORMAT.write().unwrap() = config_format.clone();
+
+    let profile = UserConfig::get_active_profile();
+    let report = ConfigReport::new(&config_file, &contents, &config_format, profile.as_deref());
+    let diagnostics: Vec<&Diagnostic> = report
+        .diagnostics
+        .iter()
+        .filter(|d| !lint_only || d.is_error())
+        .chain(report.lints.iter())
+        .collect();
+
+    for diagnostic in diagnostics.iter() {
+        eprintln!("{diagnostic}");
+    }
+
+    // Older configs still load, so needing a migration is not a problem on its own.
+    for (file, migration) in report.migrations.iter() {
+        eprintln!(
+            "{}: note: written for config version {}, so it is migrated to version {CONFIG_VERSION} when loaded\n  = help: run `tacky-borders migrate` to see the changes",
+            file.display(),
+            migration.from
+        );
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+
+    if diagnostics.is_empty() {
+        println!("{}: ok", config_file.display());
+        Ok(0)
+    } else {
+        eprintln!(
+            "{}: {errors} error(s), {warnings} warning(s)",
+            config_file.display()
+        );
+        Ok(1)
+    }
+}
+
+/// Prints the JSON schema generated from the config types, or checks or updates a schema file
+/// with it.
+///
+/// `--check` exits with `1` if the file differs, so CI can catch a `schema.json` that was not
+/// regenerated after changing the config types.
+fn schema(output: SchemaOutput) -> AnyResult<i32> {
+    match output {
+        SchemaOutput::Print => print!("{}", config_schema_string()),
+        SchemaOutput::Check(file) => {
+            let contents = read_to_string(&file)
+                .with_context(|| format!("failed to read schema file: {}", file.display()))?;
+            let committed: serde_jsonc2::Value = serde_jsonc2::from_str(&contents)
+                .with_context(|| format!("failed to parse schema file: {}", file.display()))?;
+
+            if committed != config_schema() {
+                eprintln!(
+                    "{}: out of date with the config types\n  = help: run `tacky-borders schema --write {}`",
+                    file.display(),
+                    file.display()
+                );
+                return Ok(1);
+            }
+
+            println!("{}: up to date", file.display());
+        }
+        SchemaOutput::Write(file) => {
+            write(&file, config_schema_string())
+                .with_context(|| format!("failed to write schema file: {}", file.display()))?;
+            println!("wrote {}", file.display());
+        }
+    }
+
+    Ok(0)
+}
+
+/// Prints the config the application would run with.
+///
+/// Unlike starting the application, this never writes a default config or a last known-good
+/// copy, so it is safe to run against any config file.
+fn dump_config() -> AnyResult<i32> {
+    let config_file = UserConfig::get_config_file()?;
+    let profile = UserConfig::get_active_profile();
+    let config = UserConfig::from_file(&config_file, profile.as_deref())?;
+    print!(
+        "{}",
+        serialize_document(&config, &ConfigFormat::from_path(&config_file))?
+    );
+
+    Ok(0)
+}
+
+/// Prints everything window rules can match on for every visible window, along with the rules
+/// that match it with the current config.
+fn inspect() -> AnyResult<i32> {
+    // The windows are still worth listing when the config is broken, e.g. to fix a rule.
+    let rule_engine = UserConfig::get_config_file()
+        .and_then(|file| UserConfig::from_file(&file, UserConfig::get_active_profile().as_deref()))
+        .and_then(|config| RuleEngine::new(&config));
+
+    if let Err(err) = &rule_engine {
+        eprintln!(
+            "note: matching rules are not shown since the config could not be loaded: {err:#}"
+        );
+    }
+
+    let handles = WindowsApi::collect_window_handles()?;
+
+    for hwnd in handles
+        .into_iter()
+        .filter(|&hwnd| WindowsApi::is_window_visible_on_screen(hwnd))
+    {
+        let identity = WindowsApi::get_window_identity(hwnd);
+        let state = WindowsApi::get_window_state(hwnd);
+
+        println!("{hwnd:#x}");
+        for kind in INSPECTED_KINDS {
+            println!("  {:<16} {}", format!("{kind:?}"), identity.value(&kind));
+        }
+        println!(
+            "  {:<16} {}",
+            "Monitor",
+            match &state.monitor {
+                Some(monitor) => monitor.names().join(", "),
+                None => "unknown".to_string(),
+            }
+        );
+
+        let flags: Vec<&str> = [
+            (state.maximized, "maximized"),
+            (state.fullscreen, "fullscreen"),
+            (state.topmost, "topmost"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect();
+        println!(
+            "  {:<16} {}x{}{}",
+            "state",
+            state.width,
+            state.height,
+            flags
+                .iter()
+                .map(|flag| format!(", {flag}"))
+                .collect::<String>()
+        );
+
+        if let Ok(rule_engine) = &rule_engine {
+            let rules: Vec<String> = rule_engine
+                .matching_rules(&identity, &state)
+                .iter()
+                .map(|i| format!("window_rules[{i}]"))
+                .collect();
+            println!(
+                "  {:<16} {}",
+                "rules",
+                match rules.is_empty() {
+                    true => "none".to_string(),
+                    false => rules.join(", "),
+                }
+            );
+        }
+    }
+
+    Ok(0)
+}
+
+/// Converts the config file to another format.
+///
+/// Includes and variables are expanded, so the converted config is a single file that loads to
+/// exactly the same settings. The files in `rules.d` are left out of it, since they are still
+/// merged into the converted config when it is loaded. The active profile is not applied, since
+/// the profiles are converted along with the rest of the config.
+fn convert(format: ConfigFormat, write_file: bool) -> AnyResult<i32> {
+    let config_file = UserConfig::get_config_file()?;
+    let contents = read_to_string(&config_file)
+        .with_context(|| format!("failed to read config file: {}", config_file.display()))?;
+    let mut config = UserConfig::from_file(&config_file, None)?;
+
+    if !config.include.is_empty() || !config.variables.is_empty() || !config.palette.is_empty() {
+        eprintln!("note: includes and variables are expanded in the converted config");
+    }
+    config.include.clear();
+    config.variables.clear();
+    config.palette.clear();
+
+    // Settings from `rules.d` override the same values again when the config is loaded, but
+    // window rules are added to the list, so they would otherwise apply twice.
+    let layers = ConfigLayers::load(
+        &config_file,
+        &contents,
+        &ConfigFormat::from_path(&config_file),
+    );
+    let rules_dir_rules = layers.rules_dir_rule_count();
+    if let Some(rules_dir) = layers.rules_dir.as_ref() {
+        eprintln!(
+            "note: {} is left as it is and still applies to the converted config",
+            rules_dir.display()
+        );
+    }
+    config
+        .window_rules
+        .truncate(config.window_rules.len().saturating_sub(rules_dir_rules));
+
+    let contents = serialize_document(&config, &format)?;
+
+    if !write_file {
+        print!("{contents}");
+        return Ok(0);
+    }
+
+    // Write the converted config before moving the old one aside, so a failed write never
+    // leaves the config directory without a config.
+    let converted_file = config_file.with_extension(format.extension());
+    if converted_file.exists() {
+        move_aside(&converted_file)?;
+    }
+
+    write(&converted_file, contents)
+        .with_context(|| format!("failed to write {}", converted_file.display()))?;
+    println!("wrote {}", converted_file.display());
+
+    if converted_file != config_file {
+        move_aside(&config_file)?;
+    }
+
+    Ok(0)
+}
+
+/// Renames `file` to `<file>.bak`, replacing any previous backup, and says so.
+fn move_aside(file: &Path) -> AnyResult<()> {
+    let backup = config_backup::move_aside(file)?;
+    println!("moved {} to {}", file.display(), backup.display());
+
+    Ok(())
+}
+
+/// Migrates the config file, and every file it includes, to the current version of the format.
+///
+/// Prints what was changed in each file; with `write_files` the migrated files are written
+/// back, keeping the old ones as `.bak` files.
+fn migrate(write_files: bool) -> AnyResult<i32> {
+    let config_file = UserConfig::get_config_file()?;
+    let contents = read_to_string(&config_file)
+        .with_context(|| format!("failed to read config file: {}", config_file.display()))?;
+    let layers = ConfigLayers::load(
+        &config_file,
+        &contents,
+        &ConfigFormat::from_path(&config_file),
+    );
+
+    // Files that can't be read, or are newer than this build, can't be migrated safely.
+    let errors: Vec<&Diagnostic> = layers.diagnostics.iter().filter(|d| d.is_error()).collect();
+    if !errors.is_empty() {
+        for error in errors {
+            eprintln!("{error}");
+        }
+        return Ok(1);
+    }
+
+    let migrated_sources: Vec<_> = layers
+        .sources
+        .iter()
+        .filter_map(|source| Some((source, source.migration.as_ref()?)))
+        .collect();
+
+    if migrated_sources.is_empty() {
+        println!(
+            "{}: already at config version {CONFIG_VERSION}",
+            config_file.display()
+        );
+        return Ok(0);
+    }
+
+    if write_files {
+        eprintln!("note: migrated files are rewritten, so their comments and formatting are lost");
+    }
+
+    for (source, migration) in migrated_sources {
+        println!(
+            "{}: migrated from config version {} to {CONFIG_VERSION}",
+            source.path.display(),
+            migration.from
+        );
+        for change in migration.changes.iter() {
+            println!("  {change}");
+        }
+
+        if write_files {
+            // Copy rather than move the old file, so a failed write never leaves it missing.
+            let contents = serialize_document(&source.document, &source.format)?;
+            let backup = backup_file(&source.path);
+            copy(&source.path, &backup)
+                .with_context(|| format!("failed to back up {}", source.path.display()))?;
+            write(&source.path, contents)
+                .with_context(|| format!("failed to write {}", source.path.display()))?;
+            println!("  kept the old file as {}", backup.display());
+        }
+    }
+
+    if !write_files {
+        println!("run `tacky-borders migrate --write` to save the migrated files");
+    }
+
+    Ok(0)
+}
+
+/// Writes the default config file.
+///
+/// Refuses to touch an existing config unless `force` is set, in which case every existing
+/// config file is moved aside so the new one is the one that gets loaded.
+fn init(format: Option<ConfigFormat>, force: bool) -> AnyResult<i32> {
+    let config_dir = UserConfig::get_config_dir()?;
+    let format = format.unwrap_or_else(ConfigFormat::preferred);
+    let existing_files = UserConfig::config_files(&config_dir);
+
+    if !existing_files.is_empty() && !force {
+        for file in existing_files.iter() {
+            eprintln!("error: a config file already exists: {}", file.display());
+        }
+        eprintln!("  = help: pass `--force` to replace it");
+        return Ok(1);
+    }
+
+    for file in existing_files {
+        move_aside(&file)?;
+    }
+
+    let config_file = UserConfig::write_default_config(&config_dir, &format)?;
+    println!("wrote {}", config_file.display());
+
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> AnyResult<Args> {
+        Args::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parse_err(args: &[&str]) -> String {
+        parse(args).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn no_arguments_start_the_application() {
+        assert_eq!(
+            parse(&[]).unwrap(),
+            Args {
+                config: None,
+                command: Command::Run,
+            }
+        );
+    }
+
+    #[test]
+    fn config_path_is_taken_from_either_form() {
+        for args in [
+            &["--config", "borders/config.yaml"][..],
+            &["--config=borders/config.yaml"],
+        ] {
+            let parsed = parse(args).unwrap();
+            assert_eq!(parsed.config, Some(PathBuf::from("borders/config.yaml")));
+            assert_eq!(parsed.command, Command::Run);
+        }
+
+        // The option can come before or after the command.
+        for args in [
+            &["--config", "borders", "check"][..],
+            &["check", "--config", "borders"],
+        ] {
+            let parsed = parse(args).unwrap();
+            assert_eq!(parsed.config, Some(PathBuf::from("borders")));
+            assert_eq!(parsed.command, Command::Check);
+        }
+    }
+
+    #[test]
+    fn check_flag_is_the_check_command_unless_it_follows_schema() {
+        assert_eq!(parse(&["check"]).unwrap().command, Command::Check);
+        assert_eq!(parse(&["--check"]).unwrap().command, Command::Check);
+
+        assert_eq!(
+            parse(&["schema", "--check"]).unwrap().command,
+            Command::Schema(SchemaOutput::Check(PathBuf::from(SCHEMA_FILE)))
+        );
+        assert_eq!(
+            parse(&["schema", "--write", "docs/schema.json"])
+                .unwrap()
+                .command,
+            Command::Schema(SchemaOutput::Write(PathBuf::from("docs/schema.json")))
+        );
+
+        assert_eq!(
+            parse_err(&["check", "--check"]),
+            "unknown argument `--check`"
+        );
+    }
+
+    #[test]
+    fn options_without_a_value_are_rejected() {
+        assert_eq!(parse_err(&["--config"]), "`--config` expects a path");
+        assert_eq!(parse_err(&["convert", "--to"]), "`--to` expects a format");
+        assert_eq!(
+            parse_err(&["init", "--format"]),
+            "`--format` expects a format"
+        );
+        assert_eq!(parse_err(&["convert"]), "`convert` expects `--to <format>`");
+    }
+
+    #[test]
+    fn unknown_arguments_are_rejected() {
+        assert_eq!(parse_err(&["--verbose"]), "unknown argument `--verbose`");
+        assert_eq!(parse_err(&["frobnicate"]), "unknown argument `frobnicate`");
+        // Options are only known after the command they belong to.
+        assert_eq!(parse_err(&["--force"]), "unknown argument `--force`");
+        assert_eq!(
+            parse_err(&["check", "--write"]),
+            "unknown argument `--write`"
+        );
+        assert_eq!(
+            parse_err(&["schema", "--check", "--bogus"]),
+            "unknown argument `--bogus`"
+        );
+        assert_eq!(
+            parse_err(&["convert", "--to", "xml"]),
+            "unsupported format `xml`; it may not be enabled in this build"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn command_options_are_collected() {
+        assert_eq!(
+            parse(&["convert", "--write", "--to=json"]).unwrap().command,
+            Command::Convert {
+                format: ConfigFormat::Json,
+                write: true,
+            }
+        );
+        assert_eq!(
+            parse(&["init", "--format", "jsonc", "--force"])
+                .unwrap()
+                .command,
+            Command::Init {
+                format: Some(ConfigFormat::Jsonc),
+                force: true,
+            }
+        );
+        assert_eq!(
+            parse(&["migrate"]).unwrap().command,
+            Command::Migrate { write: false }
+        );
+    }
+}
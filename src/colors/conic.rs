@@ -0,0 +1,157 @@
+use super::space::ColorSpace;
+use std::cell::Cell;
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Foundation::RECT;
+use windows::Win32::Graphics::Direct2D::Common::D2D1_COLOR_F;
+use windows::Win32::Graphics::Direct2D::Common::D2D1_GRADIENT_STOP;
+use windows::Win32::Graphics::Direct2D::Common::D2D_POINT_2F;
+use windows::Win32::Graphics::Direct2D::ID2D1BitmapBrush;
+
+/// Represents a conic gradient sweeping around a center point, as in CSS.
+///
+/// Direct2D has no conic gradient brush, so the sweep is drawn into a small bitmap that is
+/// stretched over the window. The bitmap only depends on the angle around the center, so a
+/// fixed size is enough at any window size, and rotating it around the center (e.g. for the
+/// spiral animation) rotates the sweep itself.
+///
+/// # Fields
+/// - `from`: The angle of the first stop in degrees, clockwise from the top.
+/// - `center`: The [x, y] position of the center, normalized from 0.0 to 1.0 and scaled by the
+///   window's size.
+/// - `gradient_stops`: The color stops, clockwise from `from`.
+/// - `brush`: An optional `ID2D1BitmapBrush` holding the sweep. If not initialized, this value
+///   is `None`.
+/// - `placement`: Maps the bitmap onto the window, see `update_placement`.
+/// - `transform`: The transform set with `set_transform`, applied on top of `placement`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConicGradient {
+    pub from: f32,
+    pub center: [f32; 2],
+    pub gradient_stops: Vec<D2D1_GRADIENT_STOP>,
+    pub brush: Option<ID2D1BitmapBrush>,
+    pub placement: Cell<Matrix3x2>,
+    pub transform: Cell<Matrix3x2>,
+}
+
+impl ConicGradient {
+    /// Width and height of the bitmap holding the sweep, in pixels.
+    pub const BITMAP_SIZE: u32 = 256;
+
+    pub fn new(from: f32, center: [f32; 2], gradient_stops: Vec<D2D1_GRADIENT_STOP>) -> Self {
+        Self {
+            from,
+            center,
+            gradient_stops,
+            brush: None,
+            placement: Cell::new(Matrix3x2::identity()),
+            transform: Cell::new(Matrix3x2::identity()),
+        }
+    }
+
+    /// Returns the center of the gradient in pixels.
+    pub fn center_point(&self, window_rect: &RECT) -> D2D_POINT_2F {
+        let width = (window_rect.right - window_rect.left) as f32;
+        let height = (window_rect.bottom - window_rect.top) as f32;
+
+        D2D_POINT_2F {
+            x: self.center[0] * width,
+            y: self.center[1] * height,
+        }
+    }
+
+    /// Renders the sweep as premultiplied BGRA pixels, `BITMAP_SIZE` pixels square with the
+    /// center in the middle.
+    pub fn pixels(&self) -> Vec<u8> {
+        let size = Self::BITMAP_SIZE as usize;
+        let half = size as f32 / 2.0;
+        let mut pixels = Vec::with_capacity(size * size * 4);
+
+        for row in 0..size {
+            for column in 0..size {
+                let dx = column as f32 + 0.5 - half;
+                let dy = row as f32 + 0.5 - half;
+
+                // Clockwise from the top, since y points down.
+                let angle = dx.atan2(-dy).to_degrees();
+                let position = ((angle - self.from) / 360.0).rem_euclid(1.0);
+                let color = color_at(&self.gradient_stops, position);
+
+                let alpha = color.a.clamp(0.0, 1.0);
+                let channel = |value: f32| (value.clamp(0.0, 1.0) * alpha * 255.0).round() as u8;
+                pixels.extend([
+                    channel(color.b),
+                    channel(color.g),
+                    channel(color.r),
+                    (alpha * 255.0).round() as u8,
+                ]);
+            }
+        }
+
+        pixels
+    }
+
+    /// Stretches the bitmap over the window so its middle sits on the center and it covers the
+    /// whole window at any rotation.
+    pub fn update_placement(&self, window_rect: &RECT) {
+        let width = (window_rect.right - window_rect.left) as f32;
+        let height = (window_rect.bottom - window_rect.top) as f32;
+        let center = self.center_point(window_rect);
+
+        let reach_x = center.x.abs().max((width - center.x).abs());
+        let reach_y = center.y.abs().max((height - center.y).abs());
+        let radius = reach_x.hypot(reach_y).max(1.0);
+        let scale = 2.0 * radius / Self::BITMAP_SIZE as f32;
+
+        self.placement.set(Matrix3x2 {
+            M11: scale,
+            M12: 0.0,
+            M21: 0.0,
+            M22: scale,
+            M31: center.x - radius,
+            M32: center.y - radius,
+        });
+        self.apply_transform();
+    }
+
+    /// Sets the transform applied on top of the placement, e.g. the spiral animation's rotation.
+    pub fn set_transform(&self, transform: &Matrix3x2) {
+        self.transform.set(*transform);
+        self.apply_transform();
+    }
+
+    fn apply_transform(&self) {
+        if let Some(ref id2d1_brush) = self.brush {
+            let transform = self.placement.get() * self.transform.get();
+            unsafe { id2d1_brush.SetTransform(&transform) };
+        }
+    }
+}
+
+/// Returns the color at `position` (from 0.0 to 1.0) along the stops, interpolating between
+/// the two stops around it.
+fn color_at(stops: &[D2D1_GRADIENT_STOP], position: f32) -> D2D1_COLOR_F {
+    let (Some(first), Some(last)) = (stops.first(), stops.last()) else {
+        return D2D1_COLOR_F::default();
+    };
+
+    if position <= first.position {
+        return first.color;
+    }
+
+    for pair in stops.windows(2) {
+        let (start, end) = (&pair[0], &pair[1]);
+        if position <= end.position {
+            let span = end.position - start.position;
+            let t = match span > 0.0 {
+                true => (position - start.position) / span,
+                false => 1.0,
+            };
+
+            // Stops in other color spaces have already been expanded, see
+            // `ColorSpace::interpolate_stops`, so sRGB is the same as what Direct2D does.
+            return ColorSpace::Srgb.mix(&start.color, &end.color, t);
+        }
+    }
+
+    last.color
+}
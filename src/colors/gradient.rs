@@ -1,6 +1,10 @@
+use super::radial::RadialExtent;
+use super::radial::RadialShape;
+use super::space::ColorSpace;
 use colorparser_css::GradientCoordinates;
 use schema_jsonrs::JsonSchema;
 use serde::Deserialize;
+use serde::Serialize;
 use windows::Win32::{
     Foundation::RECT,
     Graphics::Direct2D::{
@@ -16,10 +20,9 @@ use windows::Win32::{
 /// gradient appears, while the optional brush holds the gradient data for rendering.
 ///
 /// # Fields
-/// - `direction`: Specifies the gradient's direction, given by `GradientCoordinates`. It consists of
-///   start and end points, each defined as normalized values ranging from 0.0 to 1.0. These values
-///   are scaled based on the size of the window to determine the pixel positions of the gradient's
-///   start and end points.
+/// - `direction`: Specifies the gradient's direction as a `GradientLine`: either normalized start
+///   and end coordinates, scaled by the window's width and height, or an angle that is kept as is
+///   in pixel space, whatever the window's proportions.
 /// - `gradient_stops`: A vector of `D2D1_GRADIENT_STOP` values, representing the color stops in the
 ///   gradient. These stops define the colors that the gradient transitions through.
 /// - `brush`: An optional `ID2D1LinearGradientBrush` used to render the gradient. If not initialized,
@@ -29,7 +32,7 @@ use windows::Win32::{
 /// ```rust
 /// use windows::Win32::Graphics::Direct2D::Common::D2D1_GRADIENT_STOP;
 /// let gradient = Gradient {
-///     direction: GradientCoordinates { start: [0.0, 0.0], end: [1.0, 1.0] },
+///     direction: GradientLine::Angle(135.0),
 ///     gradient_stops: vec![
 ///         D2D1_GRADIENT_STOP { position: 0.0, color: D2D1_COLOR_F { r: 1.0, g: 0.0, b: 0.0, a: 1.0 } },
 ///         D2D1_GRADIENT_STOP { position: 1.0, color: D2D1_COLOR_F { r: 0.0, g: 0.0, b: 1.0, a: 1.0 } },
@@ -39,8 +42,8 @@ use windows::Win32::{
 /// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct Gradient {
-    /// The direction of the gradient, either as a string or as coordinates.
-    pub direction: GradientCoordinates,
+    /// The direction of the gradient, either as an angle or as coordinates.
+    pub direction: GradientLine,
     /// A list of gradient stops defining color stops in the gradient.
     pub gradient_stops: Vec<D2D1_GRADIENT_STOP>,
     /// An optional linear gradient brush that can be used for rendering the gradient.
@@ -49,30 +52,60 @@ pub struct Gradient {
 }
 
 impl Gradient {
+    /// Returns the start and end points of the gradient in pixels for the window's size.
+    pub fn start_end_points(&self, window_rect: &RECT) -> (D2D_POINT_2F, D2D_POINT_2F) {
+        let width = (window_rect.right - window_rect.left) as f32;
+        let height = (window_rect.bottom - window_rect.top) as f32;
+
+        let angle = match self.direction {
+            GradientLine::Coordinates(ref coordinates) => {
+                // The coordinates only range from 0.0 to 1.0, but we need to convert them into
+                // coordinates in terms of pixels
+                return (
+                    D2D_POINT_2F {
+                        x: coordinates.start[0] * width,
+                        y: coordinates.start[1] * height,
+                    },
+                    D2D_POINT_2F {
+                        x: coordinates.end[0] * width,
+                        y: coordinates.end[1] * height,
+                    },
+                );
+            }
+            GradientLine::Angle(angle) => angle,
+            // As in CSS, the gradient points into the corner's quadrant at the angle that puts
+            // the other two corners on the same color, so it ends exactly in the corner.
+            GradientLine::Corner { x, y } => (x * height).atan2(-y * width).to_degrees(),
+        };
+
+        // The gradient line runs through the middle of the window and is just long enough for
+        // the corners to get the first and last colors, as in CSS.
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let half_length = (width * sin.abs() + height * cos.abs()) / 2.0;
+        let (center_x, center_y) = (width / 2.0, height / 2.0);
+
+        (
+            D2D_POINT_2F {
+                x: center_x - sin * half_length,
+                y: center_y + cos * half_length,
+            },
+            D2D_POINT_2F {
+                x: center_x + sin * half_length,
+                y: center_y - cos * half_length,
+            },
+        )
+    }
+
     /// Updates the start and end points of the gradient based on the window's dimensions.
     ///
-    /// This method calculates the new start and end points of the gradient brush based on the
-    /// window's size (given by `window_rect`). The direction of the gradient is scaled from
-    /// normalized coordinates (ranging from 0.0 to 1.0) to pixel coordinates based on the window's
-    /// width and height.
+    /// This method recalculates the start and end points of the gradient brush for the
+    /// window's current size (given by `window_rect`), see `start_end_points`.
     ///
     /// # Parameters
     /// - `window_rect`: The dimensions of the window as a `RECT`, used to compute the pixel
     ///   positions for the gradient's start and end points.
     pub fn update_start_end_points(&self, window_rect: &RECT) {
-        let width = (window_rect.right - window_rect.left) as f32;
-        let height = (window_rect.bottom - window_rect.top) as f32;
-
-        // The direction/GradientCoordinates only range from 0.0 to 1.0, but we need to
-        // convert it into coordinates in terms of pixels
-        let start_point = D2D_POINT_2F {
-            x: self.direction.start[0] * width,
-            y: self.direction.start[1] * height,
-        };
-        let end_point = D2D_POINT_2F {
-            x: self.direction.end[0] * width,
-            y: self.direction.end[1] * height,
-        };
+        let (start_point, end_point) = self.start_end_points(window_rect);
 
         if let Some(ref id2d1_brush) = self.brush {
             unsafe {
@@ -83,9 +116,21 @@ impl Gradient {
     }
 }
 
+/// How the start and end points of a linear gradient are found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GradientLine {
+    /// Normalized start and end coordinates, scaled by the window's width and height.
+    Coordinates(GradientCoordinates),
+    /// An angle in degrees, clockwise from the top (`0deg` points up, `90deg` right), as in CSS.
+    Angle(f32),
+    /// Towards a corner, as in CSS `to bottom right`. `x` is -1.0 for left and 1.0 for right,
+    /// `y` is -1.0 for top and 1.0 for bottom.
+    Corner { x: f32, y: f32 },
+}
+
 /// Enum representing different types of gradient directions.
 /// It can either be a string describing the direction (e.g., "to right") or explicit coordinates for the gradient direction.
-#[derive(Debug, Clone, Deserialize, PartialEq, JsonSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
 #[serde(untagged)]
 pub enum GradientDirection {
     /// Direction is represented as a string (e.g., "to right").
@@ -100,20 +145,206 @@ impl From<&str> for GradientDirection {
     }
 }
 
+impl Default for GradientDirection {
+    fn default() -> Self {
+        // Same as CSS when `linear-gradient` is given no direction.
+        Self::Direction("to bottom".to_string())
+    }
+}
+
+/// A color in a `ColorMapping`, optionally with its position along the gradient.
+///
+/// Stops without a position are spread evenly between their neighbours, as in CSS.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum ColorStop {
+    /// A color, optionally followed by one or two positions as in CSS, e.g. `"#89b4fa"`,
+    /// `"#89b4fa 30%"` or `"#f38ba8 40% 45%"` (the same color from 40% to 45%).
+    String(String),
+    /// A color with its position, e.g. `{ "color": "#89b4fa", "position": "30%" }`.
+    Object {
+        /// The color of the stop.
+        color: String,
+        /// Where the stop is along the gradient. Spread evenly if not set.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<StopPosition>,
+    },
+}
+
+impl From<&str> for ColorStop {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_string())
+    }
+}
+
+/// The position of a color stop along the gradient.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum StopPosition {
+    /// A fraction from 0.0 (the start) to 1.0 (the end).
+    Fraction(f32),
+    /// A percentage from `"0%"` to `"100%"`.
+    Percentage(String),
+}
+
+/// The kind of gradient a `ColorMapping` describes, named after the CSS functions.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GradientKind {
+    /// Colors change along a line, set by `direction`.
+    #[default]
+    Linear,
+    /// Colors spread out from `center`, shaped by `shape` and `size`.
+    Radial,
+    /// Colors sweep clockwise around `center`, starting at the angle `from`.
+    Conic,
+}
+
 /// A structure that defines a gradient mapping, which contains a list of color stops and a direction.
-#[derive(Debug, Clone, Deserialize, PartialEq, JsonSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, JsonSchema)]
 pub struct ColorMapping {
-    /// A list of colors in the gradient, represented as hexadecimal color strings.
-    pub colors: Vec<String>,
-    /// The direction of the gradient, represented as a `GradientDirection`.
+    /// The kind of gradient, `linear` if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<GradientKind>,
+    /// A list of colors in the gradient, each optionally with its position.
+    pub colors: Vec<ColorStop>,
+    /// The direction of a linear gradient, represented as a `GradientDirection`.
+    #[serde(default)]
     pub direction: GradientDirection,
+    /// The [x, y] center of a radial or conic gradient, from 0.0 to 1.0. Defaults to the middle.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub center: Option<[f32; 2]>,
+    /// The shape of a radial gradient, `ellipse` if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<RadialShape>,
+    /// How far a radial gradient reaches, `farthest-corner` if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<RadialExtent>,
+    /// The angle in degrees, clockwise from the top, at which a conic gradient starts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<f32>,
+    /// The color space the colors are mixed in, `srgb` if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpolation: Option<ColorSpace>,
 }
 
 impl ColorMapping {
     pub fn new(colors: &[&str], direction: GradientDirection) -> Self {
         Self {
-            colors: colors.iter().map(|&s| s.to_string()).collect(),
+            kind: None,
+            colors: colors.iter().map(|&s| ColorStop::from(s)).collect(),
+            direction,
+            center: None,
+            shape: None,
+            size: None,
+            from: None,
+            interpolation: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(direction: GradientLine) -> Gradient {
+        Gradient {
             direction,
+            gradient_stops: Vec::new(),
+            brush: None,
+        }
+    }
+
+    fn rect(width: i32, height: i32) -> RECT {
+        RECT {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        }
+    }
+
+    /// Returns the start point and the vector from the start to the end point.
+    fn line(direction: GradientLine, width: i32, height: i32) -> ([f32; 2], [f32; 2]) {
+        let (start, end) = gradient(direction).start_end_points(&rect(width, height));
+        ([start.x, start.y], [end.x - start.x, end.y - start.y])
+    }
+
+    /// Returns where a point lies along the gradient, from 0.0 at the start to 1.0 at the end.
+    fn offset(line: ([f32; 2], [f32; 2]), point: [f32; 2]) -> f32 {
+        let ([x, y], [dx, dy]) = line;
+        ((point[0] - x) * dx + (point[1] - y) * dy) / (dx * dx + dy * dy)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {expected} but got {actual}"
+        );
+    }
+
+    #[test]
+    fn angles_are_kept_on_wide_and_tall_windows() {
+        for (width, height) in [(400, 100), (100, 400), (200, 200)] {
+            let (_, [dx, dy]) = line(GradientLine::Angle(45.0), width, height);
+
+            // 45deg points to the top right, whatever the window's proportions.
+            assert!(dx > 0.0 && dy < 0.0);
+            assert_close(dx, -dy);
         }
     }
+
+    #[test]
+    fn angle_gradients_reach_the_corners() {
+        for (width, height) in [(400.0, 100.0), (100.0, 400.0)] {
+            let line = line(GradientLine::Angle(45.0), width as i32, height as i32);
+
+            assert_close(offset(line, [0.0, height]), 0.0);
+            assert_close(offset(line, [width, 0.0]), 1.0);
+            assert_close(offset(line, [width / 2.0, height / 2.0]), 0.5);
+        }
+    }
+
+    #[test]
+    fn corners_put_the_other_corners_on_the_same_color() {
+        let corner = GradientLine::Corner { x: 1.0, y: 1.0 };
+
+        for (width, height) in [(400.0, 100.0), (100.0, 400.0)] {
+            let line = line(corner.clone(), width as i32, height as i32);
+
+            assert_close(offset(line, [0.0, 0.0]), 0.0);
+            assert_close(offset(line, [width, height]), 1.0);
+            assert_close(offset(line, [width, 0.0]), 0.5);
+            assert_close(offset(line, [0.0, height]), 0.5);
+        }
+    }
+
+    #[test]
+    fn sides_run_straight_across() {
+        let cases = [
+            (90.0, [0.0, 50.0], [400.0, 0.0]),
+            (180.0, [200.0, 0.0], [0.0, 100.0]),
+        ];
+
+        for (angle, start, vector) in cases {
+            let (actual_start, actual_vector) = line(GradientLine::Angle(angle), 400, 100);
+
+            for (actual, expected) in actual_start.iter().zip(start) {
+                assert_close(*actual, expected);
+            }
+            for (actual, expected) in actual_vector.iter().zip(vector) {
+                assert_close(*actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn coordinates_are_scaled_by_width_and_height() {
+        let coordinates = GradientLine::Coordinates(GradientCoordinates {
+            start: [0.0, 0.0],
+            end: [1.0, 1.0],
+        });
+
+        assert_eq!(line(coordinates, 400, 100), ([0.0, 0.0], [400.0, 100.0]));
+    }
 }
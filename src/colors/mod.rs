@@ -1,27 +1,45 @@
 #![allow(dead_code)]
+pub mod conic;
 pub mod error;
 pub mod gradient;
 pub mod parser;
+pub mod radial;
 pub mod solid;
+pub mod space;
 
+use conic::ConicGradient;
 use gradient::ColorMapping;
 use gradient::Gradient;
 use parser::parse_color_mapping;
 use parser::parse_color_string;
+use radial::RadialGradient;
+use schema_jsonrs::json_schema;
 use schema_jsonrs::JsonSchema;
+use schema_jsonrs::Schema;
+use schema_jsonrs::SchemaGenerator;
 use serde::Deserialize;
+use serde::Serialize;
 use solid::Solid;
+use std::borrow::Cow;
 use windows::core::Result as WinResult;
 use windows::Foundation::Numerics::Matrix3x2;
 use windows::Win32::Foundation::RECT;
+use windows::Win32::Graphics::Direct2D::Common::D2D1_ALPHA_MODE_PREMULTIPLIED;
 use windows::Win32::Graphics::Direct2D::Common::D2D1_COLOR_F;
+use windows::Win32::Graphics::Direct2D::Common::D2D1_PIXEL_FORMAT;
 use windows::Win32::Graphics::Direct2D::Common::D2D_POINT_2F;
+use windows::Win32::Graphics::Direct2D::Common::D2D_SIZE_U;
 use windows::Win32::Graphics::Direct2D::ID2D1Brush;
 use windows::Win32::Graphics::Direct2D::ID2D1HwndRenderTarget;
+use windows::Win32::Graphics::Direct2D::D2D1_BITMAP_BRUSH_PROPERTIES;
+use windows::Win32::Graphics::Direct2D::D2D1_BITMAP_INTERPOLATION_MODE_LINEAR;
+use windows::Win32::Graphics::Direct2D::D2D1_BITMAP_PROPERTIES;
 use windows::Win32::Graphics::Direct2D::D2D1_BRUSH_PROPERTIES;
 use windows::Win32::Graphics::Direct2D::D2D1_EXTEND_MODE_CLAMP;
 use windows::Win32::Graphics::Direct2D::D2D1_GAMMA_2_2;
 use windows::Win32::Graphics::Direct2D::D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES;
+use windows::Win32::Graphics::Direct2D::D2D1_RADIAL_GRADIENT_BRUSH_PROPERTIES;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
 
 /// The `Color` enum represents different types of colors, including both solid colors and gradients.
 /// It can be either a solid color or a gradient, allowing flexibility in color representation.
@@ -36,11 +54,15 @@ pub enum Color {
     /// A `Gradient` variant stores a color defined by a gradient, which may involve multiple color stops
     /// and a direction (for linear gradients).
     Gradient(Gradient),
+    /// Represents a radial gradient, spreading out from a center point.
+    RadialGradient(RadialGradient),
+    /// Represents a conic gradient, sweeping around a center point.
+    ConicGradient(ConicGradient),
 }
 
 /// The `GlobalColor` enum represents a global color that can be either a color string (e.g., a hex color code or a color name)
 /// or a mapping to a gradient definition.
-#[derive(Debug, Clone, Deserialize, PartialEq, JsonSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(untagged)]
 pub enum GlobalColor {
     /// A string representing a color, which could be a hex color code or a color name.
@@ -54,6 +76,27 @@ pub enum GlobalColor {
     Mapping(ColorMapping),
 }
 
+impl JsonSchema for GlobalColor {
+    fn schema_name() -> Cow<'static, str> {
+        "GlobalColor".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let mapping = generator.subschema_for::<ColorMapping>();
+
+        json_schema!({
+            "description": "A border color: a CSS color or gradient string, or a gradient mapping.",
+            "anyOf": [
+                {
+                    "type": "string",
+                    "description": "A CSS color such as '#89b4fa', 'rgb(137 180 250)', 'hsl(217 92% 76%)' or 'blue', a theme color name, a color function such as 'mix(#89b4fa, #1e1e2e, 30%)', 'lighten(...)', 'darken(...)', 'alpha(...)' or 'complement(...)', or a CSS 'linear-gradient(...)', 'radial-gradient(...)' or 'conic-gradient(...)'."
+                },
+                mapping
+            ]
+        })
+    }
+}
+
 impl Default for GlobalColor {
     fn default() -> Self {
         // The default global color is black (`#000000`).
@@ -167,44 +210,22 @@ impl ColorImpl for Color {
     }
 
     fn set_opacity(&self, opacity: f32) {
-        match self {
-            Color::Gradient(gradient) => {
-                if let Some(ref id2d1_brush) = gradient.brush {
-                    unsafe { id2d1_brush.SetOpacity(opacity) }
-                }
-            }
-            Color::Solid(solid) => {
-                if let Some(ref id2d1_brush) = solid.brush {
-                    unsafe { id2d1_brush.SetOpacity(opacity) }
-                }
-            }
+        if let Some(id2d1_brush) = self.get_brush() {
+            unsafe { id2d1_brush.SetOpacity(opacity) }
         }
     }
 
     fn get_opacity(&self) -> Option<f32> {
-        match self {
-            Color::Solid(solid) => solid
-                .brush
-                .as_ref()
-                .map(|id2d1_brush| unsafe { id2d1_brush.GetOpacity() }),
-            Color::Gradient(gradient) => gradient
-                .brush
-                .as_ref()
-                .map(|id2d1_brush| unsafe { id2d1_brush.GetOpacity() }),
-        }
+        self.get_brush()
+            .map(|id2d1_brush| unsafe { id2d1_brush.GetOpacity() })
     }
 
     fn set_transform(&self, transform: &Matrix3x2) {
         match self {
-            Color::Solid(solid) => {
-                if let Some(ref id2d1_brush) = solid.brush {
-                    unsafe {
-                        id2d1_brush.SetTransform(transform);
-                    }
-                }
-            }
-            Color::Gradient(gradient) => {
-                if let Some(ref id2d1_brush) = gradient.brush {
+            // The conic gradient's brush also holds its placement, so it combines the two.
+            Color::ConicGradient(conic) => conic.set_transform(transform),
+            _ => {
+                if let Some(id2d1_brush) = self.get_brush() {
                     unsafe {
                         id2d1_brush.SetTransform(transform);
                     }
@@ -220,6 +241,12 @@ impl ColorImpl for Color {
                 .brush
                 .as_ref()
                 .map(|id2d1_brush| id2d1_brush.into()),
+            Color::RadialGradient(radial) => {
+                radial.brush.as_ref().map(|id2d1_brush| id2d1_brush.into())
+            }
+            Color::ConicGradient(conic) => {
+                conic.brush.as_ref().map(|id2d1_brush| id2d1_brush.into())
+            }
         }
     }
 
@@ -241,18 +268,11 @@ impl ColorImpl for Color {
                 Ok(())
             },
             Color::Gradient(gradient) => unsafe {
-                let width = (window_rect.right - window_rect.left) as f32;
-                let height = (window_rect.bottom - window_rect.top) as f32;
+                let (start_point, end_point) = gradient.start_end_points(window_rect);
 
                 let gradient_properties = D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
-                    startPoint: D2D_POINT_2F {
-                        x: gradient.direction.start[0] * width,
-                        y: gradient.direction.start[1] * height,
-                    },
-                    endPoint: D2D_POINT_2F {
-                        x: gradient.direction.end[0] * width,
-                        y: gradient.direction.end[1] * height,
-                    },
+                    startPoint: start_point,
+                    endPoint: end_point,
                 };
 
                 let gradient_stop_collection = render_target.CreateGradientStopCollection(
@@ -272,6 +292,126 @@ impl ColorImpl for Color {
 
                 Ok(())
             },
+            Color::RadialGradient(radial) => unsafe {
+                let (radius_x, radius_y) = radial.radii(window_rect);
+
+                let gradient_properties = D2D1_RADIAL_GRADIENT_BRUSH_PROPERTIES {
+                    center: radial.center_point(window_rect),
+                    gradientOriginOffset: D2D_POINT_2F::default(),
+                    radiusX: radius_x,
+                    radiusY: radius_y,
+                };
+
+                let gradient_stop_collection = render_target.CreateGradientStopCollection(
+                    &radial.gradient_stops,
+                    D2D1_GAMMA_2_2,
+                    D2D1_EXTEND_MODE_CLAMP,
+                )?;
+
+                let id2d1_brush = render_target.CreateRadialGradientBrush(
+                    &gradient_properties,
+                    Some(brush_properties),
+                    &gradient_stop_collection,
+                )?;
+
+                id2d1_brush.SetOpacity(0.0);
+                radial.brush = Some(id2d1_brush);
+
+                Ok(())
+            },
+            Color::ConicGradient(conic) => unsafe {
+                let size = ConicGradient::BITMAP_SIZE;
+                let pixels = conic.pixels();
+
+                let bitmap_properties = D2D1_BITMAP_PROPERTIES {
+                    pixelFormat: D2D1_PIXEL_FORMAT {
+                        format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                        alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+                    },
+                    dpiX: 96.0,
+                    dpiY: 96.0,
+                };
+
+                let bitmap = render_target.CreateBitmap(
+                    D2D_SIZE_U {
+                        width: size,
+                        height: size,
+                    },
+                    Some(pixels.as_ptr() as *const _),
+                    size * 4,
+                    &bitmap_properties,
+                )?;
+
+                let bitmap_brush_properties = D2D1_BITMAP_BRUSH_PROPERTIES {
+                    extendModeX: D2D1_EXTEND_MODE_CLAMP,
+                    extendModeY: D2D1_EXTEND_MODE_CLAMP,
+                    interpolationMode: D2D1_BITMAP_INTERPOLATION_MODE_LINEAR,
+                };
+
+                let id2d1_brush = render_target.CreateBitmapBrush(
+                    &bitmap,
+                    Some(&bitmap_brush_properties),
+                    Some(brush_properties),
+                )?;
+
+                id2d1_brush.SetOpacity(0.0);
+                conic.brush = Some(id2d1_brush);
+                conic.update_placement(window_rect);
+
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Color {
+    /// Fits the brush to the window's current size, which only gradients depend on.
+    pub fn update_brush_geometry(&self, window_rect: &RECT) {
+        match self {
+            Color::Solid(_) => {}
+            Color::Gradient(gradient) => gradient.update_start_end_points(window_rect),
+            Color::RadialGradient(radial) => radial.update_center_radius(window_rect),
+            Color::ConicGradient(conic) => conic.update_placement(window_rect),
+        }
+    }
+
+    /// Returns the color of a solid color, or `None` for gradients.
+    pub fn solid_color(&self) -> Option<D2D1_COLOR_F> {
+        match self {
+            Color::Solid(solid) => Some(solid.color),
+            _ => None,
+        }
+    }
+
+    /// Paints the brush of a solid color in `color` without changing the color itself, e.g.
+    /// while an animation passes through the colors in between. Does nothing for gradients.
+    pub fn set_brush_color(&self, color: &D2D1_COLOR_F) {
+        if let Color::Solid(Solid {
+            brush: Some(ref id2d1_brush),
+            ..
+        }) = self
+        {
+            unsafe { id2d1_brush.SetColor(color) }
+        }
+    }
+
+    /// Paints the brush of a solid color in its own color again, see `set_brush_color`.
+    pub fn reset_brush_color(&self) {
+        if let Color::Solid(solid) = self {
+            self.set_brush_color(&solid.color);
+        }
+    }
+
+    /// Returns the point the color turns around when rotated: the center of a radial or conic
+    /// gradient, or else the middle of the window.
+    pub fn center(&self, window_rect: &RECT) -> D2D_POINT_2F {
+        match self {
+            Color::RadialGradient(radial) => radial.center_point(window_rect),
+            Color::ConicGradient(conic) => conic.center_point(window_rect),
+            _ => D2D_POINT_2F {
+                x: (window_rect.right - window_rect.left) as f32 / 2.0,
+                y: (window_rect.bottom - window_rect.top) as f32 / 2.0,
+            },
         }
     }
 }
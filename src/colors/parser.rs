@@ -9,15 +9,28 @@ use windows::Win32::Graphics::Direct2D::Common::D2D1_GRADIENT_STOP;
 use crate::core::app_state::APP_STATE;
 use crate::user_config::UserConfig;
 
+use super::conic::ConicGradient;
 use super::error::Error;
 use super::error::ErrorKind;
 use super::error::Result;
 use super::gradient::ColorMapping;
+use super::gradient::ColorStop;
 use super::gradient::Gradient;
 use super::gradient::GradientDirection;
+use super::gradient::GradientKind;
+use super::gradient::GradientLine;
+use super::gradient::StopPosition;
+use super::radial::RadialExtent;
+use super::radial::RadialGradient;
+use super::radial::RadialShape;
 use super::solid::Solid;
+use super::space::ColorSpace;
 use super::Color;
 use colorparser_css::GradientCoordinates;
+use std::path::PathBuf;
+
+/// Center of radial and conic gradients that don't set one: the middle of the window.
+const DEFAULT_CENTER: [f32; 2] = [0.5, 0.5];
 
 /// Parses a `ColorMapping` into a `Color`.
 ///
@@ -35,75 +48,258 @@ use colorparser_css::GradientCoordinates;
 ///
 /// ```rust
 /// let mapping = ColorMapping {
-///     colors: vec!["#FF0000".to_string()],
+///     colors: vec!["#FF0000".into()],
 ///     direction: GradientCoordinates::String("90deg".to_string())
 /// };
 /// let color = parse_color_mapping(mapping, Some(false))?;
 /// ```
 pub fn parse_color_mapping(s: ColorMapping) -> anyhow::Result<Color> {
+    parse_color_mapping_with_theme(s, current_theme().as_deref())
+}
+
+/// Parses a `ColorMapping` into a `Color`, resolving theme colors from the given theme.
+///
+/// Like [`parse_color_string_with_theme`], this does not read the live configuration.
+pub fn parse_color_mapping_with_theme(
+    s: ColorMapping,
+    theme: Option<&str>,
+) -> anyhow::Result<Color> {
     match s.colors.len() {
         0 => Ok(Color::Solid(Solid {
             color: D2D1_COLOR_F::default(),
             brush: None,
         })),
         1 => {
-            let result = parse_color_string(&s.colors[0])?;
+            let (color, _) = color_stop_parts(&s.colors[0])?;
+            let result = parse_color_string_with_theme(color, theme)?;
             Ok(result)
         }
         _ => {
-            let gradient_stops = generate_gradient_stops(&s.colors)?;
+            let gradient_stops = generate_gradient_stops(&s.colors, theme)?;
+            let gradient_stops = s
+                .interpolation
+                .unwrap_or_default()
+                .interpolate_stops(gradient_stops);
+            let center = s.center.unwrap_or(DEFAULT_CENTER);
 
-            if gradient_stops.is_empty() {
-                return Err(anyhow!("invalid color mapping: no valid colors found"));
-            }
+            match s.kind.unwrap_or_default() {
+                GradientKind::Linear => {
+                    let direction = parse_gradient_direction(&s.direction)?;
 
-            let direction = parse_gradient_direction(&s.direction)?;
-
-            Ok(Color::Gradient(Gradient {
-                gradient_stops,
-                direction,
-                brush: None,
-            }))
+                    Ok(Color::Gradient(Gradient {
+                        gradient_stops,
+                        direction,
+                        brush: None,
+                    }))
+                }
+                GradientKind::Radial => Ok(Color::RadialGradient(RadialGradient {
+                    shape: s.shape.unwrap_or_default(),
+                    extent: s.size.unwrap_or_default(),
+                    center,
+                    gradient_stops,
+                    brush: None,
+                })),
+                GradientKind::Conic => Ok(Color::ConicGradient(ConicGradient::new(
+                    s.from.unwrap_or(0.0),
+                    center,
+                    gradient_stops,
+                ))),
+            }
         }
     }
 }
 
-/// Generates gradient stops from a list of color strings.
+/// Generates gradient stops from the colors of a `ColorMapping`.
 ///
 /// # Arguments
 ///
-/// - `colors`: A slice of strings representing color values in CSS-compatible format.
+/// - `colors`: The color stops, each a CSS-compatible color with optional positions.
+/// - `theme`: The theme to resolve theme colors from.
 ///
 /// # Returns
 ///
 /// - `Ok(Vec<D2D1_GRADIENT_STOP>)`: A vector of gradient stops for use with Direct2D.
-/// - `Err(Error)`: An error if color parsing fails.
+/// - `Err(Error)`: An error if a color or position is invalid.
 ///
 /// # Examples
 ///
 /// ```rust
-/// let stops = generate_gradient_stops(&vec!["#FF0000".to_string(), "#00FF00".to_string()])?;
+/// let stops = generate_gradient_stops(&["#FF0000".into(), "#00FF00 80%".into()], None)?;
 /// ```
-fn generate_gradient_stops(colors: &[String]) -> Result<Vec<D2D1_GRADIENT_STOP>> {
-    let num_colors = colors.len();
-    let step = 1.0 / (num_colors - 1) as f32;
-
-    let stops: Vec<D2D1_GRADIENT_STOP> = colors
+pub fn generate_gradient_stops(
+    colors: &[ColorStop],
+    theme: Option<&str>,
+) -> anyhow::Result<Vec<D2D1_GRADIENT_STOP>> {
+    let stops = colors
         .iter()
-        .enumerate()
-        .filter_map(|(i, hex)| match parse_color_string(hex).ok()? {
-            Color::Solid(solid) => Some(D2D1_GRADIENT_STOP {
-                position: i as f32 * step,
-                color: solid.color,
-            }),
-            _ => None, // Skip invalid colors
+        .map(|stop| {
+            let (color, positions) = color_stop_parts(stop)?;
+            Ok((parse_stop_color(color, theme)?, positions))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(place_gradient_stops(stops)?)
+}
+
+/// Splits a `ColorMapping` color stop into its color and positions (from 0.0 to 1.0).
+pub fn color_stop_parts(stop: &ColorStop) -> Result<(&str, Vec<f32>)> {
+    match stop {
+        ColorStop::String(s) => {
+            let (color, positions) = split_color_stop(s);
+            let positions = positions
+                .into_iter()
+                .map(parse_stop_position)
+                .collect::<Result<Vec<_>>>()?;
+            Ok((color, positions))
+        }
+        ColorStop::Object { color, position } => {
+            let position = match position {
+                None => None,
+                Some(StopPosition::Fraction(fraction)) => Some(*fraction),
+                Some(StopPosition::Percentage(percentage)) => {
+                    Some(parse_stop_position(percentage)?)
+                }
+            };
+            Ok((color.as_str(), position.into_iter().collect()))
+        }
+    }
+}
+
+/// Splits up to two trailing percentages off a color stop as in CSS, e.g. `"#89b4fa 30%"`
+/// into `("#89b4fa", ["30%"])`.
+fn split_color_stop(s: &str) -> (&str, Vec<&str>) {
+    let is_percentage = |token: &str| {
+        token
+            .strip_suffix('%')
+            .is_some_and(|number| number.parse::<f32>().is_ok())
+    };
+
+    let mut color = s.trim();
+    let mut positions = Vec::new();
+    while positions.len() < 2 {
+        match color.rsplit_once(char::is_whitespace) {
+            Some((rest, last)) if is_percentage(last) => {
+                positions.insert(0, last);
+                color = rest.trim_end();
+            }
+            _ => break,
+        }
+    }
+
+    (color, positions)
+}
+
+/// Parses a stop position such as `30%` into a fraction.
+fn parse_stop_position(s: &str) -> Result<f32> {
+    s.trim()
+        .strip_suffix('%')
+        .and_then(|number| number.trim().parse::<f32>().ok())
+        .filter(|number| number.is_finite())
+        .map(|percentage| percentage / 100.0)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid stop position `{s}`; expected a percentage such as '30%'"),
+            )
+        })
+}
+
+/// Parses the color of a gradient stop, which has to be a solid color.
+fn parse_stop_color(s: &str, theme: Option<&str>) -> anyhow::Result<D2D1_COLOR_F> {
+    match parse_color_string_with_theme(s, theme)? {
+        Color::Solid(solid) => Ok(solid.color),
+        _ => Err(anyhow!(
+            "expected a solid color in the gradient but found `{s}`"
+        )),
+    }
+}
+
+/// Turns colors with their positions into gradient stops. A color with two positions covers
+/// the range between them, and stops without one are placed as described in
+/// [`resolve_stop_positions`].
+fn place_gradient_stops(stops: Vec<(D2D1_COLOR_F, Vec<f32>)>) -> Result<Vec<D2D1_GRADIENT_STOP>> {
+    let stops: Vec<(D2D1_COLOR_F, Option<f32>)> = stops
+        .into_iter()
+        .flat_map(|(color, positions)| match positions.is_empty() {
+            true => vec![(color, None)],
+            false => positions
+                .into_iter()
+                .map(|position| (color, Some(position)))
+                .collect(),
         })
         .collect();
 
-    Ok(stops)
+    let positions: Vec<Option<f32>> = stops.iter().map(|(_, position)| *position).collect();
+    let positions = resolve_stop_positions(&positions)?;
+
+    Ok(stops
+        .into_iter()
+        .zip(positions)
+        .map(|((color, _), position)| D2D1_GRADIENT_STOP { position, color })
+        .collect())
+}
+
+/// Fills in the positions of stops that don't set one, as in CSS: the first stop defaults to
+/// 0.0, the last one to 1.0, and the others are spread evenly between their neighbours.
+///
+/// Set positions must lie within 0.0 and 1.0 and must not decrease.
+pub fn resolve_stop_positions(positions: &[Option<f32>]) -> Result<Vec<f32>> {
+    let percentage = |position: f32| format!("{}%", (position * 10000.0).round() / 100.0);
+
+    let mut previous: Option<f32> = None;
+    for &position in positions.iter().flatten() {
+        if !(0.0..=1.0).contains(&position) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "stop position {} is outside of 0% to 100%",
+                    percentage(position)
+                ),
+            ));
+        }
+        if let Some(previous) = previous.filter(|&previous| position < previous) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "stop positions must not decrease, but {} comes after {}",
+                    percentage(position),
+                    percentage(previous)
+                ),
+            ));
+        }
+        previous = Some(position);
+    }
+
+    let mut resolved: Vec<Option<f32>> = positions.to_vec();
+    if let Some(first) = resolved.first_mut() {
+        first.get_or_insert(0.0);
+    }
+    if let Some(last) = resolved.last_mut() {
+        last.get_or_insert(1.0);
+    }
+
+    // Spread every run of unset positions evenly between the set ones around it.
+    let mut start = 0;
+    for end in 1..resolved.len() {
+        let Some(end_position) = resolved[end] else {
+            continue;
+        };
+        let start_position = resolved[start].unwrap_or_default();
+        let step = (end_position - start_position) / (end - start) as f32;
+
+        for (offset, position) in resolved[start + 1..end].iter_mut().enumerate() {
+            *position = Some(start_position + step * (offset + 1) as f32);
+        }
+        start = end;
+    }
+
+    Ok(resolved.into_iter().flatten().collect())
 }
 
-/// Parses a gradient direction into `GradientCoordinates`.
+/// Parses a gradient direction into a `GradientLine`.
+///
+/// Angles (`135deg`) and keywords (`to right`, `to bottom right`) are kept as angles, so they
+/// look the same on any window; coordinates are scaled by the window's width and height.
 ///
 /// # Arguments
 ///
@@ -111,32 +307,86 @@ fn generate_gradient_stops(colors: &[String]) -> Result<Vec<D2D1_GRADIENT_STOP>>
 ///
 /// # Returns
 ///
-/// - `Ok(GradientCoordinates)`: A valid gradient coordinate mapping.
+/// - `Ok(GradientLine)`: A valid gradient line.
 /// - `Err(Error)`: An error if the direction is invalid.
 ///
 /// # Examples
 ///
 /// ```rust
 /// let direction = GradientDirection::Direction("90deg".to_string());
-/// let coordinates = parse_gradient_direction(&direction)?;
+/// let line = parse_gradient_direction(&direction)?;
 /// ```
-fn parse_gradient_direction(direction: &GradientDirection) -> Result<GradientCoordinates> {
+pub fn parse_gradient_direction(direction: &GradientDirection) -> Result<GradientLine> {
     match direction {
         GradientDirection::Direction(dir) => {
-            GradientCoordinates::try_from(dir.as_str()).map_err(|e| {
-                Error::new(
-                    ErrorKind::InvalidData,
-                    format!("Invalid gradient direction: {}", e),
-                )
-            })
+            if let Some(line) = parse_direction_keywords(dir)? {
+                return Ok(line);
+            }
+            if let Ok(angle) = parse_angle(dir) {
+                return Ok(GradientLine::Angle(angle));
+            }
+
+            GradientCoordinates::try_from(dir.as_str())
+                .map(GradientLine::Coordinates)
+                .map_err(|e| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Invalid gradient direction: {}", e),
+                    )
+                })
         }
-        GradientDirection::Coordinates(coords) => Ok(coords.clone()),
+        GradientDirection::Coordinates(coords) => Ok(GradientLine::Coordinates(coords.clone())),
     }
 }
 
+/// Parses a CSS direction such as `to right` or `to bottom right`, or returns `None` if the
+/// direction doesn't start with `to`.
+fn parse_direction_keywords(direction: &str) -> Result<Option<GradientLine>> {
+    let direction = direction.trim().to_lowercase();
+    let mut tokens = direction.split_whitespace();
+    if tokens.next() != Some("to") {
+        return Ok(None);
+    }
+
+    let invalid = || {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "invalid gradient direction `{direction}`; expected e.g. 'to right' or 'to bottom left'"
+            ),
+        )
+    };
+
+    let (mut x, mut y) = (None, None);
+    for token in tokens {
+        let (axis, value) = match token {
+            "left" => (&mut x, -1.0),
+            "right" => (&mut x, 1.0),
+            "top" => (&mut y, -1.0),
+            "bottom" => (&mut y, 1.0),
+            _ => return Err(invalid()),
+        };
+
+        // Each axis can only be given once, e.g. not `to left right`.
+        if axis.replace(value).is_some() {
+            return Err(invalid());
+        }
+    }
+
+    let line = match (x, y) {
+        (Some(x), Some(y)) => GradientLine::Corner { x, y },
+        (Some(x), None) => GradientLine::Angle(90.0 * x),
+        (None, Some(y)) => GradientLine::Angle(90.0 + 90.0 * y),
+        (None, None) => return Err(invalid()),
+    };
+
+    Ok(Some(line))
+}
+
 /// Parses a CSS color string into a `Color`.
 ///
-/// This function supports solid colors and gradients in CSS-compatible formats.
+/// This function supports solid colors and gradients in CSS-compatible formats, as well as the
+/// color functions described in [`evaluate_color_function`].
 ///
 /// # Arguments
 ///
@@ -153,19 +403,65 @@ fn parse_gradient_direction(direction: &GradientDirection) -> Result<GradientCoo
 /// let color = parse_color_string("#FF0000")?;
 /// ```
 pub fn parse_color_string(s: &str) -> anyhow::Result<Color> {
-    let theme = match APP_STATE.config.read() {
+    parse_color_string_with_theme(s, current_theme().as_deref())
+}
+
+/// Returns the theme of the live configuration.
+fn current_theme() -> Option<String> {
+    match APP_STATE.config.read() {
         Ok(config) => config.theme.clone(),
         Err(_) => None,
+    }
+}
+
+/// Returns the path of the file that defines the given theme.
+///
+/// This only resolves the path and never creates the config directory, so validating and
+/// linting a config has no side effects.
+pub fn theme_file(theme: &str) -> anyhow::Result<PathBuf> {
+    let themes_dir = UserConfig::get_config_location()?.dir.join("themes");
+    Ok(themes_dir.join(theme).with_extension("jsonc"))
+}
+
+/// Parses a CSS color string into a `Color`, resolving theme colors from the given theme.
+///
+/// Unlike [`parse_color_string`], this does not read the live configuration, so it can be
+/// used to validate a configuration before it is applied.
+pub fn parse_color_string_with_theme(s: &str, theme: Option<&str>) -> anyhow::Result<Color> {
+    // Color functions are evaluated down to the plain colors in their arguments, which are
+    // parsed like any other color, theme colors included.
+    let parse_argument = |arg: &str| match parse_color_string_with_theme(arg, theme) {
+        Ok(Color::Solid(solid)) => Ok(solid.color),
+        Ok(_) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("expected a solid color in `{s}` but found `{arg}`"),
+        )),
+        Err(e) => Err(e
+            .downcast::<Error>()
+            .unwrap_or_else(|e| Error::new(ErrorKind::InvalidInput, e.to_string()))),
     };
+    if let Some(color) = evaluate_color_function(s, &parse_argument) {
+        return Ok(Color::Solid(Solid {
+            color: color?,
+            brush: None,
+        }));
+    }
+
+    // Gradients are parsed here, colors and all, since the CSS parser spaces their stops evenly
+    // and only knows linear ones.
+    if let Some(args) = function_args(s, "linear-gradient") {
+        return parse_linear_gradient(&args, theme);
+    }
+    if let Some(args) = function_args(s, "radial-gradient") {
+        return parse_radial_gradient(&args, theme);
+    }
+    if let Some(args) = function_args(s, "conic-gradient") {
+        return parse_conic_gradient(&args, theme);
+    }
 
     let css_color = match theme {
         Some(theme) => {
-            let config_dir = UserConfig::get_config_dir().unwrap().join("themes");
-            let theme_file = config_dir
-                .join(theme)
-                .with_extension("jsonc")
-                .to_string_lossy()
-                .to_string();
+            let theme_file = theme_file(theme)?.to_string_lossy().to_string();
             CssColor::from_html_with_theme(s, &theme_file).map_err(|e| {
                 Error::new(
                     ErrorKind::InvalidInput,
@@ -265,8 +561,825 @@ fn parse_gradient(css_color: &CssColor) -> anyhow::Result<Color> {
     };
 
     Ok(Color::Gradient(Gradient {
-        direction,
+        direction: GradientLine::Coordinates(direction),
         gradient_stops,
         brush: None,
     }))
 }
+
+/// The color functions [`evaluate_color_function`] knows, with how they are called.
+const COLOR_FUNCTIONS: [(&str, &str); 5] = [
+    ("mix", "mix(<color>, <color>[, <amount>[, <color space>]])"),
+    ("lighten", "lighten(<color>, <amount>)"),
+    ("darken", "darken(<color>, <amount>)"),
+    ("alpha", "alpha(<color>, <amount>)"),
+    ("complement", "complement(<color>)"),
+];
+
+/// Evaluates a color function, or returns `None` if `s` is not one:
+///
+/// - `mix(a, b, 30%)`: 70% of `a` and 30% of `b`, half of each if no amount is given. A color
+///   space such as `oklch` can follow the amount, sRGB is used otherwise.
+/// - `lighten(color, 10%)` and `darken(color, 10%)`: raises or lowers the OKLab lightness.
+/// - `alpha(color, 0.5)`: sets the opacity.
+/// - `complement(color)`: turns the OKLCH hue halfway around.
+///
+/// Amounts are percentages or fractions from 0.0 to 1.0. Functions nest freely; any other color
+/// in the arguments is handed to `parse_color`, so the evaluation itself only depends on `s`.
+pub fn evaluate_color_function(
+    s: &str,
+    parse_color: &dyn Fn(&str) -> Result<D2D1_COLOR_F>,
+) -> Option<Result<D2D1_COLOR_F>> {
+    COLOR_FUNCTIONS.iter().find_map(|&(name, usage)| {
+        let args = function_args(s, name)?;
+        Some(apply_color_function(name, usage, &args, parse_color))
+    })
+}
+
+/// Applies the color function `name` to its arguments, see [`evaluate_color_function`].
+fn apply_color_function(
+    name: &str,
+    usage: &str,
+    args: &[&str],
+    parse_color: &dyn Fn(&str) -> Result<D2D1_COLOR_F>,
+) -> Result<D2D1_COLOR_F> {
+    let color =
+        |arg: &str| evaluate_color_function(arg, parse_color).unwrap_or_else(|| parse_color(arg));
+
+    match (name, args) {
+        ("mix", [first, second]) => {
+            Ok(ColorSpace::default().mix(&color(first)?, &color(second)?, 0.5))
+        }
+        ("mix", [first, second, amount]) => {
+            Ok(ColorSpace::default().mix(&color(first)?, &color(second)?, parse_amount(amount)?))
+        }
+        ("mix", [first, second, amount, space]) => {
+            let space = space
+                .parse::<ColorSpace>()
+                .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+            Ok(space.mix(&color(first)?, &color(second)?, parse_amount(amount)?))
+        }
+        ("lighten", [base, amount]) => Ok(shift_lightness(&color(base)?, parse_amount(amount)?)),
+        ("darken", [base, amount]) => Ok(shift_lightness(&color(base)?, -parse_amount(amount)?)),
+        ("alpha", [base, amount]) => Ok(D2D1_COLOR_F {
+            a: parse_amount(amount)?,
+            ..color(base)?
+        }),
+        ("complement", [base]) => {
+            let base = color(base)?;
+            let [lightness, chroma, hue] = ColorSpace::Oklch.to_components(&base);
+            Ok(ColorSpace::Oklch.to_color([lightness, chroma, hue + 180.0], base.a))
+        }
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "wrong number of arguments in `{name}({})`; expected {usage}",
+                args.join(", ")
+            ),
+        )),
+    }
+}
+
+/// Raises (or, if negative, lowers) the OKLab lightness of a color by `amount`.
+fn shift_lightness(color: &D2D1_COLOR_F, amount: f32) -> D2D1_COLOR_F {
+    let [lightness, a, b] = ColorSpace::Oklab.to_components(color);
+    let lightness = (lightness + amount).clamp(0.0, 1.0);
+
+    ColorSpace::Oklab.to_color([lightness, a, b], color.a)
+}
+
+/// Parses the amount of a color function, a percentage such as `30%` or a fraction such as
+/// `0.3`, into a fraction from 0.0 to 1.0.
+fn parse_amount(s: &str) -> Result<f32> {
+    let s = s.trim();
+    let amount = match s.strip_suffix('%') {
+        Some(percentage) => percentage.trim().parse::<f32>().map(|value| value / 100.0),
+        None => s.parse::<f32>(),
+    }
+    .ok()
+    .filter(|amount| amount.is_finite())
+    .ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid amount `{s}`; expected a percentage such as '30%' or a fraction such as '0.3'"),
+        )
+    })?;
+
+    match (0.0..=1.0).contains(&amount) {
+        true => Ok(amount),
+        false => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("amount `{s}` is outside of 0% to 100%"),
+        )),
+    }
+}
+
+/// Splits a CSS function call such as `radial-gradient(circle, red, blue)` into its arguments,
+/// or returns `None` if `s` is not a call to `name`.
+fn function_args<'a>(s: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let s = s.trim();
+    let prefix = s.get(..name.len() + 1)?;
+    if !prefix[..name.len()].eq_ignore_ascii_case(name) || !prefix.ends_with('(') {
+        return None;
+    }
+    let inner = s[name.len() + 1..].strip_suffix(')')?;
+
+    // Colors like `rgb(0, 0, 0)` contain commas of their own.
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(inner[start..].trim());
+
+    Some(args)
+}
+
+/// Parses the arguments of `linear-gradient(...)`: an optional direction such as `to right` or
+/// `90deg` and color space such as `in oklch`, followed by the colors.
+fn parse_linear_gradient(args: &[&str], theme: Option<&str>) -> anyhow::Result<Color> {
+    let mut direction = GradientDirection::default();
+    let mut interpolation = None;
+    let mut colors = args;
+
+    if let Some((first, rest)) = args.split_first() {
+        let mut tokens = lowercase_tokens(first);
+        let found = take_interpolation(&mut tokens)?;
+        let remaining = tokens.join(" ");
+
+        if found.is_some() || remaining.starts_with("to ") || parse_angle(&remaining).is_ok() {
+            if !remaining.is_empty() {
+                direction = GradientDirection::from(remaining.as_str());
+            }
+            interpolation = found;
+            colors = rest;
+        }
+    }
+
+    let gradient_stops = parse_gradient_colors(colors, theme)?;
+
+    Ok(Color::Gradient(Gradient {
+        direction: parse_gradient_direction(&direction)?,
+        gradient_stops: interpolation
+            .unwrap_or_default()
+            .interpolate_stops(gradient_stops),
+        brush: None,
+    }))
+}
+
+/// Parses the arguments of `radial-gradient(...)`: an optional `[<shape> || <size>] [at
+/// <position>] [in <color space>]`, followed by the colors.
+fn parse_radial_gradient(args: &[&str], theme: Option<&str>) -> anyhow::Result<Color> {
+    const KEYWORDS: [&str; 8] = [
+        "circle",
+        "ellipse",
+        "closest-side",
+        "farthest-side",
+        "closest-corner",
+        "farthest-corner",
+        "at",
+        "in",
+    ];
+
+    let mut shape = RadialShape::default();
+    let mut extent = RadialExtent::default();
+    let mut center = DEFAULT_CENTER;
+    let mut interpolation = None;
+
+    let colors = match split_prelude(args, &KEYWORDS) {
+        Some((mut prelude, colors)) => {
+            interpolation = take_interpolation(&mut prelude)?;
+            let (shape_size, position) = split_at_position(&prelude);
+
+            for token in shape_size {
+                if let Ok(parsed) = token.parse::<RadialShape>() {
+                    shape = parsed;
+                } else {
+                    extent = token
+                        .parse::<RadialExtent>()
+                        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+                }
+            }
+            if let Some(position) = position {
+                center = parse_position(&position)?;
+            }
+
+            colors
+        }
+        None => args,
+    };
+
+    let gradient_stops = parse_gradient_colors(colors, theme)?;
+
+    Ok(Color::RadialGradient(RadialGradient {
+        shape,
+        extent,
+        center,
+        gradient_stops: interpolation
+            .unwrap_or_default()
+            .interpolate_stops(gradient_stops),
+        brush: None,
+    }))
+}
+
+/// Parses the arguments of `conic-gradient(...)`: an optional `[from <angle>] [at <position>]
+/// [in <color space>]`, followed by the colors.
+fn parse_conic_gradient(args: &[&str], theme: Option<&str>) -> anyhow::Result<Color> {
+    let mut from = 0.0;
+    let mut center = DEFAULT_CENTER;
+    let mut interpolation = None;
+
+    let colors = match split_prelude(args, &["from", "at", "in"]) {
+        Some((mut prelude, colors)) => {
+            interpolation = take_interpolation(&mut prelude)?;
+            let (from_tokens, position) = split_at_position(&prelude);
+
+            match from_tokens.as_slice() {
+                [] => {}
+                [keyword, angle] if keyword == "from" => from = parse_angle(angle)?,
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "expected `from <angle>` but found `{}`",
+                            from_tokens.join(" ")
+                        ),
+                    )
+                    .into())
+                }
+            }
+            if let Some(position) = position {
+                center = parse_position(&position)?;
+            }
+
+            colors
+        }
+        None => args,
+    };
+
+    let gradient_stops = parse_gradient_colors(colors, theme)?;
+
+    Ok(Color::ConicGradient(ConicGradient::new(
+        from,
+        center,
+        interpolation
+            .unwrap_or_default()
+            .interpolate_stops(gradient_stops),
+    )))
+}
+
+/// Splits off the first argument if it starts with one of `keywords`, returning its lowercased
+/// tokens and the remaining arguments.
+fn split_prelude<'a, 'b>(
+    args: &'a [&'b str],
+    keywords: &[&str],
+) -> Option<(Vec<String>, &'a [&'b str])> {
+    let (first, rest) = args.split_first()?;
+    let tokens = lowercase_tokens(first);
+
+    tokens
+        .first()
+        .is_some_and(|token| keywords.contains(&token.as_str()))
+        .then_some((tokens, rest))
+}
+
+/// Splits prelude tokens into the ones before `at` and the position after it, if any.
+fn split_at_position(tokens: &[String]) -> (Vec<String>, Option<Vec<String>>) {
+    match tokens.iter().position(|token| token == "at") {
+        Some(i) => (tokens[..i].to_vec(), Some(tokens[i + 1..].to_vec())),
+        None => (tokens.to_vec(), None),
+    }
+}
+
+/// Splits a string into lowercased, whitespace-separated tokens.
+fn lowercase_tokens(s: &str) -> Vec<String> {
+    s.split_whitespace()
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+/// Removes `in <color space>` from prelude tokens, returning the color space if there was one.
+fn take_interpolation(tokens: &mut Vec<String>) -> Result<Option<ColorSpace>> {
+    let Some(i) = tokens.iter().position(|token| token == "in") else {
+        return Ok(None);
+    };
+
+    let space = tokens
+        .get(i + 1)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "expected a color space after `in`, e.g. 'in oklch'",
+            )
+        })?
+        .parse::<ColorSpace>()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    tokens.drain(i..i + 2);
+
+    Ok(Some(space))
+}
+
+/// Parses the colors of a CSS gradient, each optionally followed by one or two positions.
+fn parse_gradient_colors(
+    colors: &[&str],
+    theme: Option<&str>,
+) -> anyhow::Result<Vec<D2D1_GRADIENT_STOP>> {
+    if colors.len() < 2 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "a gradient needs at least two colors",
+        )
+        .into());
+    }
+
+    let stops = colors
+        .iter()
+        .map(|s| {
+            let (color, positions) = split_color_stop(s);
+            let positions = positions
+                .into_iter()
+                .map(parse_stop_position)
+                .collect::<Result<Vec<_>>>()?;
+            Ok((parse_stop_color(color, theme)?, positions))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(place_gradient_stops(stops)?)
+}
+
+/// Parses a CSS angle such as `45deg`, `0.25turn`, `1.5rad` or `100grad` into degrees.
+pub fn parse_angle(s: &str) -> Result<f32> {
+    const UNITS: [(&str, f32); 4] = [
+        ("deg", 1.0),
+        ("grad", 0.9),
+        ("rad", 180.0 / std::f32::consts::PI),
+        ("turn", 360.0),
+    ];
+
+    let s = s.trim();
+    UNITS
+        .iter()
+        .find_map(|(unit, degrees)| {
+            let value = s.strip_suffix(unit)?.trim().parse::<f32>().ok()?;
+            value.is_finite().then_some(value * degrees)
+        })
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid angle `{s}`; expected e.g. '45deg' or '0.25turn'"),
+            )
+        })
+}
+
+/// Parses a CSS position such as `center`, `top left` or `25% 75%` into [x, y] from 0.0 to 1.0.
+fn parse_position(tokens: &[String]) -> Result<[f32; 2]> {
+    let invalid = || {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "invalid position `{}`; expected e.g. 'center', 'top left' or '25% 75%'",
+                tokens.join(" ")
+            ),
+        )
+    };
+
+    let keyword = |token: &str| match token {
+        "left" | "top" => Some(0.0),
+        "center" => Some(0.5),
+        "right" | "bottom" => Some(1.0),
+        _ => token
+            .strip_suffix('%')
+            .and_then(|percent| percent.parse::<f32>().ok())
+            .filter(|percent| percent.is_finite())
+            .map(|percent| percent / 100.0),
+    };
+    let is_vertical = |token: &str| matches!(token, "top" | "bottom");
+    let is_horizontal = |token: &str| matches!(token, "left" | "right");
+
+    match tokens {
+        [only] if is_vertical(only) => Ok([0.5, keyword(only).ok_or_else(invalid)?]),
+        [only] => Ok([keyword(only).ok_or_else(invalid)?, 0.5]),
+        // Keywords may come in either order, e.g. `top left`.
+        [first, second] if is_vertical(first) || is_horizontal(second) => Ok([
+            keyword(second).ok_or_else(invalid)?,
+            keyword(first).ok_or_else(invalid)?,
+        ]),
+        [first, second] => Ok([
+            keyword(first).ok_or_else(invalid)?,
+            keyword(second).ok_or_else(invalid)?,
+        ]),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `#rrggbb`, standing in for the CSS parser so that only the functions are tested.
+    fn parse_hex(s: &str) -> Result<D2D1_COLOR_F> {
+        let channel = |i: usize| {
+            s.strip_prefix('#')
+                .filter(|hex| hex.len() == 6)
+                .and_then(|hex| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+                .map(|value| value as f32 / 255.0)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("invalid color `{s}`")))
+        };
+
+        Ok(D2D1_COLOR_F {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+            a: 1.0,
+        })
+    }
+
+    fn evaluate(s: &str) -> Result<D2D1_COLOR_F> {
+        evaluate_color_function(s, &parse_hex).unwrap_or_else(|| panic!("`{s}` is not a function"))
+    }
+
+    fn assert_color(actual: D2D1_COLOR_F, expected: D2D1_COLOR_F) {
+        let channels = |c: D2D1_COLOR_F| [c.r, c.g, c.b, c.a];
+        let close = channels(actual)
+            .iter()
+            .zip(channels(expected))
+            .all(|(actual, expected)| (actual - expected).abs() < 1e-3);
+
+        assert!(close, "expected {expected:?} but got {actual:?}");
+    }
+
+    fn rgba(r: f32, g: f32, b: f32, a: f32) -> D2D1_COLOR_F {
+        D2D1_COLOR_F { r, g, b, a }
+    }
+
+    #[test]
+    fn mix_takes_half_of_each_color_by_default() {
+        assert_color(
+            evaluate("mix(#000000, #ffffff)").unwrap(),
+            rgba(0.5, 0.5, 0.5, 1.0),
+        );
+    }
+
+    #[test]
+    fn mix_takes_the_amount_of_the_second_color() {
+        assert_color(
+            evaluate("mix(#ff0000, #0000ff, 25%)").unwrap(),
+            rgba(0.75, 0.0, 0.25, 1.0),
+        );
+        assert_color(
+            evaluate("mix(#ff0000, #0000ff, 0.25)").unwrap(),
+            rgba(0.75, 0.0, 0.25, 1.0),
+        );
+    }
+
+    #[test]
+    fn mix_in_a_color_space() {
+        // Half of white in linear light is 0.5, which is about 0.735 gamma-encoded.
+        assert_color(
+            evaluate("mix(#000000, #ffffff, 50%, srgb-linear)").unwrap(),
+            rgba(0.7354, 0.7354, 0.7354, 1.0),
+        );
+        assert_eq!(
+            evaluate("mix(#000000, #ffffff, 50%, hsl)")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn lighten_and_darken_shift_the_lightness() {
+        let lightness = |s: &str| ColorSpace::Oklab.to_components(&evaluate(s).unwrap())[0];
+
+        assert!((lightness("lighten(#000000, 10%)") - 0.1).abs() < 1e-3);
+        assert!((lightness("darken(#ffffff, 0.25)") - 0.75).abs() < 1e-3);
+        assert_color(
+            evaluate("lighten(#000000, 100%)").unwrap(),
+            rgba(1.0, 1.0, 1.0, 1.0),
+        );
+        assert_color(
+            evaluate("darken(#ffffff, 100%)").unwrap(),
+            rgba(0.0, 0.0, 0.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn alpha_sets_the_opacity() {
+        assert_color(
+            evaluate("alpha(#ff0000, 0.5)").unwrap(),
+            rgba(1.0, 0.0, 0.0, 0.5),
+        );
+        assert_color(
+            evaluate("alpha(#ff0000, 25%)").unwrap(),
+            rgba(1.0, 0.0, 0.0, 0.25),
+        );
+    }
+
+    #[test]
+    fn complement_turns_the_hue_halfway_around() {
+        let hue = |color: &D2D1_COLOR_F| ColorSpace::Oklch.to_components(color)[2];
+        let red = parse_hex("#ff0000").unwrap();
+        let complement = evaluate("complement(#ff0000)").unwrap();
+
+        let turned = (hue(&complement) - hue(&red)).rem_euclid(360.0);
+        assert!((turned - 180.0).abs() < 1.0, "turned by {turned} degrees");
+        assert_color(
+            evaluate("complement(complement(#808080))").unwrap(),
+            parse_hex("#808080").unwrap(),
+        );
+    }
+
+    #[test]
+    fn functions_nest() {
+        let lightened = evaluate("lighten(#000000, 10%)").unwrap();
+        let red = parse_hex("#ff0000").unwrap();
+
+        assert_color(
+            evaluate("mix(lighten(#000000, 10%), #ff0000)").unwrap(),
+            ColorSpace::Srgb.mix(&lightened, &red, 0.5),
+        );
+        assert_color(
+            evaluate("alpha(mix(#000000, darken(#ffffff, 0%)), 50%)").unwrap(),
+            rgba(0.5, 0.5, 0.5, 0.5),
+        );
+    }
+
+    #[test]
+    fn other_colors_are_not_functions() {
+        for s in [
+            "#ff0000",
+            "rgb(255, 0, 0)",
+            "mixed(#000000, #ffffff)",
+            "linear-gradient(#000000, #ffffff)",
+        ] {
+            assert!(
+                evaluate_color_function(s, &parse_hex).is_none(),
+                "{s:?} was evaluated"
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_number_of_arguments_is_rejected() {
+        for s in [
+            "mix(#000000)",
+            "mix(#000000, #ffffff, 50%, oklab, 1)",
+            "lighten(#000000)",
+            "darken(#000000, 10%, 20%)",
+            "alpha(#000000)",
+            "complement(#000000, #ffffff)",
+        ] {
+            assert_eq!(
+                evaluate(s).unwrap_err().kind(),
+                ErrorKind::InvalidInput,
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_amounts_are_rejected() {
+        assert_eq!(
+            evaluate("lighten(#000000, lots)").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+
+        for s in [
+            "lighten(#000000, 150%)",
+            "darken(#000000, -10%)",
+            "alpha(#000000, 1.5)",
+            "mix(#000000, #ffffff, 101%)",
+        ] {
+            assert_eq!(
+                evaluate(s).unwrap_err().kind(),
+                ErrorKind::InvalidData,
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn color_strings_evaluate_functions() {
+        let Color::Solid(solid) =
+            parse_color_string_with_theme("mix(lighten(#000, 10%), red)", None).unwrap()
+        else {
+            panic!("expected a solid color");
+        };
+
+        assert_color(
+            solid.color,
+            evaluate("mix(lighten(#000000, 10%), #ff0000)").unwrap(),
+        );
+    }
+
+    #[test]
+    fn gradient_stops_can_be_functions() {
+        let gradient = parse_color_string_with_theme(
+            "linear-gradient(to right, darken(#ffffff, 100%), alpha(red, 50%) 80%)",
+            None,
+        )
+        .unwrap();
+        let Color::Gradient(gradient) = gradient else {
+            panic!("expected a linear gradient");
+        };
+
+        let stops = gradient.gradient_stops;
+        assert_eq!(stops.len(), 2);
+        assert_color(stops[0].color, rgba(0.0, 0.0, 0.0, 1.0));
+        assert_color(stops[1].color, rgba(1.0, 0.0, 0.0, 0.5));
+        assert_eq!(stops[1].position, 0.8);
+    }
+
+    /// Places `stops` given as CSS strings, parsing their colors with `parse_hex`.
+    fn place(stops: &[&str]) -> Result<Vec<D2D1_GRADIENT_STOP>> {
+        let stops = stops
+            .iter()
+            .map(|&stop| {
+                let stop = ColorStop::from(stop);
+                let (color, positions) = color_stop_parts(&stop)?;
+                Ok((parse_hex(color)?, positions))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        place_gradient_stops(stops)
+    }
+
+    fn positions(stops: &[D2D1_GRADIENT_STOP]) -> Vec<f32> {
+        stops.iter().map(|stop| stop.position).collect()
+    }
+
+    #[test]
+    fn stops_without_positions_are_spread_evenly() {
+        assert_eq!(
+            resolve_stop_positions(&[None, None, None]).unwrap(),
+            [0.0, 0.5, 1.0]
+        );
+        assert_eq!(
+            resolve_stop_positions(&[None, Some(0.2), None, None, Some(0.8), None]).unwrap(),
+            [0.0, 0.2, 0.4, 0.6, 0.8, 1.0]
+        );
+    }
+
+    #[test]
+    fn decreasing_stops_are_rejected() {
+        let error = resolve_stop_positions(&[Some(0.5), None, Some(0.3)]).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            error.message(),
+            "stop positions must not decrease, but 30% comes after 50%"
+        );
+        assert!(place(&["#000000 60%", "#ffffff 40%"]).is_err());
+    }
+
+    #[test]
+    fn stops_outside_of_the_gradient_are_rejected() {
+        for position in [-0.1, 1.5] {
+            let error = resolve_stop_positions(&[Some(0.0), Some(position)]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            assert!(error.message().contains("outside of 0% to 100%"));
+        }
+        assert!(place(&["#000000", "#ffffff 120%"]).is_err());
+    }
+
+    #[test]
+    fn stops_can_have_two_positions() {
+        let stop = ColorStop::from("#fff 20% 40%");
+        assert_eq!(color_stop_parts(&stop).unwrap(), ("#fff", vec![0.2, 0.4]));
+
+        let stops = place(&["#000000", "#ffffff 20% 40%", "#ff0000"]).unwrap();
+        assert_eq!(positions(&stops), [0.0, 0.2, 0.4, 1.0]);
+        assert_eq!(stops[1].color, stops[2].color);
+    }
+
+    #[test]
+    fn stop_positions_in_objects_and_strings() {
+        let object = |position| ColorStop::Object {
+            color: "#89b4fa".to_string(),
+            position,
+        };
+
+        assert_eq!(
+            color_stop_parts(&object(Some(StopPosition::Fraction(0.3)))).unwrap(),
+            ("#89b4fa", vec![0.3])
+        );
+        assert_eq!(
+            color_stop_parts(&object(Some(StopPosition::Percentage("30%".to_string())))).unwrap(),
+            ("#89b4fa", vec![0.3])
+        );
+        assert_eq!(
+            color_stop_parts(&object(None)).unwrap(),
+            ("#89b4fa", vec![])
+        );
+        // Only trailing percentages are positions, so colors with spaces are kept whole.
+        assert_eq!(
+            color_stop_parts(&ColorStop::from("rgb(0 0 0 / 50%) 10%")).unwrap(),
+            ("rgb(0 0 0 / 50%)", vec![0.1])
+        );
+        assert!(
+            color_stop_parts(&object(Some(StopPosition::Percentage("lots".to_string())))).is_err()
+        );
+    }
+
+    fn radial(s: &str) -> RadialGradient {
+        match parse_color_string_with_theme(s, None).unwrap() {
+            Color::RadialGradient(gradient) => gradient,
+            _ => panic!("`{s}` is not a radial gradient"),
+        }
+    }
+
+    fn conic(s: &str) -> ConicGradient {
+        match parse_color_string_with_theme(s, None).unwrap() {
+            Color::ConicGradient(gradient) => gradient,
+            _ => panic!("`{s}` is not a conic gradient"),
+        }
+    }
+
+    #[test]
+    fn radial_gradients_default_to_a_centered_ellipse() {
+        let gradient = radial("radial-gradient(#000, #fff)");
+
+        assert_eq!(gradient.shape, RadialShape::Ellipse);
+        assert_eq!(gradient.extent, RadialExtent::FarthestCorner);
+        assert_eq!(gradient.center, DEFAULT_CENTER);
+        assert_eq!(positions(&gradient.gradient_stops), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn radial_gradients_parse_shape_size_and_position() {
+        let gradient = radial("radial-gradient(circle closest-side at top left, #000 20%, #fff)");
+        assert_eq!(gradient.shape, RadialShape::Circle);
+        assert_eq!(gradient.extent, RadialExtent::ClosestSide);
+        assert_eq!(gradient.center, [0.0, 0.0]);
+        assert_eq!(positions(&gradient.gradient_stops), [0.2, 1.0]);
+
+        // Shape and size may come in either order, and the color space after the position.
+        let gradient =
+            radial("radial-gradient(Farthest-Side circle at 25% 75% in oklab, #000, #fff)");
+        assert_eq!(gradient.shape, RadialShape::Circle);
+        assert_eq!(gradient.extent, RadialExtent::FarthestSide);
+        assert_eq!(gradient.center, [0.25, 0.75]);
+        assert_eq!(gradient.gradient_stops.len(), ColorSpace::SEGMENT_STEPS + 1);
+    }
+
+    #[test]
+    fn invalid_radial_gradients_are_rejected() {
+        for s in [
+            "radial-gradient(circle huge, #000, #fff)",
+            "radial-gradient(circle at nowhere, #000, #fff)",
+            "radial-gradient(circle in nothing, #000, #fff)",
+            "radial-gradient(circle, #000)",
+        ] {
+            assert!(parse_color_string_with_theme(s, None).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn conic_gradients_default_to_the_top_and_center() {
+        let gradient = conic("conic-gradient(#000, #fff)");
+
+        assert_eq!(gradient.from, 0.0);
+        assert_eq!(gradient.center, DEFAULT_CENTER);
+        assert_eq!(positions(&gradient.gradient_stops), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn conic_gradients_parse_angle_position_and_color_space() {
+        let gradient = conic("conic-gradient(from 90deg at 25% 75% in oklch, #000, #fff 50%)");
+        assert_eq!(gradient.from, 90.0);
+        assert_eq!(gradient.center, [0.25, 0.75]);
+        assert_eq!(gradient.gradient_stops.len(), ColorSpace::SEGMENT_STEPS + 1);
+        assert_eq!(gradient.gradient_stops.last().unwrap().position, 0.5);
+
+        assert_eq!(
+            conic("conic-gradient(from 0.5turn, #000, #fff)").from,
+            180.0
+        );
+        assert_eq!(
+            conic("conic-gradient(at bottom right, #000, #fff)").center,
+            [1.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn invalid_conic_gradients_are_rejected() {
+        for s in [
+            "conic-gradient(from, #000, #fff)",
+            "conic-gradient(from nowhere, #000, #fff)",
+            "conic-gradient(from 90deg 45deg, #000, #fff)",
+            "conic-gradient(at nowhere, #000, #fff)",
+            "conic-gradient(from 90deg, #000)",
+        ] {
+            assert!(parse_color_string_with_theme(s, None).is_err(), "{s}");
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use schema_jsonrs::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::str::FromStr;
+use windows::Win32::Foundation::RECT;
+use windows::Win32::Graphics::Direct2D::Common::D2D1_GRADIENT_STOP;
+use windows::Win32::Graphics::Direct2D::Common::D2D_POINT_2F;
+use windows::Win32::Graphics::Direct2D::ID2D1RadialGradientBrush;
+
+/// The shape of a radial gradient's ending shape, as in CSS.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RadialShape {
+    /// A circle with the same radius in both directions.
+    Circle,
+    /// An ellipse stretched to the window's proportions.
+    #[default]
+    Ellipse,
+}
+
+/// How far a radial gradient reaches from its center, as in CSS.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RadialExtent {
+    /// The last stop touches the side of the window closest to the center.
+    ClosestSide,
+    /// The last stop touches the side of the window farthest from the center.
+    FarthestSide,
+    /// The last stop passes through the corner of the window closest to the center.
+    ClosestCorner,
+    /// The last stop passes through the corner of the window farthest from the center.
+    #[default]
+    FarthestCorner,
+}
+
+impl FromStr for RadialShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "circle" => Ok(Self::Circle),
+            "ellipse" => Ok(Self::Ellipse),
+            _ => Err(format!("unknown radial gradient shape `{s}`")),
+        }
+    }
+}
+
+impl FromStr for RadialExtent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "closest-side" => Ok(Self::ClosestSide),
+            "farthest-side" => Ok(Self::FarthestSide),
+            "closest-corner" => Ok(Self::ClosestCorner),
+            "farthest-corner" => Ok(Self::FarthestCorner),
+            _ => Err(format!("unknown radial gradient size `{s}`")),
+        }
+    }
+}
+
+/// Represents a radial gradient spreading out from a center point.
+///
+/// # Fields
+/// - `shape`: Whether the gradient is a circle or an ellipse.
+/// - `extent`: How far the last stop reaches, relative to the window's sides or corners.
+/// - `center`: The [x, y] position of the center, normalized from 0.0 to 1.0 and scaled by the
+///   window's size.
+/// - `gradient_stops`: The color stops, from the center outwards.
+/// - `brush`: An optional `ID2D1RadialGradientBrush` used to render the gradient. If not
+///   initialized, this value is `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadialGradient {
+    pub shape: RadialShape,
+    pub extent: RadialExtent,
+    pub center: [f32; 2],
+    pub gradient_stops: Vec<D2D1_GRADIENT_STOP>,
+    pub brush: Option<ID2D1RadialGradientBrush>,
+}
+
+impl RadialGradient {
+    /// Returns the center of the gradient in pixels.
+    pub fn center_point(&self, window_rect: &RECT) -> D2D_POINT_2F {
+        let width = (window_rect.right - window_rect.left) as f32;
+        let height = (window_rect.bottom - window_rect.top) as f32;
+
+        D2D_POINT_2F {
+            x: self.center[0] * width,
+            y: self.center[1] * height,
+        }
+    }
+
+    /// Returns the horizontal and vertical radius of the gradient in pixels.
+    pub fn radii(&self, window_rect: &RECT) -> (f32, f32) {
+        let width = (window_rect.right - window_rect.left) as f32;
+        let height = (window_rect.bottom - window_rect.top) as f32;
+        let center = self.center_point(window_rect);
+
+        let (left, right) = (center.x.abs(), (width - center.x).abs());
+        let (top, bottom) = (center.y.abs(), (height - center.y).abs());
+
+        let (radius_x, radius_y) = match self.shape {
+            RadialShape::Circle => {
+                let radius = match self.extent {
+                    RadialExtent::ClosestSide => left.min(right).min(top).min(bottom),
+                    RadialExtent::FarthestSide => left.max(right).max(top).max(bottom),
+                    RadialExtent::ClosestCorner => left.min(right).hypot(top.min(bottom)),
+                    RadialExtent::FarthestCorner => left.max(right).hypot(top.max(bottom)),
+                };
+                (radius, radius)
+            }
+            RadialShape::Ellipse => match self.extent {
+                RadialExtent::ClosestSide => (left.min(right), top.min(bottom)),
+                RadialExtent::FarthestSide => (left.max(right), top.max(bottom)),
+                // Like CSS, the ellipse keeps the proportions of the matching side variant and
+                // is scaled up to pass through the corner.
+                RadialExtent::ClosestCorner => (
+                    left.min(right) * std::f32::consts::SQRT_2,
+                    top.min(bottom) * std::f32::consts::SQRT_2,
+                ),
+                RadialExtent::FarthestCorner => (
+                    left.max(right) * std::f32::consts::SQRT_2,
+                    top.max(bottom) * std::f32::consts::SQRT_2,
+                ),
+            },
+        };
+
+        // Direct2D draws nothing for a zero radius, e.g. for a center on the window's edge.
+        (radius_x.max(1.0), radius_y.max(1.0))
+    }
+
+    /// Updates the center and radii of the brush for the window's current size.
+    pub fn update_center_radius(&self, window_rect: &RECT) {
+        let center = self.center_point(window_rect);
+        let (radius_x, radius_y) = self.radii(window_rect);
+
+        if let Some(ref id2d1_brush) = self.brush {
+            unsafe {
+                id2d1_brush.SetCenter(center);
+                id2d1_brush.SetRadiusX(radius_x);
+                id2d1_brush.SetRadiusY(radius_y);
+            }
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! Color spaces and the math to convert and mix colors in them.
+//!
+//! Colors are stored as sRGB `D2D1_COLOR_F`s, which is also the space Direct2D interpolates
+//! gradients in. Mixing in linear light, OKLab or OKLCH avoids the dull, greyish colors sRGB
+//! passes through between e.g. blue and yellow.
+
+use schema_jsonrs::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::str::FromStr;
+use windows::Win32::Graphics::Direct2D::Common::D2D1_COLOR_F;
+use windows::Win32::Graphics::Direct2D::Common::D2D1_GRADIENT_STOP;
+
+/// Chroma below which an OKLCH color is treated as grey, whose hue means nothing.
+const ACHROMATIC_CHROMA: f32 = 1e-4;
+
+/// How far an sRGB channel may lie outside of 0.0 to 1.0 and still count as in gamut, which
+/// absorbs rounding errors in the conversions.
+const GAMUT_TOLERANCE: f32 = 1e-4;
+
+/// Number of halvings used to find the most chroma that fits in the sRGB gamut.
+const GAMUT_SEARCH_STEPS: usize = 16;
+
+/// The color space colors are mixed in, named as in CSS `in <color space>`.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorSpace {
+    /// Gamma-encoded sRGB, the way Direct2D and most browsers mix colors by default.
+    #[default]
+    Srgb,
+    /// sRGB in linear light, as light physically adds up.
+    SrgbLinear,
+    /// The perceptual OKLab space, which keeps the brightness even along the way.
+    Oklab,
+    /// OKLab in polar form, which also keeps the colors saturated by going around the hue wheel.
+    Oklch,
+}
+
+impl FromStr for ColorSpace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "srgb" => Ok(Self::Srgb),
+            "srgb-linear" => Ok(Self::SrgbLinear),
+            "oklab" => Ok(Self::Oklab),
+            "oklch" => Ok(Self::Oklch),
+            _ => Err(format!(
+                "unknown color space `{s}`; expected 'srgb', 'srgb-linear', 'oklab' or 'oklch'"
+            )),
+        }
+    }
+}
+
+impl ColorSpace {
+    /// Number of stops each gradient segment is split into when it is interpolated outside of
+    /// sRGB, so Direct2D's own interpolation between them is no longer visible.
+    pub const SEGMENT_STEPS: usize = 16;
+
+    /// Returns the three components of an sRGB color in this space. For OKLCH, the hue comes
+    /// last, in degrees.
+    pub fn to_components(self, color: &D2D1_COLOR_F) -> [f32; 3] {
+        let srgb = [color.r, color.g, color.b];
+
+        match self {
+            ColorSpace::Srgb => srgb,
+            ColorSpace::SrgbLinear => srgb.map(srgb_to_linear),
+            ColorSpace::Oklab => linear_to_oklab(srgb.map(srgb_to_linear)),
+            ColorSpace::Oklch => oklab_to_oklch(linear_to_oklab(srgb.map(srgb_to_linear))),
+        }
+    }
+
+    /// Turns components in this space back into an sRGB color that fits in the sRGB gamut.
+    pub fn to_color(self, components: [f32; 3], alpha: f32) -> D2D1_COLOR_F {
+        let [r, g, b] = match self {
+            ColorSpace::Srgb => components,
+            ColorSpace::SrgbLinear => components.map(linear_to_srgb),
+            ColorSpace::Oklab => oklab_to_srgb(components),
+            ColorSpace::Oklch => oklab_to_srgb(oklch_to_oklab(components)),
+        };
+
+        D2D1_COLOR_F {
+            r: r.clamp(0.0, 1.0),
+            g: g.clamp(0.0, 1.0),
+            b: b.clamp(0.0, 1.0),
+            a: alpha.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Mixes two colors in this space, `t` going from 0.0 (`start`) to 1.0 (`end`).
+    ///
+    /// As in CSS, the colors are premultiplied by their alpha while mixing, so a transparent
+    /// color doesn't tint the other one, and OKLCH hues take the shorter way around.
+    pub fn mix(self, start: &D2D1_COLOR_F, end: &D2D1_COLOR_F, t: f32) -> D2D1_COLOR_F {
+        let mut from = self.to_components(start);
+        let mut to = self.to_components(end);
+
+        // The hue isn't premultiplied, since it is an angle rather than an amount.
+        let premultiplied = match self {
+            ColorSpace::Oklch => 2,
+            _ => 3,
+        };
+
+        if self == ColorSpace::Oklch {
+            // A grey has no hue of its own, so it takes the other color's instead of turning
+            // through unrelated ones on the way.
+            if from[1] < ACHROMATIC_CHROMA {
+                from[2] = to[2];
+            } else if to[1] < ACHROMATIC_CHROMA {
+                to[2] = from[2];
+            }
+
+            let difference = to[2] - from[2];
+            if difference > 180.0 {
+                to[2] -= 360.0;
+            } else if difference < -180.0 {
+                to[2] += 360.0;
+            }
+        }
+
+        let alpha = lerp(start.a, end.a, t);
+        let mixed = std::array::from_fn(|i| match i < premultiplied {
+            true if alpha > 0.0 => lerp(from[i] * start.a, to[i] * end.a, t) / alpha,
+            true => 0.0,
+            false => lerp(from[i], to[i], t),
+        });
+
+        self.to_color(mixed, alpha)
+    }
+
+    /// Adds stops between the given ones so a gradient that Direct2D draws in sRGB looks as if
+    /// it was interpolated in this space. sRGB stops are returned as they are.
+    pub fn interpolate_stops(self, stops: Vec<D2D1_GRADIENT_STOP>) -> Vec<D2D1_GRADIENT_STOP> {
+        if self == ColorSpace::Srgb || stops.len() < 2 {
+            return stops;
+        }
+
+        let mut interpolated = Vec::with_capacity(stops.len() * Self::SEGMENT_STEPS);
+        for pair in stops.windows(2) {
+            let (start, end) = (&pair[0], &pair[1]);
+            interpolated.push(*start);
+
+            // Hard stops and runs of the same color have nothing in between.
+            if end.position <= start.position || start.color == end.color {
+                continue;
+            }
+
+            for step in 1..Self::SEGMENT_STEPS {
+                let t = step as f32 / Self::SEGMENT_STEPS as f32;
+                interpolated.push(D2D1_GRADIENT_STOP {
+                    position: lerp(start.position, end.position, t),
+                    color: self.mix(&start.color, &end.color, t),
+                });
+            }
+        }
+        interpolated.extend(stops.last());
+
+        interpolated
+    }
+}
+
+/// Converts a gamma-encoded sRGB component to linear light.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    let magnitude = value.abs();
+    let linear = match magnitude <= 0.04045 {
+        true => magnitude / 12.92,
+        false => ((magnitude + 0.055) / 1.055).powf(2.4),
+    };
+
+    linear.copysign(value)
+}
+
+/// Converts a linear-light component to gamma-encoded sRGB.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let magnitude = value.abs();
+    let srgb = match magnitude <= 0.0031308 {
+        true => magnitude * 12.92,
+        false => 1.055 * magnitude.powf(1.0 / 2.4) - 0.055,
+    };
+
+    srgb.copysign(value)
+}
+
+/// Converts linear sRGB to OKLab [lightness, a, b].
+pub fn linear_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+/// Converts OKLab [lightness, a, b] to linear sRGB.
+pub fn oklab_to_linear([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let l = (lightness + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m = (lightness - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s = (lightness - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+
+    [
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+        -0.004_196_086_4 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    ]
+}
+
+/// Converts OKLab [lightness, a, b] to gamma-encoded sRGB. Like CSS, a color outside of the sRGB
+/// gamut loses chroma until it fits, which keeps its lightness and hue, rather than having each
+/// channel clipped, which would shift them.
+pub fn oklab_to_srgb([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let srgb = |scale: f32| oklab_to_linear([lightness, a * scale, b * scale]).map(linear_to_srgb);
+    let in_gamut = |rgb: [f32; 3]| {
+        rgb.iter()
+            .all(|channel| (-GAMUT_TOLERANCE..=1.0 + GAMUT_TOLERANCE).contains(channel))
+    };
+
+    let color = srgb(1.0);
+    // Beyond black and white no amount of chroma fits, so those are clipped.
+    if in_gamut(color) || !(0.0..=1.0).contains(&lightness) {
+        return color;
+    }
+
+    let (mut fits, mut overflows) = (0.0, 1.0);
+    for _ in 0..GAMUT_SEARCH_STEPS {
+        let scale = (fits + overflows) / 2.0;
+        match in_gamut(srgb(scale)) {
+            true => fits = scale,
+            false => overflows = scale,
+        }
+    }
+
+    srgb(fits)
+}
+
+/// Converts OKLab [lightness, a, b] to OKLCH [lightness, chroma, hue], the hue in degrees from
+/// 0.0 to 360.0.
+pub fn oklab_to_oklch([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    [
+        lightness,
+        a.hypot(b),
+        b.atan2(a).to_degrees().rem_euclid(360.0),
+    ]
+}
+
+/// Converts OKLCH [lightness, chroma, hue] to OKLab [lightness, a, b].
+pub fn oklch_to_oklab([lightness, chroma, hue]: [f32; 3]) -> [f32; 3] {
+    let (sin, cos) = hue.to_radians().sin_cos();
+    [lightness, chroma * cos, chroma * sin]
+}
+
+fn lerp(start: f32, end: f32, t: f32) -> f32 {
+    start + (end - start) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(r: f32, g: f32, b: f32) -> D2D1_COLOR_F {
+        D2D1_COLOR_F { r, g, b, a: 1.0 }
+    }
+
+    fn assert_close<const N: usize>(actual: [f32; N], expected: [f32; N], tolerance: f32) {
+        let close = actual
+            .iter()
+            .zip(expected)
+            .all(|(actual, expected)| (actual - expected).abs() < tolerance);
+
+        assert!(close, "expected {expected:?} but got {actual:?}");
+    }
+
+    fn channels(color: D2D1_COLOR_F) -> [f32; 4] {
+        [color.r, color.g, color.b, color.a]
+    }
+
+    #[test]
+    fn oklab_matches_the_reference_values() {
+        // From the OKLab reference: https://bottosson.github.io/posts/oklab/
+        let cases = [
+            (rgb(1.0, 1.0, 1.0), [1.0, 0.0, 0.0]),
+            (rgb(0.0, 0.0, 0.0), [0.0, 0.0, 0.0]),
+            (rgb(1.0, 0.0, 0.0), [0.627_955, 0.224_863, 0.125_846]),
+            (rgb(0.0, 1.0, 0.0), [0.866_440, -0.233_888, 0.179_498]),
+            (rgb(0.0, 0.0, 1.0), [0.452_014, -0.032_457, -0.311_528]),
+        ];
+
+        for (color, oklab) in cases {
+            assert_close(ColorSpace::Oklab.to_components(&color), oklab, 1e-3);
+        }
+    }
+
+    #[test]
+    fn oklch_is_oklab_in_polar_form() {
+        let red = ColorSpace::Oklch.to_components(&rgb(1.0, 0.0, 0.0));
+        assert_close(red, [0.627_955, 0.257_683, 29.233_9], 1e-2);
+
+        let blue = ColorSpace::Oklch.to_components(&rgb(0.0, 0.0, 1.0));
+        assert_close(blue, [0.452_014, 0.313_214, 264.052], 1e-2);
+    }
+
+    #[test]
+    fn colors_survive_a_round_trip_through_every_space() {
+        let colors = [
+            rgb(1.0, 1.0, 1.0),
+            rgb(0.0, 0.0, 0.0),
+            rgb(1.0, 0.0, 0.0),
+            rgb(0.537, 0.706, 0.98),
+            rgb(0.953, 0.545, 0.659),
+            rgb(0.02, 0.5, 0.25),
+        ];
+        let spaces = [
+            ColorSpace::Srgb,
+            ColorSpace::SrgbLinear,
+            ColorSpace::Oklab,
+            ColorSpace::Oklch,
+        ];
+
+        for space in spaces {
+            for color in colors {
+                let round_trip = space.to_color(space.to_components(&color), color.a);
+                assert_close(channels(round_trip), channels(color), 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_gamut_colors_lose_chroma_instead_of_being_clipped() {
+        // Far more chroma than sRGB can show at this lightness.
+        let lightness = 0.7;
+        let color = ColorSpace::Oklab.to_color([lightness, 0.4, 0.0], 1.0);
+
+        assert!(channels(color).iter().all(|c| (0.0..=1.0).contains(c)));
+        assert!((ColorSpace::Oklab.to_components(&color)[0] - lightness).abs() < 1e-2);
+    }
+
+    #[test]
+    fn mixing_in_oklch_keeps_the_colors_saturated() {
+        let blue = rgb(0.0, 0.0, 1.0);
+        let yellow = rgb(1.0, 1.0, 0.0);
+        let chroma = |color: &D2D1_COLOR_F| ColorSpace::Oklch.to_components(color)[1];
+
+        // sRGB passes through grey on the way.
+        let srgb = ColorSpace::Srgb.mix(&blue, &yellow, 0.5);
+        assert_close(channels(srgb), [0.5, 0.5, 0.5, 1.0], 1e-6);
+        assert!(chroma(&srgb) < 1e-3);
+
+        let oklch = ColorSpace::Oklch.mix(&blue, &yellow, 0.5);
+        assert!(chroma(&oklch) > 0.1, "{oklch:?}");
+    }
+
+    #[test]
+    fn transparent_colors_dont_tint_the_mix() {
+        let red = rgb(1.0, 0.0, 0.0);
+        let transparent_blue = D2D1_COLOR_F {
+            a: 0.0,
+            ..rgb(0.0, 0.0, 1.0)
+        };
+
+        for space in [ColorSpace::Srgb, ColorSpace::Oklab] {
+            let mixed = space.mix(&red, &transparent_blue, 0.5);
+            assert_close(channels(mixed), [1.0, 0.0, 0.0, 0.5], 1e-3);
+        }
+    }
+
+    #[test]
+    fn stops_are_only_interpolated_outside_of_srgb() {
+        let stops = vec![
+            D2D1_GRADIENT_STOP {
+                position: 0.0,
+                color: rgb(0.0, 0.0, 1.0),
+            },
+            D2D1_GRADIENT_STOP {
+                position: 0.5,
+                color: rgb(1.0, 1.0, 0.0),
+            },
+            // A hard stop, with nothing to interpolate in between.
+            D2D1_GRADIENT_STOP {
+                position: 0.5,
+                color: rgb(1.0, 0.0, 0.0),
+            },
+        ];
+
+        assert_eq!(ColorSpace::Srgb.interpolate_stops(stops.clone()), stops);
+
+        let interpolated = ColorSpace::Oklch.interpolate_stops(stops.clone());
+        assert_eq!(interpolated.len(), ColorSpace::SEGMENT_STEPS + 2);
+        assert_eq!(interpolated.first(), stops.first());
+        assert_eq!(interpolated.last(), stops.last());
+        assert!(interpolated
+            .windows(2)
+            .all(|pair| pair[0].position <= pair[1].position));
+    }
+}
@@ -0,0 +1,464 @@
+//! Backups of config files: `.bak` copies of files that get replaced, and snapshots of the
+//! last configs that loaded successfully.
+//!
+//! A snapshot holds a copy of every file a config was loaded from (the config itself, the files
+//! it includes and the files in `rules.d`), mirrored under `last-good` in the config directory.
+//! Two snapshots are kept: `latest`, the config as it was last loaded, and `previous`, the last
+//! one that differed from it. That way reverting goes back a step even when the config on disk
+//! is valid.
+//!
+//! Files outside of the config directory are not part of the snapshots, so they are always
+//! loaded as they are: a snapshot resolves relative includes that leave the config directory
+//! from the original file's directory.
+
+use crate::config_loader::rules_files;
+use crate::config_loader::RULES_DIR_NAME;
+use anyhow::Context;
+use anyhow::Result as AnyResult;
+use std::fs::copy;
+use std::fs::create_dir_all;
+use std::fs::read;
+use std::fs::read_dir;
+use std::fs::remove_dir_all;
+use std::fs::rename;
+use std::io::ErrorKind;
+use std::path::absolute;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Directory in the config directory holding the snapshots.
+pub const SNAPSHOT_DIR_NAME: &str = "last-good";
+
+/// Extension appended to a file that is moved aside.
+pub const BACKUP_EXTENSION: &str = "bak";
+
+/// The snapshot of the config as it was last loaded successfully.
+const LATEST_SNAPSHOT_NAME: &str = "latest";
+
+/// The last snapshot that differed from the latest one.
+const PREVIOUS_SNAPSHOT_NAME: &str = "previous";
+
+/// A snapshot being written, which only replaces the latest one once it is complete.
+const STAGING_SNAPSHOT_NAME: &str = "staging";
+
+/// A copy of the files a config that loaded successfully was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Directory the files are copied to, mirroring their paths relative to `base_dir`.
+    dir: PathBuf,
+    /// Directory of the main config file.
+    base_dir: PathBuf,
+}
+
+impl Snapshot {
+    /// Returns the snapshot of the config as it was last loaded successfully.
+    pub fn latest(config_file: &Path) -> Self {
+        Self::named(config_file, LATEST_SNAPSHOT_NAME)
+    }
+
+    /// Returns the last snapshot that differed from the latest one.
+    pub fn previous(config_file: &Path) -> Self {
+        Self::named(config_file, PREVIOUS_SNAPSHOT_NAME)
+    }
+
+    fn named(config_file: &Path, name: &str) -> Self {
+        let config_file = absolute(config_file).unwrap_or(config_file.to_path_buf());
+        let base_dir = config_file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        Self {
+            dir: base_dir.join(SNAPSHOT_DIR_NAME).join(name),
+            base_dir,
+        }
+    }
+
+    /// Returns the snapshot `file` is a copy in, or `None` if it is not in one.
+    pub fn containing(file: &Path) -> Option<Self> {
+        file.ancestors().find_map(|dir| {
+            let snapshots_dir = dir.parent()?;
+            if snapshots_dir.file_name()? != SNAPSHOT_DIR_NAME {
+                return None;
+            }
+
+            Some(Self {
+                dir: dir.to_path_buf(),
+                base_dir: snapshots_dir.parent()?.to_path_buf(),
+            })
+        })
+    }
+
+    pub fn exists(&self) -> bool {
+        self.dir.is_dir()
+    }
+
+    /// Returns the copy of `file` in this snapshot, or `None` if `file` is outside of the config
+    /// directory.
+    pub fn copy_of(&self, file: &Path) -> Option<PathBuf> {
+        let relative = file.strip_prefix(&self.base_dir).ok()?;
+        Some(self.dir.join(relative))
+    }
+
+    /// Returns the file a path in this snapshot is a copy of, or `None` if it is not in the
+    /// snapshot.
+    pub fn original_of(&self, copy: &Path) -> Option<PathBuf> {
+        let relative = copy.strip_prefix(&self.dir).ok()?;
+        Some(self.base_dir.join(relative))
+    }
+
+    /// Returns the directory a relative `path` in the copy `copy` is resolved against.
+    ///
+    /// That is the copy's own directory if `path` points into the snapshot, as it would in the
+    /// original. Anything else is resolved from the original's directory, since files outside of
+    /// the config directory are not copied.
+    pub fn resolve_dir(&self, copy: &Path, path: &Path) -> PathBuf {
+        let copy_dir = copy.parent().unwrap_or(Path::new(""));
+        if normalize(&copy_dir.join(path)).starts_with(&self.dir) {
+            return copy_dir.to_path_buf();
+        }
+
+        self.original_of(copy_dir)
+            .unwrap_or_else(|| copy_dir.to_path_buf())
+    }
+
+    /// Returns every file in the snapshot.
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        collect_files(&self.dir, &mut files);
+        files
+    }
+
+    /// Returns whether the snapshot holds a copy of exactly `files`, with their current contents.
+    fn matches(&self, files: &[PathBuf]) -> bool {
+        self.files().len() == files.len()
+            && files.iter().all(|file| {
+                self.copy_of(file)
+                    .is_some_and(|copy| same_contents(file, &copy))
+            })
+    }
+
+    /// Returns whether restoring the snapshot would change anything: a copied file differs from
+    /// the original, or `rules_files` has a file the snapshot doesn't.
+    fn differs(&self, rules_files: &[PathBuf]) -> bool {
+        self.files().iter().any(|snapshot_file| {
+            self.original_of(snapshot_file)
+                .is_some_and(|original| !same_contents(&original, snapshot_file))
+        }) || rules_files
+            .iter()
+            .any(|file| !self.copy_of(file).is_some_and(|copy| copy.is_file()))
+    }
+}
+
+/// Saves the files a config was loaded from as the latest snapshot.
+///
+/// Nothing is written if the latest snapshot already matches them. Otherwise the latest
+/// snapshot becomes the previous one, so reloading the same config over and over never loses
+/// the last good one that differed.
+pub fn save_snapshot(config_file: &Path, sources: &[PathBuf]) -> AnyResult<()> {
+    let latest = Snapshot::latest(config_file);
+    let files: Vec<PathBuf> = sources
+        .iter()
+        .filter(|path| path.is_file() && latest.copy_of(path).is_some())
+        .cloned()
+        .collect();
+
+    if latest.matches(&files) {
+        return Ok(());
+    }
+
+    let staging = Snapshot::named(config_file, STAGING_SNAPSHOT_NAME);
+    remove_dir(&staging.dir)?;
+    for file in files.iter() {
+        let Some(snapshot_file) = staging.copy_of(file) else {
+            continue;
+        };
+        if let Some(parent) = snapshot_file.parent() {
+            create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        copy(file, &snapshot_file)
+            .with_context(|| format!("failed to copy {} to the snapshot", file.display()))?;
+    }
+
+    let previous = Snapshot::previous(config_file);
+    if latest.exists() {
+        remove_dir(&previous.dir)?;
+        rename(&latest.dir, &previous.dir)
+            .with_context(|| format!("failed to move {} aside", latest.dir.display()))?;
+    }
+    rename(&staging.dir, &latest.dir)
+        .with_context(|| format!("failed to write {}", latest.dir.display()))
+}
+
+/// Restores the newest snapshot that differs from the files on disk.
+///
+/// Every file that is replaced, along with every file in `rules.d` the snapshot doesn't have,
+/// is moved aside as a `.bak` file first. Returns the files that were moved aside.
+pub fn restore_snapshot(config_file: &Path) -> AnyResult<Vec<PathBuf>> {
+    let latest = Snapshot::latest(config_file);
+    let rules_files = rules_files(&latest.base_dir.join(RULES_DIR_NAME));
+
+    let snapshot = [latest, Snapshot::previous(config_file)]
+        .into_iter()
+        .filter(Snapshot::exists)
+        .find(|snapshot| snapshot.differs(&rules_files))
+        .context("the config already matches the last known-good config")?;
+
+    let mut backups = Vec::new();
+    for snapshot_file in snapshot.files() {
+        let Some(original) = snapshot.original_of(&snapshot_file) else {
+            continue;
+        };
+        if same_contents(&original, &snapshot_file) {
+            continue;
+        }
+
+        if original.exists() {
+            backups.push(move_aside(&original)?);
+        } else if let Some(parent) = original.parent() {
+            create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        copy(&snapshot_file, &original)
+            .with_context(|| format!("failed to restore {}", original.display()))?;
+    }
+
+    // A file added to `rules.d` would still be merged into the restored config.
+    for file in rules_files {
+        if !snapshot.copy_of(&file).is_some_and(|copy| copy.is_file()) {
+            backups.push(move_aside(&file)?);
+        }
+    }
+
+    Ok(backups)
+}
+
+/// Renames `file` to `<file>.bak`, replacing any previous backup. Returns the backup's path.
+pub fn move_aside(file: &Path) -> AnyResult<PathBuf> {
+    let backup = backup_file(file);
+    rename(file, &backup).with_context(|| format!("failed to move {} aside", file.display()))?;
+
+    Ok(backup)
+}
+
+/// Returns the `.bak` file that `file` is kept as when it is replaced.
+pub fn backup_file(file: &Path) -> PathBuf {
+    let mut backup = file.as_os_str().to_owned();
+    backup.push(".");
+    backup.push(BACKUP_EXTENSION);
+    PathBuf::from(backup)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        match path.is_dir() {
+            true => collect_files(&path, files),
+            false => files.push(path),
+        }
+    }
+}
+
+/// Removes `.` and `..` from a path without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+fn same_contents(a: &Path, b: &Path) -> bool {
+    matches!((read(a), read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+fn remove_dir(dir: &Path) -> AnyResult<()> {
+    match remove_dir_all(dir) {
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("failed to remove {}", dir.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_loader::ConfigLayers;
+    use crate::user_config::ConfigFormat;
+    use std::fs::read_to_string;
+    use std::fs::write;
+
+    /// A directory in the system's temp directory, removed again at the end of the test.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("tacky-borders-{}-{name}", std::process::id()));
+            let _ = remove_dir_all(&dir);
+            create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, path: &str, contents: &str) -> PathBuf {
+            let file = self.0.join(path);
+            create_dir_all(file.parent().unwrap()).unwrap();
+            write(&file, contents).unwrap();
+            file
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = remove_dir_all(&self.0);
+        }
+    }
+
+    fn contents(file: &Path) -> String {
+        read_to_string(file).unwrap()
+    }
+
+    #[test]
+    fn save_copies_the_files_in_the_config_dir() {
+        let temp = TempDir::new("save");
+        let config = temp.write("config/config.jsonc", "v1");
+        let rules = temp.write("config/rules.d/a.jsonc", "rules");
+        let shared = temp.write("shared/base.jsonc", "base");
+
+        save_snapshot(&config, &[shared, config.clone(), rules.clone()]).unwrap();
+
+        let latest = Snapshot::latest(&config);
+        assert_eq!(contents(&latest.copy_of(&config).unwrap()), "v1");
+        assert_eq!(contents(&latest.copy_of(&rules).unwrap()), "rules");
+        assert_eq!(latest.files().len(), 2);
+        assert!(!Snapshot::previous(&config).exists());
+    }
+
+    #[test]
+    fn save_keeps_the_last_snapshot_that_differed() {
+        let temp = TempDir::new("rotate");
+        let config = temp.write("config/config.jsonc", "v1");
+        save_snapshot(&config, &[config.clone()]).unwrap();
+
+        write(&config, "v2").unwrap();
+        save_snapshot(&config, &[config.clone()]).unwrap();
+        // Saving the same files again must not rotate `v1` out.
+        save_snapshot(&config, &[config.clone()]).unwrap();
+
+        let latest = Snapshot::latest(&config);
+        let previous = Snapshot::previous(&config);
+        assert_eq!(contents(&latest.copy_of(&config).unwrap()), "v2");
+        assert_eq!(contents(&previous.copy_of(&config).unwrap()), "v1");
+        assert!(!temp.0.join("config/last-good/staging").exists());
+    }
+
+    #[test]
+    fn restore_moves_the_broken_files_aside() {
+        let temp = TempDir::new("restore");
+        let config = temp.write("config/config.jsonc", "good");
+        save_snapshot(&config, &[config.clone()]).unwrap();
+
+        write(&config, "broken").unwrap();
+        let added = temp.write("config/rules.d/added.jsonc", "added");
+
+        let backups = restore_snapshot(&config).unwrap();
+
+        assert_eq!(contents(&config), "good");
+        assert_eq!(backups, vec![backup_file(&config), backup_file(&added)]);
+        assert_eq!(contents(&backup_file(&config)), "broken");
+        assert!(!added.exists());
+    }
+
+    #[test]
+    fn restore_goes_back_a_step_when_the_config_is_unchanged() {
+        let temp = TempDir::new("restore-previous");
+        let config = temp.write("config/config.jsonc", "v1");
+        save_snapshot(&config, &[config.clone()]).unwrap();
+        write(&config, "v2").unwrap();
+        save_snapshot(&config, &[config.clone()]).unwrap();
+
+        restore_snapshot(&config).unwrap();
+
+        assert_eq!(contents(&config), "v1");
+        assert_eq!(contents(&backup_file(&config)), "v2");
+    }
+
+    #[test]
+    fn restore_fails_when_nothing_differs() {
+        let temp = TempDir::new("restore-same");
+        let config = temp.write("config/config.jsonc", "v1");
+        save_snapshot(&config, &[config.clone()]).unwrap();
+
+        assert!(restore_snapshot(&config).is_err());
+        assert_eq!(contents(&config), "v1");
+    }
+
+    #[test]
+    fn move_aside_replaces_the_previous_backup() {
+        let temp = TempDir::new("move-aside");
+        let file = temp.write("config.jsonc", "first");
+        move_aside(&file).unwrap();
+        temp.write("config.jsonc", "second");
+
+        let backup = move_aside(&file).unwrap();
+
+        assert_eq!(backup, temp.0.join("config.jsonc.bak"));
+        assert_eq!(contents(&backup), "second");
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn includes_outside_of_the_config_dir_resolve_from_the_original() {
+        let temp = TempDir::new("resolve");
+        let config = temp.write("config/config.jsonc", "");
+        let snapshot = Snapshot::latest(&config);
+        let copy = snapshot.copy_of(&absolute(&config).unwrap()).unwrap();
+
+        assert_eq!(Snapshot::containing(&copy), Some(snapshot.clone()));
+        assert_eq!(
+            snapshot.resolve_dir(&copy, Path::new("../shared/base.jsonc")),
+            temp.0.join("config")
+        );
+        assert_eq!(
+            snapshot.resolve_dir(&copy, Path::new("themes/*.jsonc")),
+            copy.parent().unwrap()
+        );
+    }
+
+    #[test]
+    fn last_good_config_still_includes_shared_files() {
+        let temp = TempDir::new("shared-include");
+        let shared = temp.write(
+            "shared/base.jsonc",
+            r#"{ "global": { "border_width": 4 } }"#,
+        );
+        let config = temp.write(
+            "config/config.jsonc",
+            r#"{ "include": ["../shared/base.jsonc"] }"#,
+        );
+        save_snapshot(&config, &[shared.clone(), config.clone()]).unwrap();
+
+        let copy = Snapshot::latest(&config).copy_of(&config).unwrap();
+        let layers = ConfigLayers::load(&copy, &contents(&copy), &ConfigFormat::Jsonc);
+
+        assert!(layers.diagnostics.is_empty(), "{:?}", layers.diagnostics);
+        // The path still has the `..` in it.
+        assert_eq!(
+            layers.sources[0].path.canonicalize().unwrap(),
+            shared.canonicalize().unwrap()
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use crate::colors::parser::theme_file;
+use crate::core::keybindings::Keybindings;
+use crate::diagnostics::Diagnostic;
+use crate::user_config::MatchCondition;
+use crate::user_config::MatchKind;
+use crate::user_config::MatchStrategy;
+use crate::user_config::UserConfig;
+use crate::user_config::WindowMatchConfig;
+use crate::user_config::WindowRuleConfig;
+use crate::window_state::StatePredicate;
+
+/// A single test that a window has to pass.
+#[derive(Debug)]
+enum Leaf<'a> {
+    /// A `kind`/`value`/`strategy` test.
+    Window {
+        kind: &'a MatchKind,
+        value: &'a str,
+        strategy: &'a MatchStrategy,
+    },
+    /// A test on the window's state, such as `maximized` or `min_width`.
+    State(StatePredicate),
+}
+
+impl Leaf<'_> {
+    /// Returns `true` if every window that passes `self` also passes `other`.
+    fn implies(&self, other: &Leaf) -> bool {
+        match (self, other) {
+            (
+                Leaf::Window {
+                    kind,
+                    value,
+                    strategy,
+                },
+                Leaf::Window {
+                    kind: other_kind,
+                    value: other_value,
+                    strategy: other_strategy,
+                },
+            ) => {
+                if kind != other_kind {
+                    return false;
+                }
+
+                let lowercase_value = value.to_lowercase();
+                let lowercase_other_value = other_value.to_lowercase();
+
+                match (strategy, other_strategy) {
+                    (MatchStrategy::Equals, MatchStrategy::Equals) => {
+                        lowercase_value == lowercase_other_value
+                    }
+                    (MatchStrategy::Equals | MatchStrategy::Contains, MatchStrategy::Contains) => {
+                        lowercase_value.contains(&lowercase_other_value)
+                    }
+                    // Regexes are only compared literally; anything smarter is not worth the cost.
+                    (MatchStrategy::Regex, MatchStrategy::Regex) => value == other_value,
+                    _ => false,
+                }
+            }
+            (Leaf::State(predicate), Leaf::State(other_predicate)) => {
+                match (predicate, other_predicate) {
+                    (StatePredicate::MinWidth(width), StatePredicate::MinWidth(other_width)) => {
+                        width >= other_width
+                    }
+                    (
+                        StatePredicate::MinHeight(height),
+                        StatePredicate::MinHeight(other_height),
+                    ) => height >= other_height,
+                    // Aspect ratios are only compared literally, like regexes.
+                    _ => predicate == other_predicate,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Reports rules and settings that are valid but can never have any effect.
+///
+/// Everything found here is a warning, since the config still works as written.
+pub fn lint_config(config: &UserConfig, diagnostics: &mut Vec<Diagnostic>) {
+    lint_window_rules(&config.window_rules, diagnostics);
+    lint_keybindings(&config.keybindings, diagnostics);
+
+    if let Some(ref theme) = config.theme {
+        if let Ok(file) = theme_file(theme) {
+            if !file.is_file() {
+                diagnostics.push(
+                    Diagnostic::warning("theme", format!("theme `{theme}` does not exist"))
+                        .with_help(format!("expected a theme file at {}", file.display())),
+                );
+            }
+        }
+    }
+}
+
+fn lint_window_rules(rules: &[WindowRuleConfig], diagnostics: &mut Vec<Diagnostic>) {
+    // The order rules are applied in, which is what decides whether one can hide another.
+    let mut order: Vec<usize> = (0..rules.len()).collect();
+    order.sort_by_key(|&i| rules[i].priority.unwrap_or_default());
+
+    for (position, &i) in order.iter().enumerate() {
+        let rule = &rules[i];
+        let path = format!("window_rules[{i}]");
+
+        if !has_settings(&rule.match_window) && rule.stop != Some(true) {
+            diagnostics.push(
+                Diagnostic::warning(
+                    &path,
+                    format!("rule {i} has no effect since it doesn't set anything"),
+                )
+                .with_help(
+                    "set a color, width, style, animation, delay or `enabled`, or remove the rule",
+                ),
+            );
+            continue;
+        }
+
+        let condition = &rule.match_window.condition;
+        let earlier = order[..position].iter().map(|&j| (j, &rules[j]));
+
+        // An earlier rule that stops the cascade for every window this rule matches.
+        let stopped_by = earlier
+            .filter(|(_, other)| other.stop == Some(true))
+            .find(|(_, other)| covers(&other.match_window.condition, condition));
+
+        if let Some((j, _)) = stopped_by {
+            diagnostics.push(
+                Diagnostic::warning(
+                    &path,
+                    format!("rule {i} never applies since rule {j} matches every window it does and stops the cascade"),
+                )
+                .with_help(format!("give rule {i} a lower priority than rule {j} or make rule {j} more specific")),
+            );
+            continue;
+        }
+
+        // A later rule that matches every window this rule matches and overrides everything
+        // it sets. A stopping rule in between could keep the later one from being applied, so
+        // the search ends at the first one.
+        let later = order[position + 1..].iter().map(|&j| (j, &rules[j]));
+        let mut overridden_by = None;
+        for (j, other) in later {
+            if overrides(&other.match_window, &rule.match_window)
+                && covers(&other.match_window.condition, condition)
+            {
+                overridden_by = Some(j);
+                break;
+            }
+            if other.stop == Some(true) {
+                break;
+            }
+        }
+
+        if let Some(j) = overridden_by {
+            diagnostics.push(
+                Diagnostic::warning(
+                    &path,
+                    format!("rule {i} has no effect since rule {j} matches every window it does and overrides everything it sets"),
+                )
+                .with_help(format!("give rule {i} a higher priority than rule {j} or remove it")),
+            );
+        }
+    }
+}
+
+/// Returns `true` if every window matched by `narrow` is also matched by `broad`.
+///
+/// This is conservative: it only returns `true` when it can prove it, so `any` and `not`
+/// groups in `broad` are never considered to cover anything.
+fn covers(broad: &MatchCondition, narrow: &MatchCondition) -> bool {
+    let Some(required) = conjunction(broad) else {
+        return false;
+    };
+
+    // `any` and `not` groups in `narrow` only make it match fewer windows, so they are
+    // ignored; the leaves it always requires are enough.
+    let mut known = Vec::new();
+    collect_leaves(narrow, &mut known);
+
+    required
+        .iter()
+        .all(|leaf| known.iter().any(|known| known.implies(leaf)))
+}
+
+/// Returns the leaves that a window must pass to match `condition`, or `None` if it has
+/// `any` or `not` groups that can't be expressed that way.
+fn conjunction(condition: &MatchCondition) -> Option<Vec<Leaf<'_>>> {
+    if condition.any.is_some() || condition.not.is_some() {
+        return None;
+    }
+
+    let mut leaves: Vec<Leaf> = leaves(condition).collect();
+    for child in condition.all.iter().flatten() {
+        leaves.extend(conjunction(child)?);
+    }
+
+    Some(leaves)
+}
+
+/// Collects the leaves that every window matching `condition` is known to pass.
+fn collect_leaves<'a>(condition: &'a MatchCondition, leaves: &mut Vec<Leaf<'a>>) {
+    leaves.extend(self::leaves(condition));
+    for child in condition.all.iter().flatten() {
+        collect_leaves(child, leaves);
+    }
+}
+
+/// Returns the leaves set directly on `condition`.
+fn leaves(condition: &MatchCondition) -> impl Iterator<Item = Leaf<'_>> {
+    let window = match (&condition.match_kind, &condition.match_value) {
+        (Some(kind), Some(value)) => Some(Leaf::Window {
+            kind,
+            value,
+            strategy: condition
+                .match_strategy
+                .as_ref()
+                .unwrap_or(&MatchStrategy::Equals),
+        }),
+        _ => None,
+    };
+
+    window
+        .into_iter()
+        .chain(condition.state_predicates().into_iter().map(Leaf::State))
+}
+
+/// Returns `true` if the rule sets anything at all.
+fn has_settings(rule: &WindowMatchConfig) -> bool {
+    settings(rule).iter().any(|&set| set)
+}
+
+/// Returns `true` if `later` sets everything that `earlier` sets.
+fn overrides(later: &WindowMatchConfig, earlier: &WindowMatchConfig) -> bool {
+    settings(earlier)
+        .iter()
+        .zip(settings(later))
+        .all(|(&earlier, later)| !earlier || later)
+}
+
+/// Which settings a rule sets, in a fixed order.
+fn settings(rule: &WindowMatchConfig) -> [bool; 9] {
+    [
+        rule.enabled.is_some(),
+        rule.active_color.is_some(),
+        rule.inactive_color.is_some(),
+        rule.animations.is_some(),
+        rule.border_style.is_some(),
+        rule.border_width.is_some(),
+        rule.border_offset.is_some(),
+        rule.initialize_delay.is_some(),
+        rule.unminimize_delay.is_some(),
+    ]
+}
+
+fn lint_keybindings(keybindings: &Keybindings, diagnostics: &mut Vec<Diagnostic>) {
+    let bindings = [
+        ("reload", Some(&keybindings.reload)),
+        ("open_config", Some(&keybindings.open_config)),
+        ("exit", Some(&keybindings.exit)),
+        ("revert", keybindings.revert.as_ref()),
+        ("cycle_profile", keybindings.cycle_profile.as_ref()),
+    ];
+
+    // The last key triggers the binding, but the keys held before it can be written in any
+    // order, so `ctrl+alt+r` and `alt+ctrl+r` are the same binding.
+    let normalize = |binding: &str| {
+        let mut keys: Vec<String> = binding
+            .split('+')
+            .map(|key| key.trim().to_lowercase())
+            .filter(|key| !key.is_empty())
+            .collect();
+        let trigger = keys.pop();
+        keys.sort();
+        (keys, trigger)
+    };
+
+    for (i, (name, binding)) in bindings.iter().enumerate() {
+        let Some(binding) = binding else {
+            continue;
+        };
+
+        let duplicate = bindings[..i]
+            .iter()
+            .find(|(_, other)| other.is_some_and(|other| normalize(other) == normalize(binding)));
+
+        if let Some((other_name, _)) = duplicate {
+            diagnostics.push(Diagnostic::warning(
+                format!("keybindings.{name}"),
+                format!("`{binding}` is also bound to `{other_name}`, so pressing it does both"),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lints the given window rules and returns the paths of the rules that were reported.
+    fn lint_rules(json: &str) -> Vec<String> {
+        let rules: Vec<WindowRuleConfig> = serde_jsonc2::from_str(json).unwrap();
+        let mut diagnostics = Vec::new();
+        lint_window_rules(&rules, &mut diagnostics);
+
+        diagnostics.into_iter().map(|d| d.path).collect()
+    }
+
+    fn window_leaf<'a>(value: &'a str, strategy: &'a MatchStrategy) -> Leaf<'a> {
+        Leaf::Window {
+            kind: &MatchKind::Process,
+            value,
+            strategy,
+        }
+    }
+
+    #[test]
+    fn equals_implies_contains() {
+        let equals = window_leaf("Firefox.exe", &MatchStrategy::Equals);
+        let contains = window_leaf("fire", &MatchStrategy::Contains);
+
+        assert!(equals.implies(&contains));
+        assert!(!contains.implies(&equals));
+        assert!(!equals.implies(&window_leaf("chrome", &MatchStrategy::Contains)));
+    }
+
+    #[test]
+    fn equals_rule_followed_by_contains_rule_is_overridden() {
+        let rules = r##"[
+            { "match": { "kind": "Process", "value": "firefox.exe", "active_color": "#ff0000" } },
+            { "match": { "kind": "Process", "value": "fire", "strategy": "Contains", "active_color": "#00ff00" } }
+        ]"##;
+        assert_eq!(lint_rules(rules), ["window_rules[0]"]);
+
+        // The other way around, the narrower rule wins for the windows it matches.
+        let rules = r##"[
+            { "match": { "kind": "Process", "value": "fire", "strategy": "Contains", "active_color": "#00ff00" } },
+            { "match": { "kind": "Process", "value": "firefox.exe", "active_color": "#ff0000" } }
+        ]"##;
+        assert!(lint_rules(rules).is_empty());
+    }
+
+    #[test]
+    fn stop_rule_hides_later_rules() {
+        let rules = r##"[
+            { "match": { "kind": "Process", "value": "code", "strategy": "Contains", "enabled": false }, "stop": true },
+            { "match": { "kind": "Process", "value": "Code.exe", "active_color": "#ff0000" } }
+        ]"##;
+        assert_eq!(lint_rules(rules), ["window_rules[1]"]);
+
+        // A higher priority moves the stopping rule after the other one.
+        let rules = r##"[
+            { "match": { "kind": "Process", "value": "code", "strategy": "Contains", "enabled": false }, "stop": true, "priority": 1 },
+            { "match": { "kind": "Process", "value": "Code.exe", "active_color": "#ff0000" } }
+        ]"##;
+        assert!(lint_rules(rules).is_empty());
+    }
+
+    #[test]
+    fn partial_overrides_are_not_reported() {
+        let rules = r##"[
+            { "match": { "kind": "Class", "value": "Notepad", "active_color": "#ff0000", "border_width": 4 } },
+            { "match": { "kind": "Class", "value": "Notepad", "active_color": "#00ff00" } }
+        ]"##;
+        assert!(lint_rules(rules).is_empty());
+
+        // A later rule that only matches some of the windows doesn't hide the earlier one.
+        let rules = r##"[
+            { "match": { "kind": "Class", "value": "Notepad", "active_color": "#ff0000" } },
+            { "match": { "kind": "Class", "value": "Notepad", "maximized": true, "active_color": "#00ff00" } }
+        ]"##;
+        assert!(lint_rules(rules).is_empty());
+    }
+
+    #[test]
+    fn keybindings_with_modifiers_in_another_order_are_duplicates() {
+        let keybindings = Keybindings {
+            reload: "ctrl+alt+r".to_string(),
+            exit: " ALT + Ctrl+R".to_string(),
+            // Only the modifiers can be held in any order; the last key triggers the binding.
+            revert: Some("ctrl+r+alt".to_string()),
+            ..Default::default()
+        };
+
+        let mut diagnostics = Vec::new();
+        lint_keybindings(&keybindings, &mut diagnostics);
+
+        let paths: Vec<&str> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["keybindings.exit"]);
+    }
+}
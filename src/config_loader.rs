@@ -0,0 +1,681 @@
+use crate::config_backup::Snapshot;
+use crate::config_backup::BACKUP_EXTENSION;
+use crate::config_backup::SNAPSHOT_DIR_NAME;
+use crate::config_migration::migrate;
+use crate::config_migration::Migration;
+use crate::config_migration::CONFIG_VERSION;
+use crate::diagnostics::parent_path;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::SourceMap;
+use crate::user_config::ConfigFormat;
+use anyhow::anyhow;
+use anyhow::Result as AnyResult;
+use serde::Serialize;
+use serde_jsonc2::Value as JsonValue;
+use std::fs::read_dir;
+use std::fs::read_to_string;
+use std::path::absolute;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Directory next to the main config file whose files are merged on top of it.
+pub const RULES_DIR_NAME: &str = "rules.d";
+
+/// Top-level keys whose lists are concatenated across files instead of being overridden.
+const CONCATENATED_KEYS: [&str; 2] = ["window_rules", "include"];
+
+/// Keys a profile can override.
+const PROFILE_KEYS: [&str; 4] = ["global", "window_rules", "monitors", "theme"];
+
+/// A single file that contributed to the configuration.
+#[derive(Debug)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    /// The parsed file, migrated to the current version of the format.
+    pub document: JsonValue,
+    /// What was changed to migrate the file, if it was written for an older version.
+    pub migration: Option<Migration>,
+    pub format: ConfigFormat,
+    source_map: SourceMap,
+}
+
+impl ConfigSource {
+    /// Fills in the location of diagnostics whose path refers to this file's own document.
+    pub fn locate(&self, diagnostics: &mut [Diagnostic]) {
+        self.source_map.locate(&self.path, diagnostics);
+    }
+}
+
+/// The main config file merged with everything it includes and the files in `rules.d`.
+///
+/// Files are merged in a fixed order: the files a config includes (in the order they are
+/// listed, glob matches sorted by path), then the config itself, then every file in `rules.d`
+/// sorted by name. Later files override the scalar keys of earlier ones, while `window_rules`
+/// are concatenated.
+#[derive(Debug, Default)]
+pub struct ConfigLayers {
+    /// Every file that was merged, in merge order.
+    pub sources: Vec<ConfigSource>,
+    /// The merged document.
+    pub document: JsonValue,
+    /// Problems found while reading the files, already located.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The `rules.d` directory, if there is one.
+    pub rules_dir: Option<PathBuf>,
+    /// Index of the main config file in `sources`.
+    main: Option<usize>,
+    /// For every merged window rule, the source it came from and its key path in that source.
+    rule_origins: Vec<(usize, String)>,
+    /// The profile merged on top of the files, if any.
+    profile: Option<String>,
+    /// Files that are currently being loaded, used to detect include cycles.
+    stack: Vec<PathBuf>,
+    /// Version the main config file was written for, once it is known.
+    version: Option<u32>,
+}
+
+impl ConfigLayers {
+    pub fn load(file: &Path, contents: &str, format: &ConfigFormat) -> Self {
+        let mut layers = Self::default();
+
+        layers.main = layers.load_source(file, contents, format);
+        if layers.main.is_none() {
+            return layers;
+        }
+
+        let rules_dir = file.with_file_name(RULES_DIR_NAME);
+        if rules_dir.is_dir() {
+            for rules_file in rules_files(&rules_dir) {
+                if let Err(message) = layers.load_file(&rules_file) {
+                    layers
+                        .diagnostics
+                        .push(Diagnostic::error("", message).at(&rules_file, 1, 1));
+                }
+            }
+            layers.rules_dir = Some(absolute(&rules_dir).unwrap_or(rules_dir));
+        }
+
+        layers.merge();
+        layers
+    }
+
+    /// Returns whether the main config file could be parsed.
+    pub fn is_loaded(&self) -> bool {
+        self.main.is_some()
+    }
+
+    /// Returns how many of the merged window rules come from the files in `rules.d`. Those are
+    /// merged last, so they are always the last rules.
+    pub fn rules_dir_rule_count(&self) -> usize {
+        let Some(main) = self.main else {
+            return 0;
+        };
+
+        self.rule_origins
+            .iter()
+            .filter(|(source, _)| *source > main)
+            .count()
+    }
+
+    /// Returns every file and directory that should be watched for changes.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.sources
+            .iter()
+            .map(|source| source.path.clone())
+            .chain(self.rules_dir.clone())
+            .collect()
+    }
+
+    /// Fills in the location of diagnostics about the merged document, pointing each one at
+    /// the file that provided the offending value.
+    pub fn locate(&self, diagnostics: &mut [Diagnostic]) {
+        for diagnostic in diagnostics.iter_mut().filter(|d| d.file.is_none()) {
+            let (index, path) = self.origin(&diagnostic.path);
+            let Some(source) = self.sources.get(index) else {
+                continue;
+            };
+
+            let (line, column) = source.source_map.position(&path);
+            diagnostic.path = path;
+            diagnostic.file = Some(source.path.clone());
+            diagnostic.line = line;
+            diagnostic.column = column;
+        }
+    }
+
+    /// Returns the source a merged key path came from, along with the path inside that source.
+    fn origin(&self, path: &str) -> (usize, String) {
+        let main = self.main.unwrap_or_default();
+
+        // Window rules are concatenated, so their index has to be translated as well.
+        if let Some(rest) = path.strip_prefix("window_rules[") {
+            if let Some((index, rest)) = rest.split_once(']') {
+                if let Some((source, local_path)) = index
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| self.rule_origins.get(index))
+                {
+                    return (*source, format!("{local_path}{rest}"));
+                }
+            }
+        }
+
+        let mut current = Some(path);
+        while let Some(ancestor) = current.filter(|ancestor| !ancestor.is_empty()) {
+            // Values set by the profile override the ones set outside of it.
+            if let Some(ref profile) = self.profile {
+                let profile_path = format!("profiles.{profile}.{path}");
+                let profile_ancestor = format!("profiles.{profile}.{ancestor}");
+                if let Some(source) = self
+                    .sources
+                    .iter()
+                    .rposition(|source| lookup(&source.document, &profile_ancestor).is_some())
+                {
+                    return (source, profile_path);
+                }
+            }
+
+            if let Some(source) = self
+                .sources
+                .iter()
+                .rposition(|source| lookup(&source.document, ancestor).is_some())
+            {
+                return (source, path.to_string());
+            }
+            current = parent_path(ancestor);
+        }
+
+        (main, path.to_string())
+    }
+
+    /// Parses a file and everything it includes. Returns the index of the file in `sources`.
+    fn load_source(&mut self, file: &Path, contents: &str, format: &ConfigFormat) -> Option<usize> {
+        let file = absolute(file).unwrap_or(file.to_path_buf());
+
+        let mut document = match parse_document(&file, contents, format) {
+            Ok(document) => document,
+            Err(diagnostic) => {
+                self.diagnostics.push(diagnostic);
+                return None;
+            }
+        };
+        let source_map = SourceMap::new(contents, format);
+
+        // Every file is migrated on its own, since included files may be older than the config.
+        // Files without a `version` are taken to be written for the main config's version.
+        let default_version = self.version.unwrap_or(1);
+        let (migration, version) = match migrate(&mut document, default_version) {
+            Ok(migration) => {
+                let version = migration.as_ref().map_or(CONFIG_VERSION, |m| m.from);
+                (migration, version)
+            }
+            Err(diagnostic) => {
+                let mut diagnostics = [diagnostic];
+                source_map.locate(&file, &mut diagnostics);
+                self.diagnostics.extend(diagnostics);
+                (None, default_version)
+            }
+        };
+        // The main config is always the first file to be loaded.
+        self.version.get_or_insert(version);
+
+        // Included files are merged first so that the including file overrides them.
+        self.stack.push(file.clone());
+        let mut diagnostics = self.load_includes(&file, &document);
+        self.stack.pop();
+
+        source_map.locate(&file, &mut diagnostics);
+        self.diagnostics.extend(diagnostics);
+
+        self.sources.push(ConfigSource {
+            path: file,
+            document,
+            migration,
+            format: format.clone(),
+            source_map,
+        });
+
+        Some(self.sources.len() - 1)
+    }
+
+    fn load_includes(&mut self, file: &Path, document: &JsonValue) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        let patterns = match document.get("include") {
+            None | Some(JsonValue::Null) => return diagnostics,
+            Some(JsonValue::Array(patterns)) => patterns,
+            Some(_) => {
+                diagnostics.push(Diagnostic::error(
+                    "include",
+                    "expected a list of file paths or glob patterns",
+                ));
+                return diagnostics;
+            }
+        };
+
+        for (i, pattern) in patterns.iter().enumerate() {
+            let path = format!("include[{i}]");
+
+            let Some(pattern) = pattern.as_str() else {
+                diagnostics.push(Diagnostic::error(path, "expected a file path"));
+                continue;
+            };
+
+            match expand_include(file, pattern) {
+                Ok(included_files) if included_files.is_empty() => {
+                    diagnostics.push(Diagnostic::warning(
+                        path,
+                        format!("`{pattern}` did not match any files"),
+                    ));
+                }
+                Ok(included_files) => {
+                    for included_file in included_files {
+                        if let Err(message) = self.load_file(&included_file) {
+                            diagnostics.push(Diagnostic::error(&path, message));
+                        }
+                    }
+                }
+                Err(message) => diagnostics.push(Diagnostic::error(path, message)),
+            }
+        }
+
+        diagnostics
+    }
+
+    fn load_file(&mut self, file: &Path) -> Result<(), String> {
+        let file = absolute(file).unwrap_or(file.to_path_buf());
+
+        if self.stack.contains(&file) {
+            return Err(format!("include cycle: {} includes itself", file.display()));
+        }
+
+        // A file that is included more than once is only merged the first time.
+        if self.sources.iter().any(|source| source.path == file) {
+            return Ok(());
+        }
+
+        let contents = read_to_string(&file)
+            .map_err(|err| format!("could not read {}: {err}", file.display()))?;
+
+        self.load_source(&file, &contents, &ConfigFormat::from_path(&file));
+
+        Ok(())
+    }
+
+    /// Merges the profile `name` on top of the merged files: its `global`, `monitors` and
+    /// `theme` override the config's the same way another file would, and its `window_rules`
+    /// replace the config's.
+    ///
+    /// Returns `false` if the config has no such profile.
+    pub fn apply_profile(&mut self, name: &str) -> bool {
+        let Some(JsonValue::Object(profile)) = self
+            .document
+            .get("profiles")
+            .and_then(|profiles| profiles.get(name))
+        else {
+            return false;
+        };
+
+        // Anything else in the profile is reported as an unknown key, so it is left out here.
+        let profile = JsonValue::Object(
+            profile
+                .iter()
+                .filter(|(key, _)| PROFILE_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        );
+
+        if let Some(JsonValue::Array(rules)) = profile.get("window_rules") {
+            let rules_path = format!("profiles.{name}.window_rules");
+            let source = self
+                .sources
+                .iter()
+                .rposition(|source| lookup(&source.document, &rules_path).is_some())
+                .unwrap_or_default();
+            self.rule_origins = (0..rules.len())
+                .map(|local_index| (source, format!("{rules_path}[{local_index}]")))
+                .collect();
+
+            if let Some(document) = self.document.as_object_mut() {
+                document.remove("window_rules");
+            }
+        }
+
+        merge_value(&mut self.document, profile, true);
+        self.profile = Some(name.to_string());
+        true
+    }
+
+    fn merge(&mut self) {
+        let mut document = JsonValue::Object(Default::default());
+
+        for (index, source) in self.sources.iter().enumerate() {
+            if let Some(JsonValue::Array(rules)) = source.document.get("window_rules") {
+                self.rule_origins.extend(
+                    (0..rules.len())
+                        .map(|local_index| (index, format!("window_rules[{local_index}]"))),
+                );
+            }
+
+            merge_value(&mut document, source.document.clone(), true);
+        }
+
+        self.document = document;
+    }
+}
+
+/// Parses `contents` into a format-independent document.
+pub fn parse_document(
+    file: &Path,
+    contents: &str,
+    format: &ConfigFormat,
+) -> Result<JsonValue, Diagnostic> {
+    #[cfg(feature = "json")]
+    if matches!(format, ConfigFormat::Json | ConfigFormat::Jsonc) {
+        return serde_jsonc2::from_str(contents).map_err(|err| {
+            Diagnostic::error("", format!("invalid JSON: {err}")).at(file, err.line(), err.column())
+        });
+    }
+
+    #[cfg(feature = "yml")]
+    if matches!(format, ConfigFormat::Yaml) {
+        return serde_yml::from_str(contents).map_err(|err| {
+            let (line, column) = err
+                .location()
+                .map(|location| (location.line(), location.column()))
+                .unwrap_or((1, 1));
+            Diagnostic::error("", format!("invalid YAML: {err}")).at(file, line, column)
+        });
+    }
+
+    #[cfg(feature = "toml")]
+    if matches!(format, ConfigFormat::Toml) {
+        return toml::from_str(contents).map_err(|err| {
+            let offset = err.span().map(|span| span.start).unwrap_or_default();
+            let (line, column) = line_column(contents, offset);
+            Diagnostic::error("", format!("invalid TOML: {}", err.message())).at(file, line, column)
+        });
+    }
+
+    #[cfg(feature = "json5")]
+    if matches!(format, ConfigFormat::Json5) {
+        return json5::from_str(contents).map_err(|err| {
+            let json5::Error::Message { msg, location } = err;
+            let (line, column) = location
+                .map(|location| (location.line, location.column))
+                .unwrap_or((1, 1));
+            Diagnostic::error("", format!("invalid JSON5: {msg}")).at(file, line, column)
+        });
+    }
+
+    Err(Diagnostic::error("", "unsupported configuration format").at(file, 1, 1))
+}
+
+/// Writes `value` in the given format. This is the inverse of [`parse_document`], and what
+/// every feature that writes the config back to disk goes through.
+pub fn serialize_document<T>(value: &T, format: &ConfigFormat) -> AnyResult<String>
+where
+    T: Serialize,
+{
+    let mut contents = match format {
+        // Plain JSON is valid JSONC and JSON5 as well.
+        #[cfg(feature = "json")]
+        ConfigFormat::Json | ConfigFormat::Jsonc => serde_jsonc2::to_string_pretty(value)?,
+        #[cfg(feature = "json5")]
+        ConfigFormat::Json5 => serde_jsonc2::to_string_pretty(value)?,
+        #[cfg(feature = "yml")]
+        ConfigFormat::Yaml => serde_yml::to_string(value)?,
+        #[cfg(feature = "toml")]
+        ConfigFormat::Toml => toml::to_string_pretty(value)?,
+        ConfigFormat::None => return Err(anyhow!("unsupported configuration format")),
+    };
+
+    if !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+
+    Ok(contents)
+}
+
+/// Converts a byte offset into a one-based line and column.
+#[cfg(feature = "toml")]
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = contents.get(..offset).unwrap_or(contents);
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+
+    (line, column)
+}
+
+/// Merges `layer` on top of `base`. Objects are merged key by key, the top-level lists in
+/// [`CONCATENATED_KEYS`] are appended to and everything else is replaced.
+fn merge_value(base: &mut JsonValue, layer: JsonValue, is_root: bool) {
+    match (base, layer) {
+        (JsonValue::Object(base), JsonValue::Object(layer)) => {
+            for (key, value) in layer {
+                let is_concatenated = is_root && CONCATENATED_KEYS.contains(&key.as_str());
+
+                match (base.get_mut(&key), value) {
+                    (Some(JsonValue::Array(existing)), JsonValue::Array(items))
+                        if is_concatenated =>
+                    {
+                        existing.extend(items);
+                    }
+                    (Some(existing), value) => merge_value(existing, value, false),
+                    (None, value) => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Resolves an `include` entry relative to the file that contains it. In a last known-good copy
+/// of the config, entries outside of the config directory resolve as they do in the original.
+///
+/// Plain paths must exist; glob patterns may match any number of files. Glob patterns never
+/// match the including file itself, `.bak` backups or the last known-good copies of the config.
+fn expand_include(file: &Path, pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let base_dir = match Snapshot::containing(file) {
+        Some(snapshot) => snapshot.resolve_dir(file, Path::new(pattern)),
+        None => file.parent().unwrap_or(Path::new("")).to_path_buf(),
+    };
+
+    if !pattern.contains(['*', '?', '[']) {
+        let included_file = base_dir.join(pattern);
+        return match included_file.is_file() {
+            true => Ok(vec![included_file]),
+            false => Err(format!(
+                "included file not found: {}",
+                included_file.display()
+            )),
+        };
+    }
+
+    let full_pattern = match Path::new(pattern).is_absolute() {
+        true => pattern.to_string(),
+        false => format!(
+            "{}/{pattern}",
+            glob::Pattern::escape(&base_dir.to_string_lossy())
+        ),
+    };
+
+    let mut included_files: Vec<PathBuf> = glob::glob(&full_pattern)
+        .map_err(|err| format!("invalid glob pattern: {err}"))?
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file() && !is_excluded_from_globs(file, path))
+        .collect();
+    included_files.sort();
+
+    Ok(included_files)
+}
+
+/// Returns whether a file matched by a glob in `file` is one of the files that are kept next to
+/// the config but are not part of it.
+///
+/// The last known-good copies are only excluded when `file` is not one of them, so a copy still
+/// includes the other copies.
+fn is_excluded_from_globs(file: &Path, path: &Path) -> bool {
+    path == file
+        || path
+            .extension()
+            .is_some_and(|extension| extension == BACKUP_EXTENSION)
+        || path.ancestors().any(|dir| {
+            dir.file_name()
+                .is_some_and(|name| name == SNAPSHOT_DIR_NAME)
+                && !file.starts_with(dir)
+        })
+}
+
+/// Returns the config files in the `rules.d` directory, sorted by name.
+pub fn rules_files(rules_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(rules_dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| !matches!(ConfigFormat::from_path(path), ConfigFormat::None))
+        .collect();
+    files.sort();
+
+    files
+}
+
+/// Returns the value at a key path such as `global.active_color.colors[1]`.
+fn lookup<'a>(value: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    let mut value = value;
+
+    for segment in path.split('.') {
+        let (key, indices) = match segment.split_once('[') {
+            Some((key, indices)) => (key, Some(indices)),
+            None => (segment, None),
+        };
+
+        if !key.is_empty() {
+            value = value.get(key)?;
+        }
+
+        for index in indices.into_iter().flat_map(|indices| indices.split('[')) {
+            value = value.get(index.trim_end_matches(']').parse::<usize>().ok()?)?;
+        }
+    }
+
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_validator::ConfigReport;
+    use crate::user_config::UserConfig;
+
+    /// Every format enabled in this build.
+    fn enabled_formats() -> Vec<ConfigFormat> {
+        vec![
+            #[cfg(feature = "json")]
+            ConfigFormat::Json,
+            #[cfg(feature = "json")]
+            ConfigFormat::Jsonc,
+            #[cfg(feature = "yml")]
+            ConfigFormat::Yaml,
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml,
+            #[cfg(feature = "json5")]
+            ConfigFormat::Json5,
+        ]
+    }
+
+    /// Loads `resources/config.<ext>` the way the application loads a config file.
+    fn load_bundled_config(format: &ConfigFormat) -> UserConfig {
+        let file = Path::new("resources/config").with_extension(format.extension());
+        let contents = read_to_string(&file).unwrap();
+
+        let document = parse_document(&file, &contents, format);
+        assert!(document.is_ok(), "{}: {document:?}", file.display());
+
+        let report = ConfigReport::new(&file, &contents, format, None);
+        assert!(
+            report.diagnostics.is_empty() && report.lints.is_empty(),
+            "{}: {:?}",
+            file.display(),
+            report.diagnostics.iter().chain(report.lints.iter())
+        );
+
+        let mut config = report.config.unwrap();
+        config.sources.clear();
+        config
+    }
+
+    #[test]
+    fn bundled_configs_are_the_same_in_every_format() {
+        let formats = enabled_formats();
+        let expected = load_bundled_config(&formats[0]);
+
+        for format in formats.iter().skip(1) {
+            assert_eq!(load_bundled_config(format), expected, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn bundled_config_converts_to_every_format() {
+        let formats = enabled_formats();
+        let config = load_bundled_config(&formats[0]);
+
+        for format in formats.iter() {
+            let file = Path::new("converted").with_extension(format.extension());
+            let contents = serialize_document(&config, format).unwrap();
+
+            let report = ConfigReport::new(&file, &contents, format, None);
+            assert!(
+                report.diagnostics.is_empty(),
+                "{format:?}: {:?}\n{contents}",
+                report.diagnostics
+            );
+
+            let mut converted = report.config.unwrap();
+            converted.sources.clear();
+            assert_eq!(converted, config, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn included_files_without_a_version_take_the_configs_version() {
+        let dir = std::env::temp_dir().join(format!(
+            "tacky-borders-{}-include-versions",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("base.jsonc"),
+            r#"{ "global": { "init_delay": 100 } }"#,
+        )
+        .unwrap();
+        let file = dir.join("config.jsonc");
+
+        let v2 = ConfigLayers::load(
+            &file,
+            r#"{ "version": 2, "include": ["base.jsonc"] }"#,
+            &ConfigFormat::Jsonc,
+        );
+        let v1 = ConfigLayers::load(
+            &file,
+            r#"{ "include": ["base.jsonc"] }"#,
+            &ConfigFormat::Jsonc,
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(v2.sources.iter().all(|source| source.migration.is_none()));
+        assert_eq!(v2.document.pointer("/global/init_delay"), Some(&100.into()));
+
+        assert!(v1.sources.iter().all(|source| source.migration.is_some()));
+        assert_eq!(
+            v1.document.pointer("/global/initialize_delay"),
+            Some(&100.into())
+        );
+    }
+}
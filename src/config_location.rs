@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Environment variable pointing at a config file or directory.
+pub const CONFIG_ENV_VAR: &str = "TACKY_BORDERS_CONFIG";
+
+/// Name of the config directory under `XDG_CONFIG_HOME`, `%APPDATA%` and `~/.config`.
+const CONFIG_DIR_NAME: &str = "tacky-borders";
+
+/// The parts of the filesystem needed to find the config, so the lookup can be run against a
+/// fake filesystem.
+pub trait FileSystem {
+    fn is_file(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+}
+
+/// The real filesystem.
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+}
+
+/// Where the config lives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigLocation {
+    /// Directory holding the config, its themes and the log file.
+    pub dir: PathBuf,
+    /// The config file, if one was given explicitly. Otherwise it is detected in `dir`.
+    pub file: Option<PathBuf>,
+    /// Whether `dir` already exists. If not, it is where a new config should be created.
+    pub exists: bool,
+}
+
+impl ConfigLocation {
+    /// Returns the location for a path that was given explicitly, either as a config file or as
+    /// a directory to look for one in.
+    ///
+    /// A path that doesn't exist is taken as a file if it has an extension, so a typo is
+    /// reported as a missing file rather than silently creating a new config directory.
+    pub fn from_path(path: &Path, fs: &impl FileSystem) -> Self {
+        let is_file = fs.is_file(path) || (!fs.is_dir(path) && path.extension().is_some());
+
+        match is_file {
+            true => {
+                let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                Self {
+                    exists: fs.is_dir(&dir) || dir.as_os_str().is_empty(),
+                    dir,
+                    file: Some(path.to_path_buf()),
+                }
+            }
+            false => Self {
+                dir: path.to_path_buf(),
+                file: None,
+                exists: fs.is_dir(path),
+            },
+        }
+    }
+}
+
+/// Finds the config location from the environment.
+///
+/// The first of these that applies is used:
+///
+/// 1. `TACKY_BORDERS_CONFIG`, as a config file or a directory
+/// 2. `$XDG_CONFIG_HOME/tacky-borders`
+/// 3. `%APPDATA%\tacky-borders`
+/// 4. `~/.config/tacky-borders`
+/// 5. `~/.tacky-borders`
+///
+/// Apart from `TACKY_BORDERS_CONFIG`, a directory is only used if it exists. If none does, the
+/// location is `$XDG_CONFIG_HOME/tacky-borders` if that is set, or `~/.config/tacky-borders`,
+/// marked as not existing yet. Returns `None` if there is no home directory to fall back to.
+pub fn resolve_config_location(
+    env: &HashMap<String, String>,
+    home_dir: Option<&Path>,
+    fs: &impl FileSystem,
+) -> Option<ConfigLocation> {
+    // Empty variables are treated as unset, as most tools do.
+    let var = |name: &str| env.get(name).filter(|value| !value.is_empty());
+
+    if let Some(path) = var(CONFIG_ENV_VAR) {
+        return Some(ConfigLocation::from_path(Path::new(path), fs));
+    }
+
+    // Relative `XDG_CONFIG_HOME` paths are invalid per the spec and are ignored.
+    let xdg_dir = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .map(|path| path.join(CONFIG_DIR_NAME));
+    let appdata_dir = var("APPDATA").map(|path| Path::new(path).join(CONFIG_DIR_NAME));
+    let home_config_dir = home_dir.map(|home| home.join(".config").join(CONFIG_DIR_NAME));
+    let home_fallback_dir = home_dir.map(|home| home.join(".tacky-borders"));
+
+    let candidates = [&xdg_dir, &appdata_dir, &home_config_dir, &home_fallback_dir];
+    if let Some(dir) = candidates.into_iter().flatten().find(|dir| fs.is_dir(dir)) {
+        return Some(ConfigLocation {
+            dir: dir.clone(),
+            file: None,
+            exists: true,
+        });
+    }
+
+    xdg_dir.or(home_config_dir).map(|dir| ConfigLocation {
+        dir,
+        file: None,
+        exists: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A filesystem holding only the given files and directories.
+    #[derive(Default)]
+    struct FakeFileSystem {
+        files: Vec<PathBuf>,
+        dirs: Vec<PathBuf>,
+    }
+
+    impl FileSystem for FakeFileSystem {
+        fn is_file(&self, path: &Path) -> bool {
+            self.files.iter().any(|file| file == path)
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.dirs.iter().any(|dir| dir == path)
+        }
+    }
+
+    /// Returns an absolute path on the platform the tests run on.
+    fn absolute(path: &str) -> PathBuf {
+        let root = match cfg!(windows) {
+            true => r"C:\",
+            false => "/",
+        };
+        Path::new(root).join(path)
+    }
+
+    fn env(vars: &[(&str, &Path)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string_lossy().into_owned()))
+            .collect()
+    }
+
+    fn location(dir: PathBuf, file: Option<PathBuf>, exists: bool) -> Option<ConfigLocation> {
+        Some(ConfigLocation { dir, file, exists })
+    }
+
+    #[test]
+    fn env_var_as_a_file() {
+        let dir = absolute("custom");
+        let file = dir.join("borders.yaml");
+        let fs = FakeFileSystem {
+            files: vec![file.clone()],
+            dirs: vec![dir.clone(), absolute("xdg").join(CONFIG_DIR_NAME)],
+        };
+        let env = env(&[
+            (CONFIG_ENV_VAR, &file),
+            ("XDG_CONFIG_HOME", &absolute("xdg")),
+        ]);
+
+        assert_eq!(
+            resolve_config_location(&env, Some(&absolute("home")), &fs),
+            location(dir, Some(file), true)
+        );
+    }
+
+    #[test]
+    fn env_var_as_a_missing_file() {
+        let file = absolute("custom").join("borders.yaml");
+        let env = env(&[(CONFIG_ENV_VAR, &file)]);
+
+        assert_eq!(
+            resolve_config_location(&env, None, &FakeFileSystem::default()),
+            location(absolute("custom"), Some(file), false)
+        );
+    }
+
+    #[test]
+    fn env_var_as_a_dir() {
+        let dir = absolute("custom");
+        let fs = FakeFileSystem {
+            dirs: vec![dir.clone()],
+            ..Default::default()
+        };
+        let env = env(&[(CONFIG_ENV_VAR, &dir)]);
+
+        assert_eq!(
+            resolve_config_location(&env, Some(&absolute("home")), &fs),
+            location(dir, None, true)
+        );
+    }
+
+    #[test]
+    fn xdg_config_home_comes_before_appdata_and_home() {
+        let xdg_dir = absolute("xdg").join(CONFIG_DIR_NAME);
+        let fs = FakeFileSystem {
+            dirs: vec![
+                xdg_dir.clone(),
+                absolute("appdata").join(CONFIG_DIR_NAME),
+                absolute("home").join(".config").join(CONFIG_DIR_NAME),
+            ],
+            ..Default::default()
+        };
+        let env = env(&[
+            ("XDG_CONFIG_HOME", &absolute("xdg")),
+            ("APPDATA", &absolute("appdata")),
+        ]);
+
+        assert_eq!(
+            resolve_config_location(&env, Some(&absolute("home")), &fs),
+            location(xdg_dir, None, true)
+        );
+    }
+
+    #[test]
+    fn appdata_is_used_when_xdg_config_home_has_no_config() {
+        let appdata_dir = absolute("appdata").join(CONFIG_DIR_NAME);
+        let fs = FakeFileSystem {
+            dirs: vec![appdata_dir.clone()],
+            ..Default::default()
+        };
+        let env = env(&[
+            ("XDG_CONFIG_HOME", &absolute("xdg")),
+            ("APPDATA", &absolute("appdata")),
+        ]);
+
+        assert_eq!(
+            resolve_config_location(&env, Some(&absolute("home")), &fs),
+            location(appdata_dir, None, true)
+        );
+    }
+
+    #[test]
+    fn home_dirs_are_used_in_order() {
+        let home = absolute("home");
+        let config_dir = home.join(".config").join(CONFIG_DIR_NAME);
+        let fallback_dir = home.join(".tacky-borders");
+
+        let fs = FakeFileSystem {
+            dirs: vec![config_dir.clone(), fallback_dir.clone()],
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_config_location(&HashMap::new(), Some(&home), &fs),
+            location(config_dir, None, true)
+        );
+
+        let fs = FakeFileSystem {
+            dirs: vec![fallback_dir.clone()],
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_config_location(&HashMap::new(), Some(&home), &fs),
+            location(fallback_dir, None, true)
+        );
+    }
+
+    #[test]
+    fn new_config_location_when_nothing_exists() {
+        let fs = FakeFileSystem::default();
+        let home = absolute("home");
+
+        let env = env(&[("XDG_CONFIG_HOME", &absolute("xdg"))]);
+        assert_eq!(
+            resolve_config_location(&env, Some(&home), &fs),
+            location(absolute("xdg").join(CONFIG_DIR_NAME), None, false)
+        );
+
+        assert_eq!(
+            resolve_config_location(&HashMap::new(), Some(&home), &fs),
+            location(home.join(".config").join(CONFIG_DIR_NAME), None, false)
+        );
+
+        assert_eq!(resolve_config_location(&HashMap::new(), None, &fs), None);
+    }
+
+    #[test]
+    fn empty_variables_are_ignored() {
+        let home = absolute("home");
+        let fs = FakeFileSystem {
+            dirs: vec![Path::new("").join(CONFIG_DIR_NAME)],
+            ..Default::default()
+        };
+        let env = env(&[
+            (CONFIG_ENV_VAR, Path::new("")),
+            ("XDG_CONFIG_HOME", Path::new("")),
+            ("APPDATA", Path::new("")),
+        ]);
+
+        assert_eq!(
+            resolve_config_location(&env, Some(&home), &fs),
+            location(home.join(".config").join(CONFIG_DIR_NAME), None, false)
+        );
+    }
+
+    #[test]
+    fn relative_xdg_config_home_is_ignored() {
+        let home = absolute("home");
+        let fs = FakeFileSystem {
+            dirs: vec![Path::new("xdg").join(CONFIG_DIR_NAME)],
+            ..Default::default()
+        };
+        let env = env(&[("XDG_CONFIG_HOME", Path::new("xdg"))]);
+
+        assert_eq!(
+            resolve_config_location(&env, Some(&home), &fs),
+            location(home.join(".config").join(CONFIG_DIR_NAME), None, false)
+        );
+    }
+
+    #[test]
+    fn relative_env_var_is_used_as_given() {
+        let file = Path::new("borders").join("config.toml");
+        let fs = FakeFileSystem {
+            files: vec![file.clone()],
+            dirs: vec![PathBuf::from("borders")],
+        };
+        let env = env(&[(CONFIG_ENV_VAR, &file)]);
+
+        assert_eq!(
+            resolve_config_location(&env, Some(&absolute("home")), &fs),
+            location(PathBuf::from("borders"), Some(file), true)
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use crate::core::animation::AnimationKind;
+use crate::core::animation::ANIMATION_KIND_NAMES;
+use crate::diagnostics::Diagnostic;
+use serde_jsonc2::Value as JsonValue;
+use std::str::FromStr;
+
+/// The current version of the config format. Configs without a `version` are version 1.
+///
+/// Files included by a config are written for the same version as the config, so the ones
+/// without a `version` of their own take the config's.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Upgrades a document by one version, describing every change it makes.
+type MigrationStep = fn(&mut JsonValue, &mut Vec<String>);
+
+/// Every migration step in order; the step at index `i` upgrades version `i + 1` to `i + 2`.
+const MIGRATION_STEPS: [MigrationStep; CONFIG_VERSION as usize - 1] = [migrate_v1];
+
+/// Spellings of the delay keys that version 2 dropped, as `(alias, key)`.
+pub const DELAY_ALIASES: [(&str, &str); 2] = [
+    ("init_delay", "initialize_delay"),
+    ("restore_delay", "unminimize_delay"),
+];
+
+/// What was changed to bring a config file up to the current version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    /// The version the file was written for.
+    pub from: u32,
+    /// Every change that was made, e.g. "renamed `global.init_delay` to `global.initialize_delay`".
+    pub changes: Vec<String>,
+}
+
+/// Rewrites a config document written for an older version of the format to the current one.
+///
+/// A document without a `version` is taken to be written for `default_version`. Returns
+/// `None` if the document is already current. A `version` that is invalid or newer than
+/// [`CONFIG_VERSION`] is an error, since the document can't be read reliably.
+pub fn migrate(
+    document: &mut JsonValue,
+    default_version: u32,
+) -> Result<Option<Migration>, Diagnostic> {
+    let Some(object) = document.as_object_mut() else {
+        return Ok(None);
+    };
+
+    let from = match object.get("version") {
+        None => default_version,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|&version| version >= 1)
+            .ok_or_else(|| Diagnostic::error("version", "expected a version number such as 2"))?,
+    };
+
+    if from > CONFIG_VERSION {
+        return Err(Diagnostic::error(
+            "version",
+            format!("version {from} is newer than this build supports (up to {CONFIG_VERSION})"),
+        )
+        .with_help("update tacky-borders to use this config"));
+    }
+
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    let mut changes = Vec::new();
+    for step in &MIGRATION_STEPS[from as usize - 1..] {
+        step(document, &mut changes);
+    }
+
+    if let Some(object) = document.as_object_mut() {
+        object.insert("version".to_string(), CONFIG_VERSION.into());
+    }
+    changes.push(format!("set `version` to {CONFIG_VERSION}"));
+
+    Ok(Some(Migration { from, changes }))
+}
+
+/// Version 2 drops the delay aliases and settles on one spelling per animation kind.
+fn migrate_v1(document: &mut JsonValue, changes: &mut Vec<String>) {
+    for_each_rule(document, &mut |path, rule| {
+        for (alias, key) in DELAY_ALIASES {
+            rename_key(rule, path, alias, key, changes);
+        }
+
+        let Some(animations) = rule.get_mut("animations") else {
+            return;
+        };
+
+        for list in ["active", "inactive"] {
+            let Some(JsonValue::Array(items)) = animations.get_mut(list) else {
+                continue;
+            };
+
+            for (i, item) in items.iter_mut().enumerate() {
+                let Some(JsonValue::String(kind)) = item.get_mut("kind") else {
+                    continue;
+                };
+
+                // Unknown kinds are left alone for the validator to report.
+                let Some(name) = canonical_kind_name(kind) else {
+                    continue;
+                };
+
+                if *kind != name {
+                    changes.push(format!(
+                        "changed `{path}.animations.{list}[{i}].kind` from `{kind}` to `{name}`"
+                    ));
+                    *kind = name.to_string();
+                }
+            }
+        }
+    });
+}
+
+/// Calls `f` with every object that holds rule settings: `global`, the `match` of every window
+/// rule, and the same in every profile.
+fn for_each_rule(document: &mut JsonValue, f: &mut impl FnMut(&str, &mut JsonValue)) {
+    fn visit(document: &mut JsonValue, prefix: &str, f: &mut impl FnMut(&str, &mut JsonValue)) {
+        if let Some(global) = document.get_mut("global") {
+            f(&format!("{prefix}global"), global);
+        }
+
+        if let Some(JsonValue::Array(rules)) = document.get_mut("window_rules") {
+            for (i, rule) in rules.iter_mut().enumerate() {
+                if let Some(rule) = rule.get_mut("match") {
+                    f(&format!("{prefix}window_rules[{i}].match"), rule);
+                }
+            }
+        }
+    }
+
+    visit(document, "", f);
+
+    if let Some(JsonValue::Object(profiles)) = document.get_mut("profiles") {
+        for (name, profile) in profiles.iter_mut() {
+            visit(profile, &format!("profiles.{name}."), f);
+        }
+    }
+}
+
+/// Renames `alias` to `key` in `object`. If both are set, `key` wins and `alias` is dropped, as
+/// the config could not have been loaded like that anyway.
+fn rename_key(
+    object: &mut JsonValue,
+    path: &str,
+    alias: &str,
+    key: &str,
+    changes: &mut Vec<String>,
+) {
+    let Some(object) = object.as_object_mut() else {
+        return;
+    };
+    let Some(value) = object.remove(alias) else {
+        return;
+    };
+
+    if object.contains_key(key) {
+        changes.push(format!(
+            "removed `{path}.{alias}` since `{path}.{key}` is also set"
+        ));
+    } else {
+        object.insert(key.to_string(), value);
+        changes.push(format!("renamed `{path}.{alias}` to `{path}.{key}`"));
+    }
+}
+
+/// Returns the canonical spelling of an animation kind, e.g. `reverse_spiral` for
+/// `ReverseSpiral`.
+fn canonical_kind_name(kind: &str) -> Option<&'static str> {
+    let kind = AnimationKind::from_str(kind).ok()?;
+
+    ANIMATION_KIND_NAMES
+        .into_iter()
+        .find(|name| AnimationKind::from_str(name).ok().as_ref() == Some(&kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(json: &str) -> JsonValue {
+        serde_jsonc2::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn v1_delay_aliases_are_renamed() {
+        let mut v1 = document(
+            r#"{
+                "global": { "init_delay": 100, "restore_delay": 50 },
+                "window_rules": [
+                    { "match": { "kind": "Process", "value": "a", "init_delay": 0 } },
+                    { "match": { "kind": "Process", "value": "b", "restore_delay": 10, "unminimize_delay": 20 } }
+                ],
+                "profiles": { "calm": { "global": { "init_delay": 500 } } }
+            }"#,
+        );
+        let v2 = document(
+            r#"{
+                "global": { "initialize_delay": 100, "unminimize_delay": 50 },
+                "window_rules": [
+                    { "match": { "kind": "Process", "value": "a", "initialize_delay": 0 } },
+                    { "match": { "kind": "Process", "value": "b", "unminimize_delay": 20 } }
+                ],
+                "profiles": { "calm": { "global": { "initialize_delay": 500 } } },
+                "version": 2
+            }"#,
+        );
+
+        let migration = migrate(&mut v1, 1).unwrap().unwrap();
+
+        assert_eq!(v1, v2);
+        assert_eq!(migration.from, 1);
+        assert_eq!(
+            migration.changes,
+            [
+                "renamed `global.init_delay` to `global.initialize_delay`",
+                "renamed `global.restore_delay` to `global.unminimize_delay`",
+                "renamed `window_rules[0].match.init_delay` to `window_rules[0].match.initialize_delay`",
+                "removed `window_rules[1].match.restore_delay` since `window_rules[1].match.unminimize_delay` is also set",
+                "renamed `profiles.calm.global.init_delay` to `profiles.calm.global.initialize_delay`",
+                "set `version` to 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn v1_animation_kinds_are_respelled() {
+        let mut v1 = document(
+            r#"{
+                "global": { "animations": {
+                    "active": [{ "kind": "Spiral" }, { "kind": "ReverseSpiral" }, { "kind": "sparkle" }],
+                    "inactive": [{ "kind": "fade" }]
+                } }
+            }"#,
+        );
+        let v2 = document(
+            r#"{
+                "global": { "animations": {
+                    "active": [{ "kind": "spiral" }, { "kind": "reverse_spiral" }, { "kind": "sparkle" }],
+                    "inactive": [{ "kind": "fade" }]
+                } },
+                "version": 2
+            }"#,
+        );
+
+        let migration = migrate(&mut v1, 1).unwrap().unwrap();
+
+        assert_eq!(v1, v2);
+        assert_eq!(
+            migration.changes,
+            [
+                "changed `global.animations.active[0].kind` from `Spiral` to `spiral`",
+                "changed `global.animations.active[1].kind` from `ReverseSpiral` to `reverse_spiral`",
+                "set `version` to 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn current_documents_are_left_alone() {
+        let json = r#"{
+            "version": 2,
+            "global": { "init_delay": 100, "animations": { "active": [{ "kind": "Spiral" }] } }
+        }"#;
+        let mut v2 = document(json);
+
+        assert_eq!(migrate(&mut v2, 1), Ok(None));
+        assert_eq!(v2, document(json));
+    }
+
+    #[test]
+    fn documents_without_a_version_take_the_default() {
+        let json = r#"{ "global": { "init_delay": 100 } }"#;
+
+        let mut fragment = document(json);
+        assert_eq!(migrate(&mut fragment, CONFIG_VERSION), Ok(None));
+        assert_eq!(fragment, document(json));
+
+        let mut fragment = document(json);
+        assert_eq!(migrate(&mut fragment, 1).unwrap().unwrap().from, 1);
+    }
+
+    #[test]
+    fn invalid_and_newer_versions_are_errors() {
+        for json in [
+            r#"{ "version": 0 }"#,
+            r#"{ "version": "2" }"#,
+            r#"{ "version": 3 }"#,
+        ] {
+            let error = migrate(&mut document(json), 1).unwrap_err();
+            assert_eq!(error.path, "version", "{json}");
+        }
+    }
+}
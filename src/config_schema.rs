@@ -0,0 +1,33 @@
+use crate::user_config::UserConfig;
+use schema_jsonrs::schema_for;
+use serde_jsonc2::Value as JsonValue;
+
+/// Returns the JSON schema of the config file, generated from the config types.
+///
+/// This is the schema the config is checked against, and the one `schema.json` is written
+/// from.
+pub fn config_schema() -> JsonValue {
+    serde_jsonc2::to_value(schema_for!(UserConfig)).unwrap_or_default()
+}
+
+/// Returns the schema formatted the way `schema.json` is committed.
+pub fn config_schema_string() -> String {
+    let schema = config_schema();
+    let mut text = serde_jsonc2::to_string_pretty(&schema).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_file_is_up_to_date() {
+        // Compared as text rather than with `assert_eq!`, which would print both schemas.
+        assert!(
+            config_schema_string() == include_str!("../schema.json"),
+            "schema.json is out of date with the config types; run `just schema`"
+        );
+    }
+}
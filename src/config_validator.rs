@@ -0,0 +1,671 @@
+use crate::animation::AnimationsConfig;
+use crate::colors::parser::color_stop_parts;
+use crate::colors::parser::generate_gradient_stops;
+use crate::colors::parser::parse_color_string_with_theme;
+use crate::colors::parser::parse_gradient_direction;
+use crate::colors::GlobalColor;
+use crate::config_linter::lint_config;
+use crate::config_loader::ConfigLayers;
+use crate::config_migration::Migration;
+use crate::config_migration::CONFIG_VERSION;
+use crate::config_migration::DELAY_ALIASES;
+use crate::config_schema::config_schema;
+use crate::config_variables::resolve_variables;
+use crate::core::animation::parse_duration_str;
+use crate::core::animation::AnimationEasing;
+use crate::core::animation::AnimationKind;
+use crate::core::animation::ANIMATION_KIND_NAMES;
+use crate::core::animation::EASING_NAMES;
+use crate::core::duration::Duration;
+#[cfg(windows)]
+use crate::core::keybindings::Keybindings;
+use crate::diagnostics::join_path;
+use crate::diagnostics::Diagnostic;
+#[cfg(windows)]
+use crate::keyboard_hook::KeyboardHook;
+use crate::rule_engine::RuleEngine;
+use crate::user_config::ConfigFormat;
+use crate::user_config::UserConfig;
+use crate::user_config::WindowMatchConfig;
+use anyhow::anyhow;
+use anyhow::Result as AnyResult;
+use serde_jsonc2::Value as JsonValue;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+/// The configuration schema, used to detect unknown keys.
+///
+/// It is generated from the config types rather than read from `schema.json`, so it can't fall
+/// out of date with what the config actually accepts.
+static CONFIG_SCHEMA: LazyLock<JsonValue> = LazyLock::new(config_schema);
+
+/// The outcome of validating a configuration file: the parsed config (if it could be
+/// deserialized at all) and every problem found in it.
+#[derive(Debug)]
+pub struct ConfigReport {
+    pub config: Option<UserConfig>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Warnings about valid settings that never have any effect, such as shadowed rules.
+    pub lints: Vec<Diagnostic>,
+    /// Files that were written for an older version of the format, with what was changed to
+    /// migrate them.
+    pub migrations: Vec<(PathBuf, Migration)>,
+}
+
+impl ConfigReport {
+    /// Parses and validates `contents` together with every file it includes, with `profile`
+    /// applied on top, collecting every problem instead of stopping at the first one.
+    pub fn new(file: &Path, contents: &str, format: &ConfigFormat, profile: Option<&str>) -> Self {
+        let mut layers = ConfigLayers::load(file, contents, format);
+        let mut diagnostics = layers.diagnostics.clone();
+        let migrations = layers
+            .sources
+            .iter()
+            .filter_map(|source| Some((source.path.clone(), source.migration.clone()?)))
+            .collect();
+
+        if !layers.is_loaded() {
+            return Self {
+                config: None,
+                diagnostics,
+                lints: Vec::new(),
+                migrations,
+            };
+        }
+
+        // Unknown keys are reported against each file, since they are dropped while merging.
+        for source in layers.sources.iter() {
+            let mut source_diagnostics = Vec::new();
+            check_unknown_keys(
+                &source.document,
+                &CONFIG_SCHEMA,
+                "",
+                &mut source_diagnostics,
+            );
+            source.locate(&mut source_diagnostics);
+            diagnostics.extend(source_diagnostics);
+        }
+
+        let mut merged_diagnostics = Vec::new();
+        let mut lints = Vec::new();
+
+        // A missing profile is not fatal, since it may have just been removed from the config.
+        let profile = profile.filter(|name| {
+            let is_applied = layers.apply_profile(name);
+            if !is_applied {
+                merged_diagnostics.push(
+                    Diagnostic::warning("profiles", format!("profile `{name}` does not exist"))
+                        .with_help("the config is used without a profile"),
+                );
+            }
+            is_applied
+        });
+
+        let mut document = layers.document.clone();
+        resolve_variables(&mut document, &mut merged_diagnostics);
+
+        let config = match serde_path_to_error::deserialize::<_, UserConfig>(document) {
+            Ok(mut config) => {
+                check_config(&config, &mut merged_diagnostics);
+                lint_config(&config, &mut lints);
+                config.sources = layers.paths();
+                config.active_profile = profile.map(str::to_string);
+                Some(config)
+            }
+            Err(err) => {
+                let path = err.path().to_string();
+                let path = if path == "." { String::new() } else { path };
+                merged_diagnostics.push(Diagnostic::error(path, err.into_inner().to_string()));
+                None
+            }
+        };
+
+        layers.locate(&mut merged_diagnostics);
+        layers.locate(&mut lints);
+        diagnostics.extend(merged_diagnostics);
+        diagnostics.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+        lints.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+
+        Self {
+            config,
+            diagnostics,
+            lints,
+            migrations,
+        }
+    }
+
+    /// Logs every warning and migration and returns the config, or an error listing every
+    /// error found.
+    pub fn into_result(self) -> AnyResult<UserConfig> {
+        for (file, migration) in self.migrations.iter() {
+            info!(
+                "migrated {} from config version {} to {CONFIG_VERSION}",
+                file.display(),
+                migration.from
+            );
+            for change in migration.changes.iter() {
+                info!("{}: {change}", file.display());
+            }
+            info!("run `tacky-borders migrate --write` to save the migrated config");
+        }
+        for warning in self.diagnostics.iter().filter(|d| !d.is_error()) {
+            warn!("{warning}");
+        }
+        for lint in self.lints.iter() {
+            warn!("{lint}");
+        }
+
+        let errors: Vec<String> = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.is_error())
+            .map(|d| d.to_string())
+            .collect();
+
+        match self.config {
+            Some(config) if errors.is_empty() => Ok(config),
+            _ => Err(anyhow!("invalid config:\n{}", errors.join("\n"))),
+        }
+    }
+}
+
+/// Follows `$ref`s into the `$defs` of the schema.
+fn resolve_ref(schema: &JsonValue) -> &JsonValue {
+    let mut schema = schema;
+
+    while let Some(name) = schema
+        .get("$ref")
+        .and_then(JsonValue::as_str)
+        .and_then(|reference| reference.strip_prefix("#/$defs/"))
+    {
+        match CONFIG_SCHEMA.get("$defs").and_then(|defs| defs.get(name)) {
+            Some(definition) => schema = definition,
+            None => break,
+        }
+    }
+
+    schema
+}
+
+/// Finds the schema that has `keyword`, looking through `anyOf`/`oneOf` alternatives.
+fn find_schema<'a>(schema: &'a JsonValue, keyword: &str) -> Option<&'a JsonValue> {
+    let schema = resolve_ref(schema);
+    if schema.get(keyword).is_some() {
+        return Some(schema);
+    }
+
+    ["anyOf", "oneOf", "allOf"]
+        .iter()
+        .filter_map(|combinator| schema.get(combinator)?.as_array())
+        .flatten()
+        .find_map(|alternative| find_schema(alternative, keyword))
+}
+
+/// Reports every key that the schema does not know about.
+fn check_unknown_keys(
+    value: &JsonValue,
+    schema: &JsonValue,
+    path: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match value {
+        JsonValue::Object(map) => {
+            let Some(properties) = find_schema(schema, "properties")
+                .and_then(|schema| schema.get("properties"))
+                .and_then(JsonValue::as_object)
+            else {
+                // Maps such as `profiles` describe every value with `additionalProperties`.
+                if let Some(value_schema) = find_schema(schema, "additionalProperties")
+                    .and_then(|schema| schema.get("additionalProperties"))
+                    .filter(|schema| schema.is_object())
+                {
+                    for (key, child) in map {
+                        check_unknown_keys(child, value_schema, &join_path(path, key), diagnostics);
+                    }
+                }
+                return;
+            };
+
+            for (key, child) in map {
+                let child_path = join_path(path, key);
+                match properties.get(key) {
+                    Some(property) => check_unknown_keys(child, property, &child_path, diagnostics),
+                    None if path.is_empty() && key == "$schema" => {}
+                    None => {
+                        // Older configs are migrated before they get here, so a dropped key comes
+                        // from a config that claims to be current.
+                        let renamed = DELAY_ALIASES.iter().find(|(alias, _)| alias == key);
+                        diagnostics.push(match renamed {
+                            Some((_, renamed)) => Diagnostic::error(
+                                child_path,
+                                format!("`{key}` was renamed to `{renamed}` in config version 2"),
+                            )
+                            .with_help(format!("use `{renamed}` instead")),
+                            None => Diagnostic::warning(child_path, format!("unknown key `{key}`"))
+                                .with_suggestion(key, properties.keys().map(String::as_str)),
+                        });
+                    }
+                }
+            }
+        }
+        JsonValue::Array(items) => {
+            let Some(items_schema) =
+                find_schema(schema, "items").and_then(|schema| schema.get("items"))
+            else {
+                return;
+            };
+
+            for (i, item) in items.iter().enumerate() {
+                check_unknown_keys(item, items_schema, &format!("{path}[{i}]"), diagnostics);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Checks the values that deserialize fine but would otherwise fail (or be ignored) later on.
+fn check_config(config: &UserConfig, diagnostics: &mut Vec<Diagnostic>) {
+    let theme = config.theme.as_deref();
+    let global = &config.global_rule;
+
+    check_color(
+        &global.active_color,
+        "global.active_color",
+        theme,
+        diagnostics,
+    );
+    check_color(
+        &global.inactive_color,
+        "global.inactive_color",
+        theme,
+        diagnostics,
+    );
+    check_animations(&global.animations, "global.animations", diagnostics);
+
+    for (i, rule) in config.window_rules.iter().enumerate() {
+        let path = format!("window_rules[{i}].match");
+        check_rule_settings(&rule.match_window, &path, theme, diagnostics);
+    }
+
+    for (name, monitor) in config.monitors.iter() {
+        let path = join_path("monitors", name);
+        check_rule_settings(&monitor.settings(), &path, theme, diagnostics);
+    }
+
+    #[cfg(windows)]
+    check_keybindings(&config.keybindings, diagnostics);
+
+    // Reports invalid regexes and incomplete match conditions.
+    RuleEngine::compile(config, diagnostics);
+}
+
+/// Checks the colors and animations a window rule or monitor override sets.
+fn check_rule_settings(
+    rule: &WindowMatchConfig,
+    path: &str,
+    theme: Option<&str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Some(ref active_color) = rule.active_color {
+        let path = join_path(path, "active_color");
+        check_color(active_color, &path, theme, diagnostics);
+    }
+    if let Some(ref inactive_color) = rule.inactive_color {
+        let path = join_path(path, "inactive_color");
+        check_color(inactive_color, &path, theme, diagnostics);
+    }
+    if let Some(ref animations) = rule.animations {
+        let path = join_path(path, "animations");
+        check_animations(animations, &path, diagnostics);
+    }
+}
+
+fn check_color(
+    color: &GlobalColor,
+    path: &str,
+    theme: Option<&str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match color {
+        GlobalColor::String(s) => {
+            if let Err(err) = parse_color_string_with_theme(s, theme) {
+                diagnostics.push(Diagnostic::error(path, format!("invalid color: {err}")));
+            }
+        }
+        GlobalColor::Mapping(mapping) => {
+            let mut colors_valid = true;
+            for (i, stop) in mapping.colors.iter().enumerate() {
+                let result = color_stop_parts(stop)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|(color, _)| parse_color_string_with_theme(color, theme));
+
+                if let Err(err) = result {
+                    colors_valid = false;
+                    diagnostics.push(Diagnostic::error(
+                        format!("{path}.colors[{i}]"),
+                        format!("invalid color: {err}"),
+                    ));
+                }
+            }
+
+            // Only checked once every color is valid, so each problem is reported once.
+            if colors_valid && mapping.colors.len() > 1 {
+                if let Err(err) = generate_gradient_stops(&mapping.colors, theme) {
+                    diagnostics.push(Diagnostic::error(
+                        join_path(path, "colors"),
+                        format!("invalid gradient: {err}"),
+                    ));
+                }
+            }
+
+            if let Err(err) = parse_gradient_direction(&mapping.direction) {
+                diagnostics.push(Diagnostic::error(
+                    join_path(path, "direction"),
+                    err.to_string(),
+                ));
+            }
+        }
+    }
+}
+
+fn check_animations(animations: &AnimationsConfig, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let groups = [
+        ("active", &animations.active),
+        ("inactive", &animations.inactive),
+    ];
+
+    for (group, animations) in groups {
+        for (i, animation) in animations.iter().flatten().enumerate() {
+            let path = format!("{path}.{group}[{i}]");
+
+            if AnimationKind::from_str(&animation.kind).is_err() {
+                diagnostics.push(
+                    Diagnostic::error(
+                        join_path(&path, "kind"),
+                        format!("unknown animation kind `{}`", animation.kind),
+                    )
+                    .with_suggestion(&animation.kind, ANIMATION_KIND_NAMES),
+                );
+            }
+
+            match animation.duration {
+                Some(Duration::Text(ref duration)) => {
+                    if let Err(err) = parse_duration_str(duration) {
+                        diagnostics.push(
+                            Diagnostic::error(join_path(&path, "duration"), err.to_string())
+                                .with_help("use milliseconds or a string like `450ms` or `1.5s`"),
+                        );
+                    }
+                }
+                Some(Duration::Number(duration)) if duration <= 0.0 => {
+                    diagnostics.push(Diagnostic::error(
+                        join_path(&path, "duration"),
+                        "duration must be greater than zero",
+                    ));
+                }
+                _ => {}
+            }
+
+            if let Some(ref easing) = animation.easing {
+                if let Err(err) = AnimationEasing::from_str(easing) {
+                    diagnostics.push(
+                        Diagnostic::error(join_path(&path, "easing"), err)
+                            .with_suggestion(easing, EASING_NAMES),
+                    );
+                }
+            }
+        }
+    }
+
+    if animations.fps.is_some_and(|fps| fps <= 0) {
+        diagnostics.push(Diagnostic::error(
+            join_path(path, "fps"),
+            "fps must be greater than zero",
+        ));
+    }
+}
+
+/// Key names map to Windows virtual-key codes, so they can only be checked on Windows.
+#[cfg(windows)]
+fn check_keybindings(keybindings: &Keybindings, diagnostics: &mut Vec<Diagnostic>) {
+    let bindings = [
+        ("reload", Some(&keybindings.reload)),
+        ("open_config", Some(&keybindings.open_config)),
+        ("exit", Some(&keybindings.exit)),
+        ("revert", keybindings.revert.as_ref()),
+        ("cycle_profile", keybindings.cycle_profile.as_ref()),
+    ];
+
+    for (name, binding) in bindings {
+        let Some(binding) = binding else {
+            continue;
+        };
+
+        for key in binding.split('+').filter(|key| !key.is_empty()) {
+            if KeyboardHook::key_to_vk_code(key).is_none() {
+                diagnostics.push(Diagnostic::warning(
+                    format!("keybindings.{name}"),
+                    format!("unrecognized key `{key}`"),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(contents: &str) -> ConfigReport {
+        ConfigReport::new(
+            Path::new("config.jsonc"),
+            contents,
+            &ConfigFormat::Jsonc,
+            None,
+        )
+    }
+
+    /// Returns the diagnostic reported at `path`, failing the test if there is none.
+    fn diagnostic<'a>(report: &'a ConfigReport, path: &str) -> &'a Diagnostic {
+        report
+            .diagnostics
+            .iter()
+            .find(|d| d.path == path)
+            .unwrap_or_else(|| panic!("nothing reported at {path}: {:?}", report.diagnostics))
+    }
+
+    #[test]
+    fn valid_config_has_no_diagnostics() {
+        let report = report(
+            r##"{
+  "global": { "border_width": 2, "active_color": "#89b4fa" },
+  "window_rules": [{ "match": { "kind": "Title", "value": "a", "enabled": false } }]
+}"##,
+        );
+
+        assert!(report.config.is_some());
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+    }
+
+    #[test]
+    fn unknown_key_suggests_the_closest_one() {
+        let report = report(
+            r#"{
+  "global": {
+    "boder_width": 2
+  }
+}"#,
+        );
+        let diagnostic = diagnostic(&report, "global.boder_width");
+
+        assert!(!diagnostic.is_error());
+        assert_eq!(
+            diagnostic.help.as_deref(),
+            Some("did you mean `border_width`?")
+        );
+        assert_eq!((diagnostic.line, diagnostic.column), (3, 5));
+        // Unknown keys are warnings, so the config still loads.
+        assert!(report.config.is_some());
+    }
+
+    #[test]
+    fn unknown_key_in_a_rule_match_has_the_rule_path() {
+        let report = report(
+            r#"{
+  "window_rules": [
+    { "match": { "kind": "Title", "value": "a" } },
+    { "match": { "kind": "Class", "value": "b", "colour": "red" } }
+  ]
+}"#,
+        );
+        let diagnostic = diagnostic(&report, "window_rules[1].match.colour");
+
+        assert_eq!((diagnostic.line, diagnostic.column), (4, 49));
+    }
+
+    #[test]
+    fn misspelled_easing_suggests_the_closest_one() {
+        let report = report(
+            r#"{
+  "global": {
+    "animations": {
+      "active": [{ "kind": "fade", "easing": "EaseInOutCubc" }]
+    }
+  }
+}"#,
+        );
+        let diagnostic = diagnostic(&report, "global.animations.active[0].easing");
+
+        assert!(diagnostic.is_error());
+        assert_eq!(
+            diagnostic.help.as_deref(),
+            Some("did you mean `EaseInOutCubic`?")
+        );
+        assert_eq!(diagnostic.line, 4);
+    }
+
+    #[test]
+    fn misspelled_animation_kind_suggests_the_closest_one() {
+        let report =
+            report(r#"{ "global": { "animations": { "inactive": [{ "kind": "spirl" }] } } }"#);
+        let diagnostic = diagnostic(&report, "global.animations.inactive[0].kind");
+
+        assert_eq!(diagnostic.help.as_deref(), Some("did you mean `spiral`?"));
+    }
+
+    #[test]
+    fn invalid_rule_color_is_an_error() {
+        let report = report(
+            r#"{
+  "window_rules": [
+    { "match": { "kind": "Title", "value": "a", "active_color": "not a color" } }
+  ]
+}"#,
+        );
+
+        assert!(diagnostic(&report, "window_rules[0].match.active_color").is_error());
+        assert!(report.into_result().is_err());
+    }
+
+    #[test]
+    fn dropped_delay_keys_are_errors_in_current_configs() {
+        let report = report(
+            r#"{
+  "version": 2,
+  "global": { "init_delay": 100 }
+}"#,
+        );
+
+        let diagnostic = diagnostic(&report, "global.init_delay");
+        assert!(diagnostic.is_error());
+        assert_eq!(
+            diagnostic.message,
+            "`init_delay` was renamed to `initialize_delay` in config version 2"
+        );
+        assert!(report.into_result().is_err());
+    }
+
+    #[test]
+    fn delay_aliases_in_older_configs_are_migrated() {
+        let report = report(r#"{ "global": { "init_delay": 100 } }"#);
+
+        assert!(report.diagnostics.iter().all(|d| !d.is_error()));
+        assert_eq!(report.config.unwrap().global_rule.initialize_delay, 100);
+    }
+
+    const PROFILES: &str = r##"{
+  "global": { "border_width": 2, "active_color": "#89b4fa" },
+  "monitors": { "1": { "border_width": 3 }, "2": { "border_offset": 1 } },
+  "window_rules": [{ "match": { "kind": "Process", "value": "a", "enabled": false } }],
+  "profiles": {
+    "meeting": { "global": { "border_width": 1 } },
+    "focus": {
+      "monitors": { "1": { "border_offset": 4 } },
+      "window_rules": [
+        { "match": { "kind": "Process", "value": "b", "enabled": false } },
+        { "match": { "kind": "Title", "value": "(", "strategy": "Regex" } }
+      ]
+    }
+  }
+}"##;
+
+    fn profile_report(profile: &str) -> ConfigReport {
+        ConfigReport::new(
+            Path::new("config.jsonc"),
+            PROFILES,
+            &ConfigFormat::Jsonc,
+            Some(profile),
+        )
+    }
+
+    #[test]
+    fn profiles_override_global_and_keep_the_rest() {
+        let report = profile_report("meeting");
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+
+        let config = report.config.unwrap();
+        assert_eq!(config.active_profile.as_deref(), Some("meeting"));
+        assert_eq!(config.global_rule.border_width, 1);
+        assert_eq!(
+            config.global_rule.active_color,
+            GlobalColor::String("#89b4fa".to_string())
+        );
+        assert_eq!(config.window_rules.len(), 1);
+    }
+
+    #[test]
+    fn profile_window_rules_replace_the_configs() {
+        let report = profile_report("focus");
+        let config = report.config.as_ref().unwrap();
+
+        let values: Vec<_> = config
+            .window_rules
+            .iter()
+            .map(|rule| rule.match_window.condition.match_value.as_deref())
+            .collect();
+        assert_eq!(values, [Some("b"), Some("(")]);
+        assert_eq!(config.monitors["1"].border_width, Some(3));
+        assert_eq!(config.monitors["1"].border_offset, Some(4));
+        assert_eq!(config.monitors["2"].border_offset, Some(1));
+
+        // Problems in the profile's rules point into the profile.
+        let diagnostic = diagnostic(&report, "profiles.focus.window_rules[1].match.value");
+        assert_eq!((diagnostic.line, diagnostic.column), (11, 39));
+    }
+
+    #[test]
+    fn unknown_profiles_are_ignored_with_a_warning() {
+        let report = profile_report("gaming");
+
+        let diagnostic = diagnostic(&report, "profiles");
+        assert!(!diagnostic.is_error());
+        assert_eq!(diagnostic.message, "profile `gaming` does not exist");
+
+        let config = report.config.unwrap();
+        assert_eq!(config.active_profile, None);
+        assert_eq!(config.global_rule.border_width, 2);
+        assert_eq!(config.window_rules.len(), 1);
+    }
+}
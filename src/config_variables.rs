@@ -0,0 +1,468 @@
+use crate::diagnostics::join_path;
+use crate::diagnostics::Diagnostic;
+use schema_jsonrs::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_jsonc2::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::env;
+
+/// Top-level keys that define variables. `variables` wins if both define the same name.
+const VARIABLE_KEYS: [&str; 2] = ["palette", "variables"];
+
+/// A value defined under `variables` or `palette`.
+///
+/// A string that is exactly `$name` is replaced by the variable as-is, so variables can hold
+/// numbers, lists or whole color mappings as well as plain strings.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum Variable {
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Variable>),
+    Map(BTreeMap<String, Variable>),
+}
+
+/// Which references a string value can contain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum References {
+    /// `$name`, `${name}` and `${env:NAME}`, with `$$` for a literal `$`.
+    All,
+    /// Only `${name}` and `${env:NAME}`. Used for regex match values, where `$` is an anchor,
+    /// so existing regexes keep matching what they did.
+    Braced,
+}
+
+/// A piece of a string value: either literal text or a reference to substitute.
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Text(String),
+    /// `$name` or `${name}`.
+    Variable(&'a str),
+    /// `${env:NAME}`.
+    Env(&'a str),
+}
+
+/// Replaces every `$name`, `${name}` and `${env:NAME}` reference in `document`. In the
+/// `value` of a match with `"strategy": "Regex"`, only `${name}` and `${env:NAME}` are
+/// references.
+///
+/// References that cannot be resolved are reported and left untouched, so the rest of the
+/// document can still be checked.
+pub fn resolve_variables(document: &mut JsonValue, diagnostics: &mut Vec<Diagnostic>) {
+    let mut resolver = Resolver::default();
+
+    for key in VARIABLE_KEYS {
+        if let Some(JsonValue::Object(variables)) = document.get(key) {
+            for (name, value) in variables {
+                resolver
+                    .definitions
+                    .insert(name.clone(), (join_path(key, name), value.clone()));
+            }
+        }
+    }
+
+    // Report problems in the definitions themselves once, where they are defined.
+    let names: Vec<String> = resolver.definitions.keys().cloned().collect();
+    for name in names {
+        if let Err(diagnostic) = resolver.variable(&name) {
+            let path = resolver.definitions[&name].0.clone();
+            diagnostics.push(Diagnostic { path, ..diagnostic });
+        }
+    }
+
+    let JsonValue::Object(root) = document else {
+        return;
+    };
+
+    for (key, value) in root.iter_mut() {
+        match VARIABLE_KEYS.contains(&key.as_str()) {
+            true => {
+                if let JsonValue::Object(variables) = value {
+                    for (name, value) in variables.iter_mut() {
+                        if let Some(Some(resolved)) = resolver.resolved.get(name) {
+                            *value = resolved.clone();
+                        }
+                    }
+                }
+            }
+            false => resolver.resolve_value(value, key, diagnostics),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Resolver {
+    /// Every variable by name, along with the key path it is defined at.
+    definitions: BTreeMap<String, (String, JsonValue)>,
+    /// Resolved variables; `None` if the variable could not be resolved (already reported).
+    resolved: BTreeMap<String, Option<JsonValue>>,
+    /// Variables that are currently being resolved, used to detect cycles.
+    resolving: Vec<String>,
+}
+
+impl Resolver {
+    /// Returns the fully resolved value of a variable, or `None` if it failed to resolve
+    /// earlier.
+    fn variable(&mut self, name: &str) -> Result<Option<JsonValue>, Diagnostic> {
+        if let Some(resolved) = self.resolved.get(name) {
+            return Ok(resolved.clone());
+        }
+
+        if self.resolving.iter().any(|resolving| resolving == name) {
+            return Err(Diagnostic::error(
+                "",
+                format!("variable `${name}` references itself"),
+            ));
+        }
+
+        let Some((_, definition)) = self.definitions.get(name) else {
+            return Err(Diagnostic::error("", format!("unknown variable `${name}`"))
+                .with_suggestion(name, self.definitions.keys().map(String::as_str)));
+        };
+
+        let mut value = definition.clone();
+        let mut errors = Vec::new();
+
+        self.resolving.push(name.to_string());
+        self.resolve_value(&mut value, "", &mut errors);
+        self.resolving.pop();
+
+        match errors.into_iter().next() {
+            Some(error) => {
+                self.resolved.insert(name.to_string(), None);
+                Err(error)
+            }
+            None => {
+                self.resolved.insert(name.to_string(), Some(value.clone()));
+                Ok(Some(value))
+            }
+        }
+    }
+
+    fn resolve_value(
+        &mut self,
+        value: &mut JsonValue,
+        path: &str,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        match value {
+            JsonValue::String(_) => {
+                self.resolve_string_value(value, path, References::All, diagnostics)
+            }
+            JsonValue::Array(items) => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    self.resolve_value(item, &format!("{path}[{i}]"), diagnostics);
+                }
+            }
+            JsonValue::Object(map) => {
+                let is_regex = map.get("strategy").and_then(JsonValue::as_str) == Some("Regex");
+
+                for (key, child) in map.iter_mut() {
+                    let child_path = join_path(path, key);
+                    match is_regex && key == "value" {
+                        true => self.resolve_string_value(
+                            child,
+                            &child_path,
+                            References::Braced,
+                            diagnostics,
+                        ),
+                        false => self.resolve_value(child, &child_path, diagnostics),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Resolves the references in `value` if it is a string.
+    fn resolve_string_value(
+        &mut self,
+        value: &mut JsonValue,
+        path: &str,
+        references: References,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let JsonValue::String(s) = value else {
+            return;
+        };
+
+        match self.resolve_string(s, references) {
+            Ok(Some(resolved)) => *value = resolved,
+            Ok(None) => {}
+            Err(diagnostic) => diagnostics.push(Diagnostic {
+                path: path.to_string(),
+                ..diagnostic
+            }),
+        }
+    }
+
+    /// Returns the value a string resolves to, or `None` if it should be left as is.
+    fn resolve_string(
+        &mut self,
+        s: &str,
+        references: References,
+    ) -> Result<Option<JsonValue>, Diagnostic> {
+        if !s.contains('$') {
+            return Ok(None);
+        }
+
+        let segments =
+            parse_references(s, references).map_err(|message| Diagnostic::error("", message))?;
+
+        // A lone reference keeps the type of the variable (e.g. a number or a color mapping).
+        if let [Segment::Variable(name)] = segments.as_slice() {
+            return self.variable(name);
+        }
+
+        let mut resolved = String::new();
+
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => resolved.push_str(&text),
+                Segment::Env(name) => match env::var(name) {
+                    Ok(value) => resolved.push_str(&value),
+                    Err(_) => {
+                        return Err(Diagnostic::error(
+                            "",
+                            format!("environment variable `{name}` is not set"),
+                        ))
+                    }
+                },
+                Segment::Variable(name) => match self.variable(name)? {
+                    Some(JsonValue::String(value)) => resolved.push_str(&value),
+                    Some(JsonValue::Number(value)) => resolved.push_str(&value.to_string()),
+                    Some(JsonValue::Bool(value)) => resolved.push_str(&value.to_string()),
+                    Some(_) => {
+                        return Err(Diagnostic::error(
+                            "",
+                            format!("variable `${name}` is not a string or number, so it can only be used on its own"),
+                        ))
+                    }
+                    None => return Ok(None),
+                },
+            }
+        }
+
+        Ok(Some(JsonValue::String(resolved)))
+    }
+}
+
+/// Splits a string into literal text and references. `$$` is a literal `$`, and a `$` that is
+/// not followed by a name (e.g. the end anchor of a regex) is kept as is. With
+/// `References::Braced`, every `$` that isn't followed by `{` is kept as is.
+fn parse_references(s: &str, references: References) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = s;
+
+    while let Some(start) = rest.find('$') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(after) = after.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("missing `}}` after `${{` in `{s}`"))?;
+
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+
+            let name = &after[..end];
+            segments.push(match name.strip_prefix("env:") {
+                Some(name) => Segment::Env(name),
+                None => Segment::Variable(name),
+            });
+            rest = &after[end + 1..];
+        } else if references == References::Braced {
+            text.push('$');
+            rest = after;
+        } else if let Some(after) = after.strip_prefix('$') {
+            text.push('$');
+            rest = after;
+        } else if after.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(after.len());
+
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+
+            segments.push(Segment::Variable(&after[..end]));
+            rest = &after[end..];
+        } else {
+            text.push('$');
+            rest = after;
+        }
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(json: &str) -> (JsonValue, Vec<Diagnostic>) {
+        let mut document: JsonValue = serde_jsonc2::from_str(json).unwrap();
+        let mut diagnostics = Vec::new();
+        resolve_variables(&mut document, &mut diagnostics);
+        (document, diagnostics)
+    }
+
+    fn resolved(json: &str, path: &str) -> JsonValue {
+        let (document, diagnostics) = resolve(json);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        document.pointer(path).unwrap().clone()
+    }
+
+    #[test]
+    fn lone_references_keep_their_type() {
+        let json = r##"{
+            "variables": { "width": 3, "colors": { "active": "#fff" } },
+            "global": { "border_width": "$width", "active_color": "${colors}" }
+        }"##;
+
+        assert_eq!(resolved(json, "/global/border_width"), 3.0);
+        assert_eq!(
+            resolved(json, "/global/active_color/active"),
+            JsonValue::from("#fff")
+        );
+    }
+
+    #[test]
+    fn references_inside_text() {
+        let json = r##"{
+            "palette": { "blue": "#89b4fa", "mauve": "#cba6f7" },
+            "variables": { "accent": "linear-gradient(135deg, $blue, $mauve)" },
+            "global": { "active_color": "$accent", "inactive_color": "${blue}80" }
+        }"##;
+
+        assert_eq!(
+            resolved(json, "/global/active_color"),
+            "linear-gradient(135deg, #89b4fa, #cba6f7)"
+        );
+        assert_eq!(resolved(json, "/global/inactive_color"), "#89b4fa80");
+    }
+
+    #[test]
+    fn double_dollar_is_a_literal_dollar() {
+        let json = r##"{ "variables": { "a": 1 }, "global": { "name": "$$a costs $$5" } }"##;
+
+        assert_eq!(resolved(json, "/global/name"), "$a costs $5");
+    }
+
+    #[test]
+    fn dollar_without_a_name_is_kept() {
+        let json = r##"{ "global": { "name": "5$ and $" } }"##;
+
+        assert_eq!(resolved(json, "/global/name"), "5$ and $");
+    }
+
+    #[test]
+    fn environment_variables() {
+        let json = r##"{ "global": { "name": "${env:CARGO_PKG_NAME}!" } }"##;
+
+        assert_eq!(
+            resolved(json, "/global/name"),
+            format!("{}!", env!("CARGO_PKG_NAME"))
+        );
+    }
+
+    #[test]
+    fn unset_environment_variables_are_reported() {
+        let json = r##"{ "global": { "name": "${env:TACKY_BORDERS_UNSET_VARIABLE}" } }"##;
+        let (_, diagnostics) = resolve(json);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, "global.name");
+        assert!(diagnostics[0].message.contains("is not set"));
+    }
+
+    #[test]
+    fn unknown_variables_are_reported_with_a_suggestion() {
+        let json = r##"{
+            "palette": { "blue": "#89b4fa" },
+            "global": { "active_color": "$bleu" }
+        }"##;
+        let (document, diagnostics) = resolve(json);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, "global.active_color");
+        assert_eq!(diagnostics[0].message, "unknown variable `$bleu`");
+        assert!(diagnostics[0].help.as_ref().unwrap().contains("blue"));
+        assert_eq!(document.pointer("/global/active_color").unwrap(), "$bleu");
+    }
+
+    #[test]
+    fn cycles_are_reported_where_they_are_defined() {
+        let json = r##"{
+            "variables": { "a": "$b", "b": "$a" },
+            "global": { "active_color": "$a" }
+        }"##;
+        let (_, diagnostics) = resolve(json);
+
+        assert!(!diagnostics.is_empty());
+        assert_eq!(diagnostics[0].path, "variables.a");
+        assert_eq!(diagnostics[0].message, "variable `$a` references itself");
+        // The definition was already reported, so its uses aren't reported again.
+        assert!(diagnostics.iter().all(|d| d.path != "global.active_color"));
+    }
+
+    #[test]
+    fn regex_values_keep_their_anchors() {
+        let json = r##"{
+            "variables": { "x": "nope" },
+            "window_rules": [
+                { "match": { "kind": "Title", "value": "foo$", "strategy": "Regex" } },
+                { "match": { "any": [
+                    { "kind": "Class", "value": "^(a|b)$x", "strategy": "Regex" },
+                    { "kind": "Class", "value": "$$x", "strategy": "Regex" }
+                ] } }
+            ]
+        }"##;
+
+        assert_eq!(resolved(json, "/window_rules/0/match/value"), "foo$");
+        assert_eq!(
+            resolved(json, "/window_rules/1/match/any/0/value"),
+            "^(a|b)$x"
+        );
+        assert_eq!(resolved(json, "/window_rules/1/match/any/1/value"), "$$x");
+    }
+
+    #[test]
+    fn regex_values_take_braced_references() {
+        let json = r##"{
+            "variables": { "browser": "firefox" },
+            "window_rules": [
+                { "match": { "kind": "Process", "value": "^${browser}$", "strategy": "Regex" } },
+                { "match": { "kind": "Process", "value": "$browser", "strategy": "Equals" } }
+            ]
+        }"##;
+
+        assert_eq!(resolved(json, "/window_rules/0/match/value"), "^firefox$");
+        assert_eq!(resolved(json, "/window_rules/1/match/value"), "firefox");
+    }
+
+    #[test]
+    fn parse_braced_references() {
+        assert_eq!(
+            parse_references("^${a}$|${env:B}", References::Braced).unwrap(),
+            vec![
+                Segment::Text("^".to_string()),
+                Segment::Variable("a"),
+                Segment::Text("$|".to_string()),
+                Segment::Env("B"),
+            ]
+        );
+        assert!(parse_references("${a", References::All).is_err());
+    }
+}
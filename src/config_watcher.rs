@@ -7,6 +7,7 @@ use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
 use std::time::Instant;
@@ -47,16 +48,16 @@ impl<T> Drop for ThreadHandle<T> {
 
 #[derive(Debug)]
 pub struct ConfigWatcher {
-    config_path: PathBuf,
+    config_paths: Arc<Mutex<Vec<PathBuf>>>,
     running: Arc<AtomicBool>,
     timeout: Duration,
     thread: ThreadHandle<()>,
 }
 
 impl ConfigWatcher {
-    pub fn new(config_path: PathBuf, timeout: Duration) -> Self {
+    pub fn new(config_paths: Vec<PathBuf>, timeout: Duration) -> Self {
         Self {
-            config_path,
+            config_paths: Arc::new(Mutex::new(config_paths)),
             running: Arc::new(AtomicBool::new(false)),
             timeout,
             thread: ThreadHandle::new(None),
@@ -67,7 +68,8 @@ impl ConfigWatcher {
         match result {
             Ok(events) => {
                 for event in events {
-                    if event.kind.is_modify() {
+                    // Creating or removing a file matters for the `rules.d` directory.
+                    if event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove() {
                         let is_reloaded = UserConfig::reload();
                         if is_reloaded {
                             break;
@@ -80,10 +82,16 @@ impl ConfigWatcher {
     }
 
     pub fn start(&mut self) -> anyhow::Result<()> {
-        if !self.config_path.exists() {
+        if let Some(config_path) = self
+            .config_paths
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|config_path| !config_path.exists())
+        {
             return Err(anyhow!(
                 "configuration file does not exist: {}",
-                self.config_path.display()
+                config_path.display()
             ));
         }
 
@@ -94,7 +102,7 @@ impl ConfigWatcher {
         debug!("configuration watcher has started.");
 
         let running = Arc::clone(&self.running);
-        let config_path = self.config_path.clone();
+        let config_paths = Arc::clone(&self.config_paths);
         let timeout = self.timeout;
         let debounce = Duration::from_millis(500);
 
@@ -103,18 +111,34 @@ impl ConfigWatcher {
                 let mut debouncer = new_debouncer(timeout, None, Self::handle_events)
                     .map_err(|e| anyhow!("failed to create debouncer: {:?}", e))?;
 
-                debug!(
-                    "watching configuration file: {}",
-                    config_path.display().to_string()
-                );
-
-                debouncer
-                    .watch(config_path.as_path(), RecursiveMode::Recursive)
-                    .map_err(|e| anyhow!("failed to watch config path: {:?}", e))?;
-
+                let mut watched_paths: Vec<PathBuf> = Vec::new();
                 let mut last_checked = Instant::now();
 
                 while running.load(Ordering::SeqCst) {
+                    // The set of files can change on every reload (e.g. a new include), so
+                    // keep the watched paths in sync with the latest config.
+                    let current_paths = config_paths.lock().unwrap().clone();
+
+                    if current_paths != watched_paths {
+                        for path in watched_paths.iter().filter(|p| !current_paths.contains(p)) {
+                            if let Err(e) = debouncer.unwatch(path.as_path()) {
+                                error!("failed to unwatch {}: {}", path.display(), e);
+                            }
+                        }
+
+                        for path in current_paths.iter().filter(|p| !watched_paths.contains(p)) {
+                            debug!("watching configuration file: {}", path.display());
+
+                            if let Err(e) =
+                                debouncer.watch(path.as_path(), RecursiveMode::NonRecursive)
+                            {
+                                error!("failed to watch config path: {:?}", e);
+                            }
+                        }
+
+                        watched_paths = current_paths;
+                    }
+
                     let elapsed = last_checked.elapsed();
                     if elapsed < debounce {
                         thread::sleep(debounce - elapsed);
@@ -122,9 +146,11 @@ impl ConfigWatcher {
                     last_checked = Instant::now();
                 }
 
-                debouncer
-                    .unwatch(config_path.as_path())
-                    .map_err(|e| anyhow!("failed to unwatch: {}", e))?;
+                for path in watched_paths.iter() {
+                    debouncer
+                        .unwatch(path.as_path())
+                        .map_err(|e| anyhow!("failed to unwatch: {}", e))?;
+                }
 
                 debug!("configuration watcher stopped gracefully");
                 Ok(())
@@ -136,6 +162,11 @@ impl ConfigWatcher {
         Ok(())
     }
 
+    /// Replaces the watched files. A running watcher picks up the change on its next check.
+    pub fn set_paths(&self, config_paths: Vec<PathBuf>) {
+        *self.config_paths.lock().unwrap() = config_paths;
+    }
+
     pub fn stop(&mut self) -> anyhow::Result<()> {
         if !self.running.load(Ordering::SeqCst) {
             debug!("config watcher is not running; skipping cleanup");
@@ -1,7 +1,12 @@
 use anyhow::anyhow;
 use regex::Regex;
+use schema_jsonrs::json_schema;
+use schema_jsonrs::JsonSchema;
+use schema_jsonrs::Schema;
+use schema_jsonrs::SchemaGenerator;
 use serde::Deserialize;
 use simple_bezier_easing::bezier;
+use std::borrow::Cow;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::LazyLock;
@@ -76,6 +81,41 @@ pub fn parse_duration_str(input: &str) -> anyhow::Result<f32> {
         })
 }
 
+/// Canonical spellings of every animation kind, used to suggest corrections for typos.
+pub const ANIMATION_KIND_NAMES: [&str; 3] = ["spiral", "fade", "reverse_spiral"];
+
+/// Canonical spellings of every named easing, used to suggest corrections for typos.
+pub const EASING_NAMES: [&str; 28] = [
+    "Linear",
+    "EaseIn",
+    "EaseInSine",
+    "EaseInQuad",
+    "EaseInCubic",
+    "EaseInQuart",
+    "EaseInQuint",
+    "EaseInExpo",
+    "EaseInCirc",
+    "EaseInBack",
+    "EaseOut",
+    "EaseOutSine",
+    "EaseOutQuad",
+    "EaseOutCubic",
+    "EaseOutQuart",
+    "EaseOutQuint",
+    "EaseOutExpo",
+    "EaseOutCirc",
+    "EaseOutBack",
+    "EaseInOut",
+    "EaseInOutSine",
+    "EaseInOutQuad",
+    "EaseInOutCubic",
+    "EaseInOutQuart",
+    "EaseInOutQuint",
+    "EaseInOutExpo",
+    "EaseInOutCirc",
+    "EaseInOutBack",
+];
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
 pub enum AnimationKind {
     Spiral,
@@ -83,6 +123,25 @@ pub enum AnimationKind {
     ReverseSpiral,
 }
 
+impl JsonSchema for AnimationKind {
+    fn schema_name() -> Cow<'static, str> {
+        "AnimationKind".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let names: Vec<String> = ANIMATION_KIND_NAMES
+            .iter()
+            .flat_map(|name| spellings(name))
+            .collect();
+
+        json_schema!({
+            "description": "The kind of animation: 'spiral' rotates the gradient around the border, 'fade' fades it in and out, and 'reverse_spiral' rotates it the other way.",
+            "type": "string",
+            "enum": names
+        })
+    }
+}
+
 impl FromStr for AnimationKind {
     type Err = &'static str;
 
@@ -219,11 +278,83 @@ impl FromStr for AnimationEasing {
                     .map_err(|err| format!("invalid cubic-bezier format: {}: {}", input, err))
             }
 
-            _ => Ok(AnimationEasing::default()),
+            _ => Err(format!("unknown easing: {input}")),
         }
     }
 }
 
+impl JsonSchema for AnimationEasing {
+    fn schema_name() -> Cow<'static, str> {
+        "AnimationEasing".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let names: Vec<String> = EASING_NAMES
+            .iter()
+            .flat_map(|name| spellings(name))
+            .collect();
+
+        json_schema!({
+            "description": "The easing of an animation: a named easing in any case style, or a custom cubic-bezier curve.",
+            "oneOf": [
+                {
+                    "type": "string",
+                    "description": "A named easing, e.g. 'EaseInOut', 'ease-in-out', 'ease_in_out' or 'easeInOut'.",
+                    "enum": names
+                },
+                {
+                    "type": "string",
+                    "pattern": "^[Cc][Uu][Bb][Ii][Cc][-_]?[Bb][Ee][Zz][Ii][Ee][Rr]\\([-+0-9\\s.,]+\\)$",
+                    "description": "A cubic-bezier curve, e.g. 'cubic-bezier(0.42, 0.0, 0.58, 1.0)'."
+                }
+            ]
+        })
+    }
+}
+
+/// Returns the spellings of a name that the schema lists: PascalCase, camelCase, kebab-case
+/// and snake_case. Names are matched case-insensitively, but a schema can only list exact
+/// strings, so these are the ones editors suggest.
+fn spellings(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in name.split('_') {
+        let mut word = String::new();
+        for c in part.chars() {
+            if c.is_ascii_uppercase() && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.push(c.to_ascii_lowercase());
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+
+    let capitalize = |word: &String| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+            .unwrap_or_default()
+    };
+    let pascal: String = words.iter().map(capitalize).collect();
+    let camel: String = words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            if i == 0 {
+                word.clone()
+            } else {
+                capitalize(word)
+            }
+        })
+        .collect();
+
+    let mut spellings = vec![pascal, camel, words.join("-"), words.join("_")];
+    spellings.dedup();
+    spellings
+}
+
 pub trait AnimationEasingImpl {
     fn evaluate(&self) -> [f32; 4];
     fn to_fn(&self) -> anyhow::Result<Arc<AnimationEasingCallback>>;
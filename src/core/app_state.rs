@@ -1,6 +1,7 @@
 use crate::border_manager::Border;
 use crate::config_watcher::ConfigWatcher;
 use crate::error::LogIfErr;
+use crate::rule_engine::RuleEngine;
 use crate::user_config::UserConfig;
 use crate::windows_api::WindowsApi;
 #[cfg(feature = "fast-hash")]
@@ -23,6 +24,7 @@ pub struct AppState {
     pub borders: Mutex<HashMap<isize, Border>>,
     pub active_window: Mutex<isize>,
     pub config: RwLock<UserConfig>,
+    pub rule_engine: RwLock<RuleEngine>,
     pub config_watcher: RwLock<ConfigWatcher>,
     pub render_factory: ID2D1Factory8,
     pub is_polling_active_window: AtomicBool,
@@ -36,23 +38,43 @@ impl AppState {
         let active_window = WindowsApi::get_foreground_window();
 
         let config_dir = UserConfig::get_config_dir().unwrap_or_default();
-        let config_file = UserConfig::detect_config_file(&config_dir)
-            .unwrap_or(UserConfig::create_default_config(&config_dir).unwrap_or_default());
-        let mut config_watcher = ConfigWatcher::new(config_file, Duration::from_millis(200));
-
-        let config = match UserConfig::create() {
-            Ok(config) => {
-                if config.monitor_config_changes {
-                    config_watcher.start().log_if_err();
-                }
-                config
-            }
+        let config_file = UserConfig::get_config_file()
+            .unwrap_or_else(|_| UserConfig::create_default_config(&config_dir).unwrap_or_default());
+        let (config, rule_engine) = match UserConfig::create_with_rule_engine() {
+            Ok(loaded) => loaded,
             Err(err) => {
                 error!("could not read config: {err:#}");
-                UserConfig::default() // Assuming `config_format` can have a default value
+
+                // Fall back to the last config that loaded successfully, so a typo in the config
+                // file doesn't cost us every rule and color on the next start.
+                let last_good = UserConfig::create_last_good().and_then(|config| {
+                    let rule_engine = RuleEngine::new(&config)?;
+                    Ok((config, rule_engine))
+                });
+
+                match last_good {
+                    Ok(loaded) => {
+                        warn!("using the last known-good config until the config file is fixed");
+                        loaded
+                    }
+                    Err(err) => {
+                        error!("could not read last known-good config: {err:#}");
+                        (UserConfig::default(), RuleEngine::default())
+                    }
+                }
             }
         };
 
+        let watched_paths = match config.sources.is_empty() {
+            true => vec![config_file],
+            false => config.sources.clone(),
+        };
+        let mut config_watcher = ConfigWatcher::new(watched_paths, Duration::from_millis(200));
+
+        if config.monitor_config_changes {
+            config_watcher.start().log_if_err();
+        }
+
         let render_factory = unsafe {
             D2D1CreateFactory::<ID2D1Factory8>(D2D1_FACTORY_TYPE_MULTI_THREADED, None)
                 .unwrap_or_else(|err| {
@@ -66,6 +88,7 @@ impl AppState {
             active_window: Mutex::new(active_window),
             is_polling_active_window: AtomicBool::new(false),
             config: RwLock::new(config),
+            rule_engine: RwLock::new(rule_engine),
             config_watcher: RwLock::new(config_watcher),
             render_factory,
         }
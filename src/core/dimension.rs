@@ -1,143 +1,118 @@
 #![allow(dead_code)]
-use crate::user_config::ConfigFormat;
-use crate::user_config::CONFIG_FORMAT;
+use schema_jsonrs::json_schema;
+use schema_jsonrs::JsonSchema;
+use schema_jsonrs::Schema;
+use schema_jsonrs::SchemaGenerator;
 use serde::de::Error;
 use serde::Deserialize;
 use serde::Deserializer;
-#[cfg(feature = "json")]
-use serde_jsonc2::{Number as JsonNumber, Value as JsonValue};
-#[cfg(feature = "yml")]
-use serde_yml::{Number as YamlNumber, Value as YamlValue};
+use std::borrow::Cow;
 
+/// A dimension as written in the config: a number of pixels, or a string such as `"2px"`.
+///
+/// Every config format is parsed into the same document before it is deserialized, so this
+/// works the same way whichever format the config file is written in.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
 pub enum Dimension {
-    #[cfg(feature = "json")]
-    JsonNumber(JsonNumber),
-    #[cfg(feature = "yml")]
-    YamlNumber(YamlNumber),
+    Number(i64),
     String(String),
 }
 
-pub trait AsI64 {
-    fn as_i64(&self) -> Option<i64>;
-}
-
-#[cfg(feature = "json")]
-impl AsI64 for JsonNumber {
-    fn as_i64(&self) -> Option<i64> {
-        self.as_i64()
+impl JsonSchema for Dimension {
+    fn schema_name() -> Cow<'static, str> {
+        "Dimension".into()
     }
-}
 
-#[cfg(feature = "yml")]
-impl AsI64 for YamlNumber {
-    fn as_i64(&self) -> Option<i64> {
-        self.as_i64()
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A size in pixels, as a number or a string with an optional 'px' suffix.",
+            "oneOf": [
+                {
+                    "type": "integer",
+                    "format": "int32",
+                    "description": "A size in pixels, e.g. 2 or -1."
+                },
+                {
+                    "type": "string",
+                    "pattern": "^-?\\d+(px)?$",
+                    "description": "A size in pixels with an optional 'px' suffix, e.g. '2px' or '-1px'."
+                }
+            ]
+        })
     }
 }
 
-fn parse_value<E>(value: Dimension) -> Result<i32, E>
-where
-    E: Error,
-{
+fn parse_value(value: Dimension) -> Option<i32> {
     match value {
-        #[cfg(feature = "json")]
-        Dimension::JsonNumber(num) => num.as_i64(),
-        #[cfg(feature = "yml")]
-        Dimension::YamlNumber(num) => num.as_i64(),
+        Dimension::Number(num) => Some(num),
         Dimension::String(s) => {
             let trimmed = s.strip_suffix("px").unwrap_or(&s);
             trimmed.parse::<i64>().ok()
         }
     }
     .map(|n| n as i32)
-    .ok_or_else(|| E::custom("Invalid value"))
-}
-
-fn parse_optional_value<E>(value: Dimension) -> Result<Option<i32>, E>
-where
-    E: Error,
-{
-    let val = match value {
-        #[cfg(feature = "json")]
-        Dimension::JsonNumber(num) => num.as_i64(),
-        #[cfg(feature = "yml")]
-        Dimension::YamlNumber(num) => num.as_i64(),
-        Dimension::String(s) => {
-            let trimmed = s.strip_suffix("px").unwrap_or(&s);
-            trimmed.parse::<i64>().ok()
-        }
-    }
-    .map(|n| n as i32);
-
-    Ok(val)
 }
 
 pub fn deserialize_dimension<'de, D>(deserializer: D) -> Result<i32, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let config_format = &*CONFIG_FORMAT
-        .read()
-        .map_err(|_| D::Error::custom("config format lock poisoned"))?;
-
-    #[cfg(feature = "json")]
-    if matches!(config_format, ConfigFormat::Json | ConfigFormat::Jsonc) {
-        let value: JsonValue = Deserialize::deserialize(deserializer)?;
-        return match value {
-            JsonValue::Number(num) => parse_value(Dimension::JsonNumber(num)),
-            JsonValue::String(s) => parse_value(Dimension::String(s)),
-            _ => Err(D::Error::custom("Expected a number or a string")),
-        };
-    }
+    let value = Dimension::deserialize(deserializer)
+        .map_err(|_| D::Error::custom("Expected a number or a string"))?;
 
-    #[cfg(feature = "yml")]
-    if matches!(config_format, ConfigFormat::Yaml) {
-        let value: YamlValue = Deserialize::deserialize(deserializer)?;
-        return match value {
-            YamlValue::Number(num) => parse_value(Dimension::YamlNumber(num)),
-            YamlValue::String(s) => parse_value(Dimension::String(s)),
-            _ => Err(D::Error::custom("Expected a number or a string")),
-        };
-    }
-
-    Err(D::Error::custom("Invalid file type"))
+    parse_value(value).ok_or_else(|| D::Error::custom("Invalid value"))
 }
 
 pub fn deserialize_optional_dimension<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let config_format = &*CONFIG_FORMAT
-        .read()
-        .map_err(|_| D::Error::custom("config format lock poisoned"))?;
+    let value: Option<Dimension> = Option::deserialize(deserializer)
+        .map_err(|_| D::Error::custom("Expected a number or string"))?;
 
-    #[cfg(feature = "json")]
-    if matches!(config_format, ConfigFormat::Json | ConfigFormat::Jsonc) {
-        let value: Option<JsonValue> = Option::deserialize(deserializer)?;
-        return match value {
-            Some(value) => match value {
-                JsonValue::Number(num) => parse_optional_value(Dimension::JsonNumber(num)),
-                JsonValue::String(s) => parse_optional_value(Dimension::String(s)),
-                JsonValue::Null => Ok(None),
-                _ => Err(D::Error::custom("Expected a number or string")),
-            },
-            None => Ok(None), // Handle the case where the value is missing
-        };
+    // A missing or null value is `None`, but a value that is set has to be valid.
+    value
+        .map(|value| parse_value(value).ok_or_else(|| D::Error::custom("Invalid value")))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Sizes {
+        #[serde(deserialize_with = "deserialize_optional_dimension", default)]
+        width: Option<i32>,
+    }
+
+    fn width(json: &str) -> Result<Option<i32>, serde_jsonc2::Error> {
+        serde_jsonc2::from_str::<Sizes>(json).map(|sizes| sizes.width)
     }
 
-    #[cfg(feature = "yml")]
-    if matches!(config_format, ConfigFormat::Yaml) {
-        let value: Option<YamlValue> = Option::deserialize(deserializer)?;
-        return match value {
-            Some(value) => match value {
-                YamlValue::Number(num) => parse_optional_value(Dimension::YamlNumber(num)),
-                YamlValue::String(s) => parse_optional_value(Dimension::String(s)),
-                YamlValue::Null => Ok(None),
-                _ => Err(D::Error::custom("Expected a number or string")),
-            },
-            None => Ok(None), // Handle the case where the value is missing
-        };
+    #[test]
+    fn optional_dimensions_parse_numbers_and_strings() {
+        assert_eq!(width(r#"{}"#).unwrap(), None);
+        assert_eq!(width(r#"{ "width": null }"#).unwrap(), None);
+        assert_eq!(width(r#"{ "width": 3 }"#).unwrap(), Some(3));
+        assert_eq!(width(r#"{ "width": "4px" }"#).unwrap(), Some(4));
+        assert_eq!(width(r#"{ "width": "-1" }"#).unwrap(), Some(-1));
     }
 
-    Err(D::Error::custom("Invalid file type")) // Handle invalid file types
+    #[test]
+    fn invalid_optional_dimensions_are_errors() {
+        for json in [
+            r#"{ "width": "wide" }"#,
+            r#"{ "width": "4em" }"#,
+            r#"{ "width": "" }"#,
+        ] {
+            let error = width(json).unwrap_err();
+            assert!(
+                error.to_string().contains("Invalid value"),
+                "{json}: {error}"
+            );
+        }
+        assert!(width(r#"{ "width": true }"#).is_err());
+    }
 }
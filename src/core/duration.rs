@@ -1,16 +1,61 @@
+use schema_jsonrs::json_schema;
 use schema_jsonrs::JsonSchema;
+use schema_jsonrs::Schema;
+use schema_jsonrs::SchemaGenerator;
 use serde::de::Error as SerdeError;
 use serde::de::Visitor;
 use serde::Deserialize;
 use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::borrow::Cow;
 
-#[derive(Clone, PartialEq, Debug, JsonSchema)]
+#[derive(Clone, PartialEq, Debug)]
 /// Represents a duration, which can be either a finite number (f32) or a non-empty string.
 pub enum Duration {
     Number(f32),
     Text(String),
 }
 
+impl Serialize for Duration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            // Go through the shortest decimal representation of the f32, so `0.3` is written
+            // back as `0.3` rather than `0.30000001192092896`.
+            Duration::Number(value) => {
+                serializer.serialize_f64(value.to_string().parse().unwrap_or(*value as f64))
+            }
+            Duration::Text(text) => serializer.serialize_str(text),
+        }
+    }
+}
+
+impl JsonSchema for Duration {
+    fn schema_name() -> Cow<'static, str> {
+        "Duration".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A duration, as a number of milliseconds or a string with a unit.",
+            "oneOf": [
+                {
+                    "type": "number",
+                    "description": "The duration in milliseconds."
+                },
+                {
+                    "type": "string",
+                    "pattern": "^[\\d.]+([Mm][Ss]|[Ss])$",
+                    "description": "The duration with a unit, e.g. '300ms' or '0.3s'."
+                }
+            ]
+        })
+    }
+}
+
 /// Visitor for deserializing `Duration`.
 struct DurationVisitor;
 
@@ -3,16 +3,29 @@ use crate::keyboard_hook::KeybindingConfig;
 use crate::sys_tray::SystemTrayEvent;
 use schema_jsonrs::JsonSchema;
 use serde::Deserialize;
+use serde::Serialize;
 
-#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 #[serde(default)]
 pub struct Keybindings {
+    /// The keybinding used to reload the border. Pressing this key will refresh the border
+    /// settings or appearance.
     #[serde(default = "default_reload_key")]
     pub reload: String,
+    /// The keybinding used to open the configuration in editor. Pressing this key will open
+    /// config for 'tacky-borders'.
     #[serde(default = "default_open_config_key")]
     pub open_config: String,
+    /// The keybinding used to close the app. Pressing this key will close 'tacky-borders'.
     #[serde(default = "default_exit_key")]
     pub exit: String,
+    /// Restores the last known-good config. Unbound unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert: Option<String>,
+    /// Switches to the next profile, and back to no profile after the last one. Unbound
+    /// unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle_profile: Option<String>,
 }
 
 fn default_reload_key() -> String {
@@ -33,6 +46,8 @@ impl Default for Keybindings {
             reload: default_reload_key(),
             open_config: default_open_config_key(),
             exit: default_exit_key(),
+            revert: None,
+            cycle_profile: None,
         }
     }
 }
@@ -40,7 +55,7 @@ impl Default for Keybindings {
 pub fn create_keybindings() -> anyhow::Result<Vec<KeybindingConfig>> {
     let config = (*APP_STATE.config.read().unwrap()).clone();
 
-    let bindings = vec![
+    let mut bindings = vec![
         KeybindingConfig::new(
             SystemTrayEvent::OpenConfig.into(),
             config.keybindings.open_config.clone().as_str(),
@@ -58,6 +73,22 @@ pub fn create_keybindings() -> anyhow::Result<Vec<KeybindingConfig>> {
         ),
     ];
 
+    if let Some(ref revert) = config.keybindings.revert {
+        bindings.push(KeybindingConfig::new(
+            SystemTrayEvent::RevertConfig.into(),
+            revert.as_str(),
+            Some(SystemTrayEvent::RevertConfig),
+        ));
+    }
+
+    if let Some(ref cycle_profile) = config.keybindings.cycle_profile {
+        bindings.push(KeybindingConfig::new(
+            SystemTrayEvent::CycleProfile.into(),
+            cycle_profile.as_str(),
+            Some(SystemTrayEvent::CycleProfile),
+        ));
+    }
+
     debug!("keybindings created: {bindings:#?}");
 
     Ok(bindings)
@@ -0,0 +1,772 @@
+use crate::user_config::ConfigFormat;
+#[cfg(feature = "fast-hash")]
+use fx_hash::{FxHashMap as HashMap, FxHashMapExt};
+#[cfg(not(feature = "fast-hash"))]
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// How serious a diagnostic is. Errors prevent the config from being loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A single problem found in a configuration file.
+///
+/// `path` is the key path of the offending value (e.g. `window_rules[2].match.value`). The file,
+/// line and column are filled in by [`SourceMap::locate`] once the problem has been collected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+    pub help: Option<String>,
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            path: path.into(),
+            message: message.into(),
+            help: None,
+            file: None,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, path, message)
+    }
+
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, path, message)
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Adds a "did you mean" hint if `input` is close to one of `candidates`.
+    pub fn with_suggestion<'a, I>(self, input: &str, candidates: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        match suggest(input, candidates) {
+            Some(suggestion) => self.with_help(format!("did you mean `{suggestion}`?")),
+            None => self,
+        }
+    }
+
+    pub fn at(mut self, file: &Path, line: usize, column: usize) -> Self {
+        self.file = Some(file.to_path_buf());
+        self.line = line;
+        self.column = column;
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{}:{}:{}: ", file.display(), self.line, self.column)?;
+        }
+
+        write!(f, "{}: ", self.severity)?;
+
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+
+        write!(f, "{}", self.message)?;
+
+        if let Some(ref help) = self.help {
+            write!(f, "\n  = help: {help}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the candidate closest to `input`, if it is close enough to be a plausible typo.
+pub fn suggest<'a, I>(input: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    // Spellings like `ease-in-out`, `ease_in_out` and `EaseInOut` are all accepted, so case and
+    // separators are ignored when measuring how close two names are.
+    let normalize = |s: &str| s.to_lowercase().replace(['-', '_'], "");
+    let input = normalize(input);
+    let max_distance = (input.chars().count() / 3).max(2);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (levenshtein(&input, &normalize(candidate)), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Joins a key onto a key path, e.g. `global` + `border_width` -> `global.border_width`.
+pub fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+/// Returns the parent of a key path, e.g. `window_rules[0].match` -> `window_rules[0]`.
+pub fn parent_path(path: &str) -> Option<&str> {
+    if path.is_empty() {
+        return None;
+    }
+
+    let end = path.rfind(['.', '[']).unwrap_or(0);
+    Some(&path[..end])
+}
+
+/// Maps key paths to the line and column where they appear in a config file.
+///
+/// This is a lightweight scan of the source rather than a full parser; it only needs to be
+/// good enough to point at the right line. Paths that cannot be found fall back to their
+/// closest located parent.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    positions: HashMap<String, (usize, usize)>,
+}
+
+impl SourceMap {
+    pub fn new(contents: &str, format: &ConfigFormat) -> Self {
+        let mut source_map = Self {
+            positions: HashMap::new(),
+        };
+        source_map.positions.insert(String::new(), (1, 1));
+
+        #[cfg(feature = "yml")]
+        if matches!(format, ConfigFormat::Yaml) {
+            source_map.scan_yaml(contents);
+            return source_map;
+        }
+
+        #[cfg(feature = "toml")]
+        if matches!(format, ConfigFormat::Toml) {
+            source_map.scan_toml(contents);
+            return source_map;
+        }
+
+        // JSON5 is close enough to JSONC for the scanner, which accepts unquoted keys and
+        // single-quoted strings.
+        JsonScanner::new(contents, &mut source_map.positions).scan();
+        source_map
+    }
+
+    /// Returns the (line, column) of `path`, or of its closest located parent.
+    pub fn position(&self, path: &str) -> (usize, usize) {
+        let mut current = Some(path);
+
+        while let Some(path) = current {
+            if let Some(position) = self.positions.get(path) {
+                return *position;
+            }
+            current = parent_path(path);
+        }
+
+        (1, 1)
+    }
+
+    /// Fills in the file, line and column of every diagnostic that does not have them yet.
+    pub fn locate(&self, file: &Path, diagnostics: &mut [Diagnostic]) {
+        for diagnostic in diagnostics.iter_mut().filter(|d| d.file.is_none()) {
+            let (line, column) = self.position(&diagnostic.path);
+            diagnostic.file = Some(file.to_path_buf());
+            diagnostic.line = line;
+            diagnostic.column = column;
+        }
+    }
+
+    /// Records the position of every block-style key and sequence item.
+    #[cfg(feature = "yml")]
+    fn scan_yaml(&mut self, contents: &str) {
+        struct Frame {
+            indent: usize,
+            path: String,
+            is_item: bool,
+            next_index: usize,
+        }
+
+        let mut frames: Vec<Frame> = Vec::new();
+
+        for (line_index, line) in contents.lines().enumerate() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---") {
+                continue;
+            }
+
+            let mut indent = line.len() - trimmed.len();
+            let mut rest = trimmed;
+
+            // A line can open several nested sequence items, e.g. `- - value`.
+            while rest == "-" || rest.starts_with("- ") {
+                while frames
+                    .last()
+                    .is_some_and(|f| f.indent > indent || (f.indent == indent && f.is_item))
+                {
+                    frames.pop();
+                }
+
+                let (parent, index) = match frames.last_mut() {
+                    Some(frame) => {
+                        frame.next_index += 1;
+                        (frame.path.clone(), frame.next_index - 1)
+                    }
+                    None => (String::new(), 0),
+                };
+
+                let path = format!("{parent}[{index}]");
+                self.positions
+                    .entry(path.clone())
+                    .or_insert((line_index + 1, indent + 1));
+                frames.push(Frame {
+                    indent,
+                    path,
+                    is_item: true,
+                    next_index: 0,
+                });
+
+                let item = rest[1..].trim_start();
+                indent += rest.len() - item.len();
+                rest = item;
+            }
+
+            let Some(key) = yaml_key(rest) else {
+                continue;
+            };
+
+            while frames.last().is_some_and(|f| f.indent >= indent) {
+                frames.pop();
+            }
+
+            let parent = frames.last().map(|f| f.path.as_str()).unwrap_or_default();
+            let path = join_path(parent, &key);
+            self.positions
+                .entry(path.clone())
+                .or_insert((line_index + 1, indent + 1));
+            frames.push(Frame {
+                indent,
+                path,
+                is_item: false,
+                next_index: 0,
+            });
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+impl SourceMap {
+    /// Records the position of every table header, `key = value` line and item of a
+    /// multi-line array.
+    fn scan_toml(&mut self, contents: &str) {
+        // Number of elements seen so far for each array of tables, e.g. `window_rules`.
+        let mut array_lengths: HashMap<String, usize> = HashMap::new();
+        let mut table = String::new();
+        // A multi-line array that is still open, and the index of its next item.
+        let mut open_array: Option<(String, usize)> = None;
+        let mut depth = 0;
+
+        for (line_index, line) in contents.lines().enumerate() {
+            let trimmed = line.trim_start();
+            let position = (line_index + 1, line.len() - trimmed.len() + 1);
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if depth > 0 {
+                if depth == 1 && !trimmed.starts_with(']') {
+                    if let Some((path, next_index)) = open_array.as_mut() {
+                        self.positions
+                            .entry(format!("{path}[{next_index}]"))
+                            .or_insert(position);
+                        *next_index += 1;
+                    }
+                }
+
+                depth += toml_depth(trimmed);
+                continue;
+            }
+
+            if let Some(header) = trimmed.strip_prefix("[[") {
+                let name = header.split("]]").next().unwrap_or_default();
+                let path = toml_path("", name, &array_lengths);
+                let length = array_lengths.entry(path.clone()).or_insert(0);
+
+                table = format!("{path}[{length}]");
+                *length += 1;
+
+                self.positions.entry(path).or_insert(position);
+                self.positions.entry(table.clone()).or_insert(position);
+                continue;
+            }
+
+            if let Some(header) = trimmed.strip_prefix('[') {
+                let name = header.split(']').next().unwrap_or_default();
+                table = toml_path("", name, &array_lengths);
+
+                self.positions.entry(table.clone()).or_insert(position);
+                continue;
+            }
+
+            let Some((key, value)) = toml_key_value(trimmed) else {
+                continue;
+            };
+
+            let path = toml_path(&table, key, &array_lengths);
+            self.positions.entry(path.clone()).or_insert(position);
+
+            depth = toml_depth(value);
+            open_array = (depth > 0 && value.starts_with('[')).then_some((path, 0));
+        }
+    }
+}
+
+/// Splits a `key = value` line, ignoring `=` inside quoted keys.
+#[cfg(feature = "toml")]
+fn toml_key_value(line: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '=') => return Some((line[..i].trim(), line[i + 1..].trim())),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Converts a dotted TOML key (e.g. `window_rules.match`) into a key path below `parent`.
+/// Arrays of tables along the way refer to their latest element, as they do in TOML.
+#[cfg(feature = "toml")]
+fn toml_path(parent: &str, key: &str, array_lengths: &HashMap<String, usize>) -> String {
+    let segments: Vec<&str> = key
+        .split('.')
+        .map(|segment| segment.trim().trim_matches(['"', '\'']))
+        .collect();
+    let mut path = parent.to_string();
+
+    for (i, segment) in segments.iter().enumerate() {
+        path = join_path(&path, segment);
+
+        if i + 1 < segments.len() {
+            if let Some(length) = array_lengths.get(&path) {
+                path = format!("{path}[{}]", length - 1);
+            }
+        }
+    }
+
+    path
+}
+
+/// Returns how many brackets and braces `value` leaves open (or closes, if negative).
+#[cfg(feature = "toml")]
+fn toml_depth(value: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+
+    for c in value.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') => break,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth
+}
+
+/// Extracts the key from a `key: value` line, ignoring colons inside quotes.
+#[cfg(feature = "yml")]
+fn yaml_key(line: &str) -> Option<String> {
+    let mut quote = None;
+
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if i == 0 => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') => return None,
+            (None, ':') => {
+                let after = &line[i + 1..];
+                if after.is_empty() || after.starts_with([' ', '\t']) {
+                    let key = line[..i].trim().trim_matches(['"', '\'']);
+                    return (!key.is_empty()).then(|| key.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Records the position of every object key and array element in a JSON(C) document.
+struct JsonScanner<'a> {
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    column: usize,
+    positions: &'a mut HashMap<String, (usize, usize)>,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn new(contents: &str, positions: &'a mut HashMap<String, (usize, usize)>) -> Self {
+        Self {
+            chars: contents.chars().collect(),
+            index: 0,
+            line: 1,
+            column: 1,
+            positions,
+        }
+    }
+
+    fn scan(&mut self) {
+        self.skip_trivia();
+        self.value("");
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn record(&mut self, path: String) {
+        self.positions
+            .entry(path)
+            .or_insert((self.line, self.column));
+    }
+
+    /// Skips whitespace as well as `//` and `/* */` comments.
+    fn skip_trivia(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '/' && self.chars.get(self.index + 1) == Some(&'/') {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else if c == '/' && self.chars.get(self.index + 1) == Some(&'*') {
+                self.bump();
+                self.bump();
+                while self.peek().is_some()
+                    && !(self.peek() == Some('*') && self.chars.get(self.index + 1) == Some(&'/'))
+                {
+                    self.bump();
+                }
+                self.bump();
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn value(&mut self, path: &str) {
+        match self.peek() {
+            Some('{') => self.object(path),
+            Some('[') => self.array(path),
+            Some('"' | '\'') => {
+                self.string();
+            }
+            Some(_) => {
+                self.scalar();
+            }
+            None => {}
+        }
+    }
+
+    fn object(&mut self, path: &str) {
+        self.bump();
+
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => return,
+                Some('}') => {
+                    self.bump();
+                    return;
+                }
+                Some(',') => {
+                    self.bump();
+                }
+                Some(c) => {
+                    let (line, column) = (self.line, self.column);
+                    let key = if c == '"' || c == '\'' {
+                        self.string()
+                    } else {
+                        self.scalar()
+                    };
+
+                    let child = join_path(path, &key);
+                    self.positions
+                        .entry(child.clone())
+                        .or_insert((line, column));
+
+                    self.skip_trivia();
+                    if self.peek() == Some(':') {
+                        self.bump();
+                    }
+                    self.skip_trivia();
+                    self.value(&child);
+                }
+            }
+        }
+    }
+
+    fn array(&mut self, path: &str) {
+        self.bump();
+        let mut index = 0;
+
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => return,
+                Some(']') => {
+                    self.bump();
+                    return;
+                }
+                Some(',') => {
+                    self.bump();
+                }
+                Some(_) => {
+                    let child = format!("{path}[{index}]");
+                    self.record(child.clone());
+                    self.value(&child);
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    fn string(&mut self) -> String {
+        let quote = self.bump();
+        let mut value = String::new();
+
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => {
+                    if let Some(escaped) = self.bump() {
+                        value.push(escaped);
+                    }
+                }
+                c if Some(c) == quote => break,
+                c => value.push(c),
+            }
+        }
+
+        value
+    }
+
+    /// Consumes a number, literal or unquoted key.
+    fn scalar(&mut self) -> String {
+        let mut value = String::new();
+
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || matches!(c, ',' | ':' | ']' | '}' | '/') {
+                break;
+            }
+            value.push(c);
+            self.bump();
+        }
+
+        // Always make progress, even on unexpected input.
+        if value.is_empty() {
+            self.bump();
+        }
+
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::animation::EASING_NAMES;
+
+    /// Position of `window_rules[1].match.value` in each of the documents below.
+    const NESTED_KEY: &str = "window_rules[1].match.value";
+
+    #[test]
+    fn suggests_close_names() {
+        let keys = ["border_width", "border_offset", "border_radius"];
+
+        assert_eq!(
+            suggest("EaseInOutCubc", EASING_NAMES),
+            Some("EaseInOutCubic")
+        );
+        assert_eq!(
+            suggest("ease-in-out-cubic", EASING_NAMES),
+            Some("EaseInOutCubic")
+        );
+        assert_eq!(suggest("boder_width", keys), Some("border_width"));
+        assert_eq!(suggest("colour", keys), None);
+    }
+
+    #[test]
+    fn levenshtein_counts_edits() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("border", "border"), 0);
+        assert_eq!(levenshtein("boder", "border"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn parent_paths() {
+        assert_eq!(
+            parent_path("window_rules[0].match"),
+            Some("window_rules[0]")
+        );
+        assert_eq!(parent_path("window_rules[0]"), Some("window_rules"));
+        assert_eq!(parent_path("global"), Some(""));
+        assert_eq!(parent_path(""), None);
+    }
+
+    #[test]
+    fn locates_nested_keys_in_json() {
+        let contents = r#"{
+  "global": { "border_width": 2 },
+  "window_rules": [
+    { "match": { "kind": "Title", "value": "a" } },
+    {
+      "match": {
+        "value": "b"
+      }
+    }
+  ]
+}"#;
+        let source_map = SourceMap::new(contents, &ConfigFormat::Json);
+
+        assert_eq!(source_map.position("global.border_width"), (2, 15));
+        assert_eq!(source_map.position("window_rules[0].match.value"), (4, 35));
+        assert_eq!(source_map.position(NESTED_KEY), (7, 9));
+    }
+
+    #[test]
+    fn locates_nested_keys_in_jsonc() {
+        let contents = r#"{
+  // "window_rules": [ { "match": {
+  "window_rules": [
+    /* the first rule, with a "value": */
+    { "match": { "kind": "Title", "value": "a" } },
+    { "match": { 'value': "b" } }, // trailing comma
+  ],
+}"#;
+        let source_map = SourceMap::new(contents, &ConfigFormat::Jsonc);
+
+        assert_eq!(source_map.position("window_rules"), (3, 3));
+        assert_eq!(source_map.position(NESTED_KEY), (6, 18));
+    }
+
+    #[cfg(feature = "yml")]
+    #[test]
+    fn locates_nested_keys_in_yaml() {
+        let contents = "\
+global:
+  border_width: 2
+window_rules:
+  # the first rule
+  - match:
+      kind: Title
+      value: a
+  - match:
+      value: b
+";
+        let source_map = SourceMap::new(contents, &ConfigFormat::Yaml);
+
+        assert_eq!(source_map.position("global.border_width"), (2, 3));
+        assert_eq!(source_map.position("window_rules[1]"), (8, 3));
+        assert_eq!(source_map.position(NESTED_KEY), (9, 7));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn locates_nested_keys_in_toml() {
+        let contents = r#"[global]
+border_width = 2
+
+[[window_rules]]
+match = { kind = "Title", value = "a" }
+
+[[window_rules]]
+[window_rules.match]
+value = "b"
+"#;
+        let source_map = SourceMap::new(contents, &ConfigFormat::Toml);
+
+        assert_eq!(source_map.position("global.border_width"), (2, 1));
+        assert_eq!(source_map.position("window_rules[1]"), (7, 1));
+        assert_eq!(source_map.position(NESTED_KEY), (9, 1));
+    }
+
+    #[test]
+    fn unknown_paths_fall_back_to_their_parent() {
+        let source_map = SourceMap::new(r#"{ "global": {} }"#, &ConfigFormat::Json);
+
+        assert_eq!(source_map.position("global.border_width"), (1, 3));
+        assert_eq!(source_map.position("theme"), (1, 1));
+    }
+}
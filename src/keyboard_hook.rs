@@ -307,7 +307,7 @@ impl KeyboardHook {
         })
     }
 
-    fn key_to_vk_code(key: &str) -> Option<u16> {
+    pub fn key_to_vk_code(key: &str) -> Option<u16> {
         match key.to_lowercase().as_str() {
             "a" => Some(VK_A.0),
             "b" => Some(VK_B.0),
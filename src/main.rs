@@ -26,6 +26,7 @@ use sp_log2::Format;
 use sp_log2::LevelFilter;
 use sp_log2::TermLogger;
 use sp_log2::TerminalMode;
+use sys_tray::rebuild_profiles_menu;
 use sys_tray::SystemTray;
 use user_config::UserConfig;
 use window_event_hook::WindowEventHook;
@@ -34,21 +35,39 @@ use windows::Win32::Foundation::GetLastError;
 use windows::Win32::UI::WindowsAndMessaging::MSG;
 use windows::Win32::UI::WindowsAndMessaging::WM_QUIT;
 use windows_api::WindowsApi;
+use windows_api::WM_APP_PROFILESCHANGED;
 
 mod animation;
 mod border_manager;
+mod cli;
 mod colors;
+mod config_backup;
+mod config_linter;
+mod config_loader;
+mod config_location;
+mod config_migration;
+mod config_schema;
+mod config_validator;
+mod config_variables;
 mod config_watcher;
 mod core;
+mod diagnostics;
 mod error;
 mod keyboard_hook;
+mod rule_engine;
 mod sys_tray;
 mod user_config;
 mod window_event_hook;
+mod window_identity;
+mod window_state;
 mod windows_api;
 mod windows_callback;
 
 fn main() -> AnyResult<()> {
+    if let Some(exit_code) = cli::run(std::env::args().skip(1)) {
+        std::process::exit(exit_code);
+    }
+
     let res = start_application();
 
     if let Err(err) = &res {
@@ -93,6 +112,12 @@ fn start_application() -> AnyResult<()> {
     loop {
         // Get the next message from the message queue
         if WindowsApi::get_message_w(&mut message, None, 0, 0).as_bool() {
+            // Thread messages have no window to be dispatched to, so handle them here.
+            if message.message == WM_APP_PROFILESCHANGED {
+                rebuild_profiles_menu().log_if_err();
+                continue;
+            }
+
             // Translate and dispatch the message
             let _ = WindowsApi::translate_message(&message);
             WindowsApi::dispatch_message_w(&message);
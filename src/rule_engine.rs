@@ -0,0 +1,687 @@
+use crate::diagnostics::Diagnostic;
+use crate::user_config::EffectiveRule;
+use crate::user_config::GlobalRuleConfig;
+use crate::user_config::MatchCondition;
+use crate::user_config::MatchKind;
+use crate::user_config::MatchStrategy;
+use crate::user_config::UserConfig;
+use crate::user_config::WindowMatchConfig;
+use crate::user_config::WindowRuleConfig;
+use crate::window_identity::WindowIdentity;
+use crate::window_state::StatePredicate;
+use crate::window_state::WindowState;
+use anyhow::anyhow;
+use anyhow::Result as AnyResult;
+#[cfg(feature = "fast-hash")]
+use fx_hash::{FxHashMap as HashMap, FxHashMapExt};
+use regex::Regex;
+#[cfg(not(feature = "fast-hash"))]
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Maximum number of cached lookups before the cache is flushed. Window titles change often
+/// (e.g. browser tabs), so the cache is bounded to keep memory usage flat.
+const MAX_CACHE_ENTRIES: usize = 1024;
+
+/// Key used to cache rule lookups: (identity, state key).
+type WindowKey = (WindowIdentity, Vec<bool>);
+
+/// A `MatchStrategy` resolved together with its match value.
+#[derive(Debug)]
+enum Matcher {
+    /// Lowercased value compared for equality.
+    Equals(String),
+    /// Lowercased value searched as a substring.
+    Contains(String),
+    /// Precompiled regular expression.
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, window_name: &str) -> bool {
+        match self {
+            Matcher::Equals(value) => window_name.to_lowercase() == *value,
+            Matcher::Contains(value) => window_name.to_lowercase().contains(value.as_str()),
+            Matcher::Regex(re) => re.is_match(window_name),
+        }
+    }
+}
+
+/// A test on the parts of a window that change while it is open.
+#[derive(Debug)]
+enum StateTest {
+    Predicate(StatePredicate),
+    /// Passes if any of the monitor's names matches.
+    Monitor(Matcher),
+}
+
+impl StateTest {
+    fn is_match(&self, state: &WindowState) -> bool {
+        match self {
+            StateTest::Predicate(predicate) => predicate.is_match(state),
+            StateTest::Monitor(matcher) => state
+                .monitor
+                .as_ref()
+                .is_some_and(|monitor| monitor.names().iter().any(|name| matcher.is_match(name))),
+        }
+    }
+}
+
+/// A `MatchCondition` with all of its regexes built and strategies resolved.
+#[derive(Debug)]
+struct CompiledCondition {
+    leaf: Option<(MatchKind, Matcher)>,
+    /// Indices of the state tests this condition requires, see `RuleEngine::state_key`.
+    state: Vec<usize>,
+    all: Vec<CompiledCondition>,
+    any: Option<Vec<CompiledCondition>>,
+    not: Option<Box<CompiledCondition>>,
+}
+
+impl CompiledCondition {
+    /// Compiles `condition`, pushing every problem found at `path` into `errors`.
+    ///
+    /// State tests are added to `tests` (predicates once each), so windows can be told apart
+    /// by which of them they pass. `Monitor` leaves are state tests too.
+    fn compile(
+        condition: &MatchCondition,
+        path: &str,
+        tests: &mut Vec<StateTest>,
+        errors: &mut Vec<Diagnostic>,
+    ) -> Option<Self> {
+        if condition.is_empty() {
+            errors.push(Diagnostic::error(
+                path,
+                "expected 'kind', a window state, 'all', 'any' or 'not' but none found",
+            ));
+            return None;
+        }
+
+        let leaf = match (&condition.match_kind, &condition.match_value) {
+            (Some(kind), Some(value)) => {
+                let matcher = match condition.match_strategy {
+                    Some(MatchStrategy::Equals) | None => Matcher::Equals(value.to_lowercase()),
+                    Some(MatchStrategy::Contains) => Matcher::Contains(value.to_lowercase()),
+                    Some(MatchStrategy::Regex) => match Regex::new(value) {
+                        Ok(re) => Matcher::Regex(re),
+                        Err(err) => {
+                            errors.push(Diagnostic::error(
+                                format!("{path}.value"),
+                                format!("invalid regex `{value}`: {err}"),
+                            ));
+                            return None;
+                        }
+                    },
+                };
+                Some((kind.clone(), matcher))
+            }
+            (Some(kind), None) => {
+                errors.push(Diagnostic::error(
+                    path,
+                    format!("expected `value` for kind `{kind:?}`"),
+                ));
+                return None;
+            }
+            (None, _) => None,
+        };
+
+        let mut state: Vec<usize> = condition
+            .state_predicates()
+            .into_iter()
+            .map(|predicate| {
+                let existing = tests.iter().position(|test| match test {
+                    StateTest::Predicate(p) => *p == predicate,
+                    StateTest::Monitor(_) => false,
+                });
+                existing.unwrap_or_else(|| {
+                    tests.push(StateTest::Predicate(predicate));
+                    tests.len() - 1
+                })
+            })
+            .collect();
+
+        let leaf = match leaf {
+            Some((MatchKind::Monitor, matcher)) => {
+                tests.push(StateTest::Monitor(matcher));
+                state.push(tests.len() - 1);
+                None
+            }
+            leaf => leaf,
+        };
+
+        let mut compile_all = |conditions: &[MatchCondition], group: &str, errors: &mut Vec<_>| {
+            conditions
+                .iter()
+                .enumerate()
+                .map(|(i, c)| Self::compile(c, &format!("{path}.{group}[{i}]"), tests, errors))
+                .collect::<Option<Vec<_>>>()
+        };
+
+        let all = match &condition.all {
+            Some(all) => compile_all(all, "all", errors)?,
+            None => Vec::new(),
+        };
+
+        let any = match &condition.any {
+            Some(any) => Some(compile_all(any, "any", errors)?),
+            None => None,
+        };
+
+        let not = match &condition.not {
+            Some(not) => Some(Box::new(Self::compile(
+                not,
+                &format!("{path}.not"),
+                tests,
+                errors,
+            )?)),
+            None => None,
+        };
+
+        Some(Self {
+            leaf,
+            state,
+            all,
+            any,
+            not,
+        })
+    }
+
+    /// Returns whether this condition or any condition nested in it matches on `kind`.
+    fn uses_kind(&self, kind: &MatchKind) -> bool {
+        self.leaf
+            .as_ref()
+            .is_some_and(|(leaf_kind, _)| leaf_kind == kind)
+            || self.all.iter().any(|c| c.uses_kind(kind))
+            || self
+                .any
+                .as_ref()
+                .is_some_and(|any| any.iter().any(|c| c.uses_kind(kind)))
+            || self.not.as_ref().is_some_and(|not| not.uses_kind(kind))
+    }
+
+    /// `state` holds whether the window passes each state test, by index.
+    fn is_match(&self, identity: &WindowIdentity, state: &[bool]) -> bool {
+        if !self.state.iter().all(|&i| state[i]) {
+            return false;
+        }
+
+        if let Some((kind, matcher)) = &self.leaf {
+            if !matcher.is_match(identity.value(kind)) {
+                return false;
+            }
+        }
+
+        self.all.iter().all(|c| c.is_match(identity, state))
+            && self
+                .any
+                .as_ref()
+                .is_none_or(|any| any.iter().any(|c| c.is_match(identity, state)))
+            && self
+                .not
+                .as_ref()
+                .is_none_or(|not| !not.is_match(identity, state))
+    }
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    /// Position of the rule in `window_rules`.
+    index: usize,
+    condition: CompiledCondition,
+    rule: WindowRuleConfig,
+}
+
+/// Window rules compiled once per configuration load.
+///
+/// Compiling up front reports invalid rules as configuration errors instead of failing on
+/// every lookup, and a small cache makes repeated lookups for the same window cheap.
+#[derive(Debug, Default)]
+pub struct RuleEngine {
+    global: GlobalRuleConfig,
+    rules: Vec<CompiledRule>,
+    /// Overrides from `monitors`, each with the index of the state test selecting its monitor.
+    monitors: Vec<(usize, WindowMatchConfig)>,
+    /// Every state test used by the rules and monitor overrides.
+    tests: Vec<StateTest>,
+    cache: Mutex<HashMap<WindowKey, EffectiveRule>>,
+    /// Last state key of each window without a border, so moving such a window only resolves
+    /// its rule again once the window crosses a threshold that some rule tests.
+    borderless_state_keys: Mutex<HashMap<isize, Vec<bool>>>,
+}
+
+impl RuleEngine {
+    /// Compiles every window rule in `config`.
+    ///
+    /// All invalid rules are reported at once, each one prefixed with its position
+    /// (e.g. `window_rules[2].match`).
+    pub fn new(config: &UserConfig) -> AnyResult<Self> {
+        let mut errors = Vec::new();
+        let rule_engine = Self::compile(config, &mut errors);
+
+        if !errors.is_empty() {
+            let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+            return Err(anyhow!("invalid window rules:\n  {}", errors.join("\n  ")));
+        }
+
+        Ok(rule_engine)
+    }
+
+    /// Compiles every valid window rule in `config`, pushing a diagnostic for each invalid one.
+    pub fn compile(config: &UserConfig, errors: &mut Vec<Diagnostic>) -> Self {
+        let mut tests = Vec::new();
+        let rules: Vec<CompiledRule> = config
+            .window_rules
+            .iter()
+            .enumerate()
+            .filter_map(|(i, rule)| {
+                let path = format!("window_rules[{i}].match");
+                let condition = &rule.match_window.condition;
+                CompiledCondition::compile(condition, &path, &mut tests, errors).map(|condition| {
+                    CompiledRule {
+                        index: i,
+                        condition,
+                        rule: rule.clone(),
+                    }
+                })
+            })
+            .collect();
+
+        let monitors = config
+            .monitors
+            .iter()
+            .map(|(name, monitor)| {
+                tests.push(StateTest::Monitor(Matcher::Equals(
+                    name.trim().to_lowercase(),
+                )));
+                (tests.len() - 1, monitor.settings())
+            })
+            .collect();
+
+        Self {
+            global: config.global_rule.clone(),
+            rules,
+            monitors,
+            tests,
+            cache: Mutex::new(HashMap::new()),
+            borderless_state_keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `true` if any rule or monitor override depends on the window's state, so rules
+    /// have to be resolved again when the window changes.
+    pub fn uses_window_state(&self) -> bool {
+        !self.tests.is_empty()
+    }
+
+    /// Returns whether `state` passes each state test used by the rules.
+    ///
+    /// Two states with the same key get the same effective rule, so resizing a window only
+    /// matters once it crosses a threshold that some rule tests.
+    pub fn state_key(&self, state: &WindowState) -> Vec<bool> {
+        self.tests.iter().map(|test| test.is_match(state)).collect()
+    }
+
+    /// Records the state of a window without a border. Returns `false` if its state key is the
+    /// same as last time, in which case its rule hasn't changed either.
+    pub fn update_borderless_state(&self, hwnd: isize, state: &WindowState) -> bool {
+        let state_key = self.state_key(state);
+        let mut state_keys = self.borderless_state_keys.lock().unwrap();
+
+        if state_keys.get(&hwnd) == Some(&state_key) {
+            return false;
+        }
+
+        if state_keys.len() >= MAX_CACHE_ENTRIES {
+            state_keys.clear();
+        }
+        state_keys.insert(hwnd, state_key);
+
+        true
+    }
+
+    /// Forgets the state recorded for a window, e.g. once it is destroyed.
+    pub fn forget_window(&self, hwnd: isize) {
+        self.borderless_state_keys.lock().unwrap().remove(&hwnd);
+    }
+
+    /// Returns `true` if any rule matches on `kind`, so windows need that part of their identity
+    /// collected.
+    pub fn uses_match_kind(&self, kind: &MatchKind) -> bool {
+        self.rules
+            .iter()
+            .any(|compiled| compiled.condition.uses_kind(kind))
+    }
+
+    /// Returns the positions in `window_rules` of every rule matching the window, in file
+    /// order.
+    pub fn matching_rules(&self, identity: &WindowIdentity, state: &WindowState) -> Vec<usize> {
+        let state_key = self.state_key(state);
+
+        self.rules
+            .iter()
+            .filter(|compiled| compiled.condition.is_match(identity, &state_key))
+            .map(|compiled| compiled.index)
+            .collect()
+    }
+
+    /// Returns the effective rule for a window with the given properties.
+    pub fn resolve(&self, identity: &WindowIdentity, state: &WindowState) -> EffectiveRule {
+        let key = (identity.clone(), self.state_key(state));
+
+        if let Some(effective) = self.cache.lock().unwrap().get(&key) {
+            return effective.clone();
+        }
+
+        let matching_rules = self
+            .rules
+            .iter()
+            .filter(|compiled| compiled.condition.is_match(identity, &key.1))
+            .map(|compiled| &compiled.rule);
+
+        // Monitor overrides sit between `global` and the window rules, applied in key order.
+        let mut base = EffectiveRule::from(&self.global);
+        for (test, settings) in &self.monitors {
+            if key.1[*test] {
+                base.apply(settings);
+            }
+        }
+
+        let effective = EffectiveRule::resolve(base, matching_rules);
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHE_ENTRIES {
+            cache.clear();
+        }
+        cache.insert(key, effective.clone());
+
+        effective
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window_state::Monitor;
+
+    /// Returns a config that only has the given window rules.
+    fn config(window_rules: &str) -> UserConfig {
+        serde_jsonc2::from_str(&format!(r#"{{ "window_rules": {window_rules} }}"#)).unwrap()
+    }
+
+    fn engine(window_rules: &str) -> RuleEngine {
+        RuleEngine::new(&config(window_rules)).unwrap()
+    }
+
+    fn window(title: &str, class: &str, process: &str) -> WindowIdentity {
+        WindowIdentity {
+            title: title.to_string(),
+            class: class.to_string(),
+            process: process.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn firefox(title: &str) -> WindowIdentity {
+        window(title, "MozillaWindowClass", "firefox")
+    }
+
+    fn matching(engine: &RuleEngine, identity: &WindowIdentity) -> Vec<usize> {
+        engine.matching_rules(identity, &WindowState::default())
+    }
+
+    #[test]
+    fn single_condition_rules_still_match() {
+        let engine = engine(
+            r#"[
+                { "match": { "kind": "Process", "value": "Firefox" } },
+                { "match": { "kind": "Title", "value": "inbox", "strategy": "Contains" } },
+                { "match": { "kind": "Class", "value": "^Mozilla\\w+Class$", "strategy": "Regex" } }
+            ]"#,
+        );
+
+        assert_eq!(matching(&engine, &firefox("Inbox - Mail")), [0, 1, 2]);
+        assert_eq!(matching(&engine, &firefox("New Tab")), [0, 2]);
+        assert_eq!(
+            matching(&engine, &window("Inbox", "Chrome_WidgetWin_1", "chrome")),
+            [1]
+        );
+    }
+
+    #[test]
+    fn all_requires_every_condition() {
+        let engine = engine(
+            r#"[{ "match": { "all": [
+                { "kind": "Process", "value": "firefox" },
+                { "kind": "Title", "value": "Picture-in-Picture", "strategy": "Contains" }
+            ] } }]"#,
+        );
+
+        assert_eq!(matching(&engine, &firefox("Picture-in-Picture")), [0]);
+        assert!(matching(&engine, &firefox("New Tab")).is_empty());
+        assert!(matching(&engine, &window("Picture-in-Picture", "", "chrome")).is_empty());
+    }
+
+    #[test]
+    fn any_requires_one_condition() {
+        let engine = engine(
+            r#"[{ "match": { "any": [
+                { "kind": "Process", "value": "firefox" },
+                { "kind": "Process", "value": "chrome" }
+            ] } }]"#,
+        );
+
+        assert_eq!(matching(&engine, &firefox("")), [0]);
+        assert_eq!(matching(&engine, &window("", "", "chrome")), [0]);
+        assert!(matching(&engine, &window("", "", "code")).is_empty());
+    }
+
+    #[test]
+    fn not_inverts_its_condition() {
+        let engine = engine(
+            r#"[{ "match": {
+                "kind": "Process",
+                "value": "firefox",
+                "not": { "kind": "Title", "value": "Private", "strategy": "Contains" }
+            } }]"#,
+        );
+
+        assert_eq!(matching(&engine, &firefox("New Tab")), [0]);
+        assert!(matching(&engine, &firefox("Private Browsing")).is_empty());
+        assert!(matching(&engine, &window("New Tab", "", "chrome")).is_empty());
+    }
+
+    #[test]
+    fn groups_nest() {
+        let engine = engine(
+            r#"[{ "match": { "all": [
+                { "kind": "Process", "value": "firefox" },
+                { "not": { "any": [
+                    { "kind": "Title", "value": "Private", "strategy": "Contains" },
+                    { "kind": "Class", "value": "MozillaDialogClass" }
+                ] } }
+            ] } }]"#,
+        );
+
+        assert_eq!(matching(&engine, &firefox("New Tab")), [0]);
+        assert!(matching(&engine, &firefox("Private Browsing")).is_empty());
+        assert!(matching(&engine, &window("Save", "MozillaDialogClass", "firefox")).is_empty());
+    }
+
+    #[test]
+    fn empty_all_matches_every_window_and_empty_any_none() {
+        let engine = engine(
+            r#"[
+                { "match": { "all": [] } },
+                { "match": { "any": [] } }
+            ]"#,
+        );
+
+        assert_eq!(matching(&engine, &firefox("New Tab")), [0]);
+        assert_eq!(matching(&engine, &window("", "", "")), [0]);
+    }
+
+    /// Returns the paths of every problem found while compiling the rules.
+    fn compile_errors(window_rules: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        RuleEngine::compile(&config(window_rules), &mut errors);
+        errors.into_iter().map(|error| error.path).collect()
+    }
+
+    #[test]
+    fn invalid_regex_is_reported_at_its_value() {
+        let window_rules = r#"[
+            { "match": { "kind": "Title", "value": "ok" } },
+            { "match": { "kind": "Title", "value": "(unclosed", "strategy": "Regex" } },
+            { "match": { "any": [
+                { "kind": "Class", "value": "ok" },
+                { "kind": "Class", "value": "[", "strategy": "Regex" }
+            ] } }
+        ]"#;
+
+        assert_eq!(
+            compile_errors(window_rules),
+            [
+                "window_rules[1].match.value",
+                "window_rules[2].match.any[1].value"
+            ]
+        );
+
+        let err = RuleEngine::new(&config(window_rules)).unwrap_err();
+        assert!(err.to_string().contains("window_rules[1].match.value"));
+    }
+
+    #[test]
+    fn incomplete_conditions_are_reported() {
+        assert_eq!(
+            compile_errors(
+                r#"[
+                    { "match": { "kind": "Title" } },
+                    { "match": { "active_color": "red" } }
+                ]"#
+            ),
+            ["window_rules[0].match", "window_rules[1].match"]
+        );
+    }
+
+    #[test]
+    fn repeated_lookups_hit_the_cache() {
+        let engine = engine(
+            r#"[{ "match": { "kind": "Process", "value": "firefox", "border_width": 4 } }]"#,
+        );
+        let state = WindowState::default();
+
+        let first = engine.resolve(&firefox("New Tab"), &state);
+        let second = engine.resolve(&firefox("New Tab"), &state);
+        assert_eq!(first, second);
+        assert_eq!(first.border_width, 4);
+        assert_eq!(engine.cache.lock().unwrap().len(), 1);
+
+        let other = engine.resolve(&window("New Tab", "", "chrome"), &state);
+        assert_ne!(other.border_width, 4);
+        assert_eq!(engine.cache.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn cache_is_flushed_when_full() {
+        let engine = engine("[]");
+        let state = WindowState::default();
+
+        for i in 0..MAX_CACHE_ENTRIES {
+            engine.resolve(&firefox(&i.to_string()), &state);
+        }
+        assert_eq!(engine.cache.lock().unwrap().len(), MAX_CACHE_ENTRIES);
+
+        engine.resolve(&firefox("one more"), &state);
+        assert_eq!(engine.cache.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn borderless_windows_are_resolved_again_only_when_their_state_changes() {
+        let engine = engine(r#"[{ "match": { "maximized": true, "enabled": true } }]"#);
+        let restored = WindowState::default();
+        let maximized = WindowState {
+            maximized: true,
+            ..Default::default()
+        };
+
+        assert!(engine.update_borderless_state(1, &restored));
+        assert!(!engine.update_borderless_state(1, &restored));
+        assert!(engine.update_borderless_state(2, &restored));
+        assert!(engine.update_borderless_state(1, &maximized));
+        assert!(!engine.update_borderless_state(1, &maximized));
+
+        engine.forget_window(1);
+        assert!(engine.update_borderless_state(1, &maximized));
+        assert!(!engine.update_borderless_state(2, &restored));
+    }
+
+    fn on_monitor(index: usize, device_name: &str, primary: bool) -> WindowState {
+        WindowState {
+            monitor: Some(Monitor {
+                index,
+                device_name: device_name.to_string(),
+                primary,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn monitor_overrides_apply_by_index_device_name_and_primary() {
+        let config: UserConfig = serde_jsonc2::from_str(
+            r#"{
+                "global": { "border_width": 2, "border_offset": 0 },
+                "monitors": {
+                    "1": { "border_width": 4 },
+                    "\\\\.\\DISPLAY2": { "enabled": false },
+                    "Primary": { "border_offset": 5 }
+                }
+            }"#,
+        )
+        .unwrap();
+        let engine = RuleEngine::new(&config).unwrap();
+        let identity = firefox("New Tab");
+
+        let first = engine.resolve(&identity, &on_monitor(1, r"\\.\DISPLAY1", true));
+        assert_eq!(first.border_width, 4);
+        assert_eq!(first.border_offset, 5);
+        assert_eq!(first.enabled, None);
+
+        let second = engine.resolve(&identity, &on_monitor(2, r"\\.\DISPLAY2", false));
+        assert_eq!(second.border_width, 2);
+        assert_eq!(second.border_offset, 0);
+        assert_eq!(second.enabled, Some(false));
+
+        let unknown = engine.resolve(&identity, &WindowState::default());
+        assert_eq!(unknown, EffectiveRule::from(&config.global_rule));
+    }
+
+    #[test]
+    fn monitor_overrides_sit_between_global_and_window_rules() {
+        let config: UserConfig = serde_jsonc2::from_str(
+            r#"{
+                "global": { "border_width": 2, "border_offset": 0 },
+                "monitors": {
+                    "1": { "border_width": 4, "border_offset": 3 },
+                    "2": { "enabled": false }
+                },
+                "window_rules": [
+                    { "match": { "kind": "Process", "value": "firefox", "border_width": 6, "enabled": true } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let engine = RuleEngine::new(&config).unwrap();
+
+        let firefox_on_first = engine.resolve(&firefox("New Tab"), &on_monitor(1, "", false));
+        assert_eq!(firefox_on_first.border_width, 6);
+        assert_eq!(firefox_on_first.border_offset, 3);
+
+        let firefox_on_second = engine.resolve(&firefox("New Tab"), &on_monitor(2, "", false));
+        assert_eq!(firefox_on_second.enabled, Some(true));
+
+        let other_on_second = engine.resolve(&window("", "", "chrome"), &on_monitor(2, "", false));
+        assert_eq!(other_on_second.enabled, Some(false));
+        assert_eq!(other_on_second.border_width, 2);
+    }
+}
@@ -1,22 +1,50 @@
+use crate::core::app_state::APP_STATE;
+use crate::error::LogIfErr;
 use crate::exit_application;
 use crate::user_config::UserConfig;
+use crate::windows_api::WindowsApi;
+use crate::windows_api::WM_APP_PROFILESCHANGED;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Error;
 use anyhow::Result as AnyResult;
+use std::cell::RefCell;
 use std::str::FromStr;
+use std::sync::OnceLock;
+use tray_icon_win::menu::CheckMenuItem;
+use tray_icon_win::menu::IsMenuItem;
 use tray_icon_win::menu::Menu;
 use tray_icon_win::menu::MenuEvent;
 use tray_icon_win::menu::MenuItem;
 use tray_icon_win::menu::PredefinedMenuItem;
+use tray_icon_win::menu::Submenu;
 use tray_icon_win::Icon;
 use tray_icon_win::TrayIcon;
 use tray_icon_win::TrayIconBuilder;
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Foundation::WPARAM;
+
+/// Prefix of the ids of the profile menu items, followed by the profile name. The item for no
+/// profile has just the prefix.
+const PROFILE_ITEM_PREFIX: &str = "profile:";
+
+/// Position of the profiles submenu in the tray menu.
+const PROFILES_MENU_POSITION: usize = 3;
+
+/// Thread the tray icon was created on. Menus can only be changed from that thread.
+static TRAY_THREAD_ID: OnceLock<u32> = OnceLock::new();
+
+thread_local! {
+    /// The tray menu and its profiles submenu, on the thread that owns them.
+    static TRAY_MENU: RefCell<Option<(Menu, Submenu)>> = const { RefCell::new(None) };
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SystemTrayEvent {
     OpenConfig,
     ReloadConfig,
+    RevertConfig,
+    CycleProfile,
     Exit,
 }
 
@@ -28,6 +56,8 @@ impl SystemTrayEvent {
             SystemTrayEvent::ReloadConfig => {
                 let _ = UserConfig::reload();
             }
+            SystemTrayEvent::RevertConfig => UserConfig::revert(),
+            SystemTrayEvent::CycleProfile => UserConfig::cycle_profile(),
         }
     }
 
@@ -36,6 +66,8 @@ impl SystemTrayEvent {
             SystemTrayEvent::OpenConfig => type_name_of_val(&UserConfig::open),
             SystemTrayEvent::Exit => type_name_of_val(&exit_application),
             SystemTrayEvent::ReloadConfig => type_name_of_val(&UserConfig::reload),
+            SystemTrayEvent::RevertConfig => type_name_of_val(&UserConfig::revert),
+            SystemTrayEvent::CycleProfile => type_name_of_val(&UserConfig::cycle_profile),
         }
     }
 
@@ -58,6 +90,8 @@ impl FromStr for SystemTrayEvent {
         match event_name_split.as_slice() {
             ["open", "config"] => Ok(SystemTrayEvent::OpenConfig),
             ["reload", "config"] => Ok(SystemTrayEvent::ReloadConfig),
+            ["revert", "config"] => Ok(SystemTrayEvent::RevertConfig),
+            ["cycle", "profile"] => Ok(SystemTrayEvent::CycleProfile),
             ["exit"] => Ok(SystemTrayEvent::Exit),
             _ => bail!("Invalid menu event: {}", event),
         }
@@ -69,6 +103,8 @@ impl From<SystemTrayEvent> for &'static str {
         match value {
             SystemTrayEvent::OpenConfig => "open_config",
             SystemTrayEvent::ReloadConfig => "reload_config",
+            SystemTrayEvent::RevertConfig => "revert_config",
+            SystemTrayEvent::CycleProfile => "cycle_profile",
             SystemTrayEvent::Exit => "exit",
         }
     }
@@ -104,20 +140,37 @@ impl SystemTray {
             }
         };
 
+        let profiles_menu = create_profiles_menu()?;
+
         let tray_menu = Menu::new();
         tray_menu.append_items(&[
             &MenuItem::with_id(SystemTrayEvent::OpenConfig, "Open config", true, None),
             &MenuItem::with_id(SystemTrayEvent::ReloadConfig, "Reload config", true, None),
+            &MenuItem::with_id(
+                SystemTrayEvent::RevertConfig,
+                "Revert to previous config",
+                true,
+                None,
+            ),
+            &profiles_menu,
             &PredefinedMenuItem::separator(),
             &MenuItem::with_id(SystemTrayEvent::Exit, "Exit", true, None),
         ])?;
 
+        let _ = TRAY_THREAD_ID.set(WindowsApi::get_current_thread_id());
+        TRAY_MENU.with_borrow_mut(|menu| *menu = Some((tray_menu.clone(), profiles_menu)));
+
         let tray = TrayIconBuilder::new()
             .with_menu(Box::new(tray_menu.clone()))
             .with_tooltip(format!("tacky-borders v{}", env!("CARGO_PKG_VERSION")))
             .with_icon(icon)
             .on_menu_event(move |event: MenuEvent| {
-                if let Ok(event) = SystemTrayEvent::from_str(event.id.as_ref()) {
+                if let Some(profile) = event.id.as_ref().strip_prefix(PROFILE_ITEM_PREFIX) {
+                    UserConfig::set_profile(Some(profile).filter(|profile| !profile.is_empty()));
+                    // Clicking a check item toggles it, so the checks have to be redone even
+                    // if the profile did not change.
+                    rebuild_profiles_menu().log_if_err();
+                } else if let Ok(event) = SystemTrayEvent::from_str(event.id.as_ref()) {
                     event.execute()
                 }
             })
@@ -128,6 +181,82 @@ impl SystemTray {
     }
 }
 
+/// Brings the profiles submenu up to date with the config, e.g. after profiles were added or
+/// another profile was switched to.
+///
+/// Can be called from any thread; the menu is rebuilt on the tray thread.
+pub fn refresh_profiles_menu() {
+    let Some(&thread_id) = TRAY_THREAD_ID.get() else {
+        return;
+    };
+
+    if thread_id == WindowsApi::get_current_thread_id() {
+        rebuild_profiles_menu().log_if_err();
+    } else {
+        WindowsApi::post_thread_message_w(
+            thread_id,
+            WM_APP_PROFILESCHANGED,
+            WPARAM::default(),
+            LPARAM::default(),
+        )
+        .log_if_err();
+    }
+}
+
+/// Replaces the profiles submenu with a new one. Must be called on the tray thread.
+pub fn rebuild_profiles_menu() -> AnyResult<()> {
+    TRAY_MENU.with_borrow_mut(|menu| {
+        let Some((tray_menu, profiles_menu)) = menu else {
+            return Ok(());
+        };
+
+        let new_profiles_menu = create_profiles_menu()?;
+        tray_menu.remove(profiles_menu)?;
+        tray_menu.insert(&new_profiles_menu, PROFILES_MENU_POSITION)?;
+        *profiles_menu = new_profiles_menu;
+
+        Ok(())
+    })
+}
+
+/// Creates the submenu listing every profile, with the active one checked.
+fn create_profiles_menu() -> AnyResult<Submenu> {
+    let (profiles, active_profile) = {
+        let config = APP_STATE.config.read().unwrap();
+        let profiles: Vec<String> = config.profiles.keys().cloned().collect();
+        (profiles, config.active_profile.clone())
+    };
+
+    let none_item = CheckMenuItem::with_id(
+        PROFILE_ITEM_PREFIX,
+        "None",
+        true,
+        active_profile.is_none(),
+        None,
+    );
+    let profile_items: Vec<CheckMenuItem> = profiles
+        .iter()
+        .map(|profile| {
+            CheckMenuItem::with_id(
+                format!("{PROFILE_ITEM_PREFIX}{profile}"),
+                profile,
+                true,
+                active_profile.as_ref() == Some(profile),
+                None,
+            )
+        })
+        .collect();
+
+    let mut items: Vec<&dyn IsMenuItem> = vec![&none_item];
+    items.extend(profile_items.iter().map(|item| item as &dyn IsMenuItem));
+
+    Ok(Submenu::with_items(
+        "Profile",
+        !profiles.is_empty(),
+        &items,
+    )?)
+}
+
 // Helpers
 pub fn type_name_of_val<T: ?Sized>(_val: &T) -> &'static str {
     std::any::type_name::<T>()
@@ -1,43 +1,73 @@
 use crate::animation::AnimationsConfig;
-use crate::border_manager::reload_borders;
+use crate::border_manager::update_borders;
 use crate::colors::GlobalColor;
+use crate::config_backup::restore_snapshot;
+use crate::config_backup::save_snapshot;
+use crate::config_backup::Snapshot;
+use crate::config_location::resolve_config_location;
+use crate::config_location::ConfigLocation;
+use crate::config_location::RealFileSystem;
+use crate::config_migration::CONFIG_VERSION;
+use crate::config_validator::ConfigReport;
+use crate::config_variables::Variable;
 use crate::core::app_state::APP_STATE;
 use crate::core::dimension::deserialize_dimension;
 use crate::core::dimension::deserialize_optional_dimension;
+use crate::core::dimension::Dimension;
 use crate::core::keybindings::Keybindings;
 use crate::create_keybindings;
 use crate::error::LogIfErr;
 use crate::keyboard_hook::KEYBOARD_HOOK;
+use crate::rule_engine::RuleEngine;
+use crate::sys_tray::refresh_profiles_menu;
+use crate::window_state::AspectRatio;
+use crate::window_state::StatePredicate;
 use crate::windows_api::WindowsApi;
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result as AnyResult;
+use schema_jsonrs::json_schema;
 use schema_jsonrs::JsonSchema;
+use schema_jsonrs::Schema;
+use schema_jsonrs::SchemaGenerator;
 use serde::de;
 use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
-use std::fs::exists;
+use serde::Serializer;
+use serde_jsonc2::Value as JsonValue;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::env;
 use std::fs::read_to_string;
+use std::fs::remove_file;
 use std::fs::write;
 use std::fs::DirBuilder;
+use std::io::ErrorKind;
+use std::path::absolute;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::LazyLock;
+use std::sync::OnceLock;
 use std::sync::RwLock;
 use windows::Win32::Graphics::Dwm::DWMWCP_DEFAULT;
 use windows::Win32::Graphics::Dwm::DWMWCP_DONOTROUND;
 use windows::Win32::Graphics::Dwm::DWMWCP_ROUND;
 use windows::Win32::Graphics::Dwm::DWMWCP_ROUNDSMALL;
 
-/// Default configuration content stored as a YAML string.
-const DEFAULT_CONFIG: &str = include_str!("../resources/config.yaml");
+/// File in the config directory holding the name of the active profile, so it survives
+/// restarts.
+const ACTIVE_PROFILE_FILE_NAME: &str = "active-profile";
 
 pub static CONFIG_FORMAT: LazyLock<RwLock<ConfigFormat>> =
     LazyLock::new(|| RwLock::new(ConfigFormat::default()));
 
+/// Config file or directory given with `--config`, overriding the usual lookup.
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
 /// Represents the supported configuration file formats.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum ConfigFormat {
     #[cfg(feature = "json")]
     /// JSON configuration file.
@@ -48,13 +78,100 @@ pub enum ConfigFormat {
     #[cfg(feature = "yml")]
     /// YAML configuration file.
     Yaml,
+    #[cfg(feature = "toml")]
+    /// TOML configuration file.
+    Toml,
+    #[cfg(feature = "json5")]
+    /// JSON5 configuration file.
+    Json5,
     /// Placeholder for cases where no configuration type is detected.
     #[default]
     None,
 }
 
+impl ConfigFormat {
+    /// Returns the format matching the extension of `path`.
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(Self::from_extension)
+            .unwrap_or_default()
+    }
+
+    /// Returns the enabled format for a file extension (e.g. `yml`), or `None` if there is no
+    /// such format or it was not enabled at build time.
+    pub fn from_extension(extension: &str) -> Self {
+        match extension {
+            #[cfg(feature = "json")]
+            "json" => ConfigFormat::Json,
+            #[cfg(feature = "json")]
+            "jsonc" => ConfigFormat::Jsonc,
+            #[cfg(feature = "yml")]
+            "yaml" | "yml" => ConfigFormat::Yaml,
+            #[cfg(feature = "toml")]
+            "toml" => ConfigFormat::Toml,
+            #[cfg(feature = "json5")]
+            "json5" => ConfigFormat::Json5,
+            _ => ConfigFormat::None,
+        }
+    }
+
+    /// The format used for a new config file: the first enabled format that allows comments,
+    /// so the default config keeps its explanations.
+    pub fn preferred() -> Self {
+        [
+            #[cfg(feature = "json")]
+            ConfigFormat::Jsonc,
+            #[cfg(feature = "yml")]
+            ConfigFormat::Yaml,
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml,
+            #[cfg(feature = "json5")]
+            ConfigFormat::Json5,
+        ]
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+    }
+
+    /// The file extension used when writing a config in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => "json",
+            #[cfg(feature = "json")]
+            ConfigFormat::Jsonc => "jsonc",
+            #[cfg(feature = "yml")]
+            ConfigFormat::Yaml => "yaml",
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => "toml",
+            #[cfg(feature = "json5")]
+            ConfigFormat::Json5 => "json5",
+            ConfigFormat::None => "",
+        }
+    }
+
+    /// The bundled default config in this format. Every format but plain JSON keeps the
+    /// comments explaining each setting.
+    pub fn default_config(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => Some(include_str!("../resources/config.json")),
+            #[cfg(feature = "json")]
+            ConfigFormat::Jsonc => Some(include_str!("../resources/config.jsonc")),
+            #[cfg(feature = "yml")]
+            ConfigFormat::Yaml => Some(include_str!("../resources/config.yaml")),
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => Some(include_str!("../resources/config.toml")),
+            #[cfg(feature = "json5")]
+            ConfigFormat::Json5 => Some(include_str!("../resources/config.json5")),
+            ConfigFormat::None => None,
+        }
+    }
+}
+
 /// Defines options for border radius customization.
-#[derive(Debug, PartialEq, Clone, Default, JsonSchema)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum BorderStyle {
     /// Fully rounded borders.
     Round,
@@ -95,6 +212,60 @@ impl<'de> Deserialize<'de> for BorderStyle {
     }
 }
 
+impl Serialize for BorderStyle {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            BorderStyle::Round => serializer.serialize_str("Round"),
+            BorderStyle::Square => serializer.serialize_str("Square"),
+            BorderStyle::SmallRound => serializer.serialize_str("SmallRound"),
+            BorderStyle::Auto => serializer.serialize_str("Auto"),
+            BorderStyle::Radius(radius) => serializer.serialize_str(&format!("Radius({radius})")),
+        }
+    }
+}
+
+impl JsonSchema for BorderStyle {
+    fn schema_name() -> Cow<'static, str> {
+        "BorderStyle".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Defines options for border radius customization. Names are case-insensitive.",
+            "oneOf": [
+                {
+                    "type": "string",
+                    "enum": ["Round", "round"],
+                    "description": "Fully rounded borders."
+                },
+                {
+                    "type": "string",
+                    "enum": ["Square", "square"],
+                    "description": "Square borders with no rounding."
+                },
+                {
+                    "type": "string",
+                    "enum": ["SmallRound", "smallround"],
+                    "description": "Small rounding for subtle border effects."
+                },
+                {
+                    "type": "string",
+                    "enum": ["Auto", "auto"],
+                    "description": "Automatically determine the border radius based on the window's corner preference."
+                },
+                {
+                    "type": "string",
+                    "pattern": "^[Rr][Aa][Dd][Ii][Uu][Ss]\\([-+]?[0-9]*\\.?[0-9]+\\)$",
+                    "description": "Custom border radius in pixels, e.g. 'Radius(10)'."
+                }
+            ]
+        })
+    }
+}
+
 impl BorderStyle {
     pub fn to_radius(&self, border_width: i32, dpi: f32, tracking_window: isize) -> f32 {
         let base_radius = (border_width as f32) / 2.0;
@@ -126,10 +297,22 @@ pub enum MatchKind {
     Class,
     /// Match based on the process name or executable associated with the window.
     Process,
+    /// Match based on the file name of the executable, e.g. `Code - Insiders.exe`.
+    ProcessFullName,
+    /// Match based on the full path of the executable.
+    ExePath,
+    /// Match based on the app user model ID of a packaged app. UWP apps are matched by the
+    /// app they host rather than `ApplicationFrameHost`.
+    AppUserModelId,
+    /// Match based on the name of the process that started the window's process.
+    ParentProcess,
+    /// Match based on the monitor the window is on: its index (starting at 1), its device
+    /// name (e.g. `\\.\DISPLAY2`) or `primary`.
+    Monitor,
 }
 
 /// Defines the strategy for matching a value against a criterion.
-#[derive(Debug, Deserialize, PartialEq, Clone, JsonSchema)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
 pub enum MatchStrategy {
     /// Match values that are exactly equal.
     Equals,
@@ -139,9 +322,14 @@ pub enum MatchStrategy {
     Contains,
 }
 
-/// Represents criteria used to match windows for applying specific configurations.
-#[derive(Debug, Deserialize, Clone, Default, PartialEq, JsonSchema)]
-pub struct WindowMatchConfig {
+/// A condition tree used to match windows.
+///
+/// A condition can be a leaf (`kind`, `value` and `strategy`), tests on the window's state
+/// (`maximized`, `fullscreen`, `topmost`, `min_width`, `min_height` and `aspect_ratio`) and/or
+/// a group of nested conditions (`all`, `any` and `not`). Every part that is present must hold
+/// for the condition to match.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct MatchCondition {
     /// Type of match (e.g., title, class, or process).
     #[serde(rename = "kind")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -154,6 +342,71 @@ pub struct WindowMatchConfig {
     #[serde(rename = "strategy")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub match_strategy: Option<MatchStrategy>,
+    /// Matches when every nested condition matches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all: Option<Vec<MatchCondition>>,
+    /// Matches when at least one nested condition matches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub any: Option<Vec<MatchCondition>>,
+    /// Matches when the nested condition does not match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not: Option<Box<MatchCondition>>,
+    /// Matches maximized windows if `true`, or windows that are not maximized if `false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximized: Option<bool>,
+    /// Matches windows covering their whole monitor if `true`, or any other window if `false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fullscreen: Option<bool>,
+    /// Matches always-on-top windows if `true`, or any other window if `false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topmost: Option<bool>,
+    /// Matches windows at least this wide, in pixels at 100% scaling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "deserialize_optional_dimension", default)]
+    #[schemars(with = "Option<Dimension>")]
+    pub min_width: Option<i32>,
+    /// Matches windows at least this tall, in pixels at 100% scaling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "deserialize_optional_dimension", default)]
+    #[schemars(with = "Option<Dimension>")]
+    pub min_height: Option<i32>,
+    /// Matches windows by width divided by height, e.g. `"16:9"` or `"> 2"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<AspectRatio>,
+}
+
+impl MatchCondition {
+    /// Returns `true` if neither a leaf, a state test nor any nested group is specified.
+    pub fn is_empty(&self) -> bool {
+        self.match_kind.is_none()
+            && self.all.is_none()
+            && self.any.is_none()
+            && self.not.is_none()
+            && self.state_predicates().is_empty()
+    }
+
+    /// Returns the tests on the window's state set directly on this condition.
+    pub fn state_predicates(&self) -> Vec<StatePredicate> {
+        [
+            self.maximized.map(StatePredicate::Maximized),
+            self.fullscreen.map(StatePredicate::Fullscreen),
+            self.topmost.map(StatePredicate::Topmost),
+            self.min_width.map(StatePredicate::MinWidth),
+            self.min_height.map(StatePredicate::MinHeight),
+            self.aspect_ratio.clone().map(StatePredicate::AspectRatio),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// Represents criteria used to match windows for applying specific configurations.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct WindowMatchConfig {
+    /// Which windows the rule applies to.
+    #[serde(flatten)]
+    pub condition: MatchCondition,
     /// Color for the border when the window is active.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub active_color: Option<GlobalColor>,
@@ -169,10 +422,12 @@ pub struct WindowMatchConfig {
     /// Width of the border in pixels.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(deserialize_with = "deserialize_optional_dimension", default)]
+    #[schemars(with = "Option<Dimension>")]
     pub border_width: Option<i32>,
     /// Offset of the border relative to the window.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(deserialize_with = "deserialize_optional_dimension", default)]
+    #[schemars(with = "Option<Dimension>")]
     pub border_offset: Option<i32>,
     /// Whether borders are enabled for this match.
     #[serde(rename = "enabled")]
@@ -183,16 +438,201 @@ pub struct WindowMatchConfig {
     pub initialize_delay: Option<u64>,
     /// Delay (in milliseconds) before applying the border after unminimizing.
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(alias = "restore_delay")]
     pub unminimize_delay: Option<u64>,
 }
 
+/// Settings that override `global` for every window on a monitor.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct MonitorConfig {
+    /// Color for the border when the window is active.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_color: Option<GlobalColor>,
+    /// Color for the border when the window is inactive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inactive_color: Option<GlobalColor>,
+    /// Animation settings for the window borders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animations: Option<AnimationsConfig>,
+    /// Radius of the border corners.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_style: Option<BorderStyle>,
+    /// Width of the border in pixels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "deserialize_optional_dimension", default)]
+    #[schemars(with = "Option<Dimension>")]
+    pub border_width: Option<i32>,
+    /// Offset of the border relative to the window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "deserialize_optional_dimension", default)]
+    #[schemars(with = "Option<Dimension>")]
+    pub border_offset: Option<i32>,
+    /// Whether borders are enabled on this monitor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Delay (in milliseconds) before applying the border after initialization.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initialize_delay: Option<u64>,
+    /// Delay (in milliseconds) before applying the border after unminimizing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unminimize_delay: Option<u64>,
+}
+
+impl MonitorConfig {
+    /// Returns the overrides as the settings of a window rule, so they merge the same way.
+    pub fn settings(&self) -> WindowMatchConfig {
+        WindowMatchConfig {
+            active_color: self.active_color.clone(),
+            inactive_color: self.inactive_color.clone(),
+            animations: self.animations.clone(),
+            border_style: self.border_style.clone(),
+            border_width: self.border_width,
+            border_offset: self.border_offset,
+            enabled: self.enabled,
+            initialize_delay: self.initialize_delay,
+            unminimize_delay: self.unminimize_delay,
+            ..Default::default()
+        }
+    }
+}
+
 /// Represents a rule for a specific window, including matching criteria and associated actions.
-#[derive(Debug, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
 pub struct WindowRuleConfig {
     /// The matching details and settings for a specific type of window.
     #[serde(rename = "match")]
     pub match_window: WindowMatchConfig,
+    /// Order in which matching rules are merged. Higher priorities are applied later and
+    /// therefore win. Rules with the same priority keep their order in the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    /// Stops the cascade after this rule, so no matching rule that would be applied after it
+    /// is: neither rules with a higher priority nor rules with the same priority further down.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<bool>,
+}
+
+/// The settings that apply to a single window once every matching rule has been merged on
+/// top of the global configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EffectiveRule {
+    /// Whether borders are enabled; `None` if no matching rule decided it.
+    pub enabled: Option<bool>,
+    pub active_color: GlobalColor,
+    pub inactive_color: GlobalColor,
+    pub animations: AnimationsConfig,
+    pub border_style: BorderStyle,
+    pub border_width: i32,
+    pub border_offset: i32,
+    pub initialize_delay: u64,
+    pub unminimize_delay: u64,
+}
+
+impl From<&GlobalRuleConfig> for EffectiveRule {
+    fn from(global: &GlobalRuleConfig) -> Self {
+        Self {
+            enabled: None,
+            active_color: global.active_color.clone(),
+            inactive_color: global.inactive_color.clone(),
+            animations: global.animations.clone(),
+            border_style: global.border_style.clone(),
+            border_width: global.border_width,
+            border_offset: global.border_offset,
+            initialize_delay: global.initialize_delay,
+            unminimize_delay: global.unminimize_delay,
+        }
+    }
+}
+
+impl EffectiveRule {
+    /// Merges the given matching rules on top of `base`, usually the global configuration.
+    ///
+    /// Rules are applied in ascending `priority` order (ties keep their original order),
+    /// each one overriding only the fields it sets. A rule with `stop: true` ends the cascade,
+    /// so the rules after it in that order, including every higher priority, are skipped.
+    pub fn resolve<'a, I>(base: Self, matching_rules: I) -> Self
+    where
+        I: IntoIterator<Item = &'a WindowRuleConfig>,
+    {
+        let mut rules: Vec<&WindowRuleConfig> = matching_rules.into_iter().collect();
+        rules.sort_by_key(|rule| rule.priority.unwrap_or_default());
+
+        let mut effective = base;
+        for rule in rules {
+            effective.apply(&rule.match_window);
+
+            if rule.stop == Some(true) {
+                break;
+            }
+        }
+
+        effective
+    }
+
+    /// Overrides the fields that are set in `rule`.
+    pub fn apply(&mut self, rule: &WindowMatchConfig) {
+        if let Some(enabled) = rule.enabled {
+            self.enabled = Some(enabled);
+        }
+        if let Some(ref active_color) = rule.active_color {
+            self.active_color = active_color.clone();
+        }
+        if let Some(ref inactive_color) = rule.inactive_color {
+            self.inactive_color = inactive_color.clone();
+        }
+        if let Some(ref animations) = rule.animations {
+            self.animations = animations.clone();
+        }
+        if let Some(ref border_style) = rule.border_style {
+            self.border_style = border_style.clone();
+        }
+        if let Some(border_width) = rule.border_width {
+            self.border_width = border_width;
+        }
+        if let Some(border_offset) = rule.border_offset {
+            self.border_offset = border_offset;
+        }
+        if let Some(initialize_delay) = rule.initialize_delay {
+            self.initialize_delay = initialize_delay;
+        }
+        if let Some(unminimize_delay) = rule.unminimize_delay {
+            self.unminimize_delay = unminimize_delay;
+        }
+    }
+
+    /// Whether a border should be drawn. Rules that don't decide it leave windows with a
+    /// filtered style, which `has_filtered_style` is only asked for then, without a border.
+    pub fn is_enabled(&self, has_filtered_style: impl FnOnce() -> bool) -> bool {
+        match self.enabled {
+            Some(enabled) => enabled,
+            None => !has_filtered_style(),
+        }
+    }
+
+    /// Compares this rule to the one a border is currently drawn with, so a border only
+    /// rebuilds the parts that changed.
+    pub fn changes_from(&self, current: &EffectiveRule) -> RuleChanges {
+        RuleChanges {
+            colors: self.active_color != current.active_color
+                || self.inactive_color != current.inactive_color,
+            animations: self.animations != current.animations,
+            shape: self.border_width != current.border_width
+                || self.border_style != current.border_style
+                || self.border_offset != current.border_offset,
+        }
+    }
+}
+
+/// The parts of a border that have to be rebuilt when its [`EffectiveRule`] changes.
+///
+/// The delays are not included since they only apply when a border is created or shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RuleChanges {
+    /// The active or inactive color changed.
+    pub colors: bool,
+    /// The animations changed.
+    pub animations: bool,
+    /// The width, style or offset changed.
+    pub shape: bool,
 }
 
 fn serde_default_global() -> GlobalRuleConfig {
@@ -204,7 +644,7 @@ fn serde_default_global() -> GlobalRuleConfig {
 }
 
 /// Contains global configuration settings applied across all windows.
-#[derive(Debug, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
 #[serde(default)]
 pub struct GlobalRuleConfig {
     /// Default width of the window borders.
@@ -212,12 +652,14 @@ pub struct GlobalRuleConfig {
         deserialize_with = "deserialize_dimension",
         default = "serde_default_i32::<2>"
     )]
+    #[schemars(with = "Dimension")]
     pub border_width: i32,
     /// Default offset for the window borders.
     #[serde(
         deserialize_with = "deserialize_dimension",
         default = "serde_default_i32::<-1>"
     )]
+    #[schemars(with = "Dimension")]
     pub border_offset: i32,
     /// Default border radius settings.
     #[serde(default)]
@@ -232,23 +674,51 @@ pub struct GlobalRuleConfig {
     #[serde(default)]
     pub animations: AnimationsConfig,
     /// Delay (in milliseconds) before applying borders after initialization.
-    #[serde(alias = "init_delay", default = "serde_default_u64::<250>")]
+    #[serde(default = "serde_default_u64::<250>")]
     pub initialize_delay: u64,
     /// Delay (in milliseconds) before applying borders after unminimizing.
-    #[serde(alias = "restore_delay", default = "serde_default_u64::<200>")]
+    #[serde(default = "serde_default_u64::<200>")]
     pub unminimize_delay: u64,
 }
 
+/// A named set of overrides that can be switched to at runtime.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// Global settings to override. Settings that are left out keep their value from `global`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<GlobalRuleConfig>")]
+    pub global: Option<JsonValue>,
+    /// Window rules to use instead of the config's own, including the ones in `rules.d`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub window_rules: Vec<WindowRuleConfig>,
+    /// Monitor overrides to merge into `monitors`, per monitor and setting.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub monitors: BTreeMap<String, MonitorConfig>,
+    /// Theme to use instead of `theme`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
 /// Stores the complete configuration including global rules, window rules, and keybindings.
-#[derive(Debug, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
 #[serde(default)]
 pub struct UserConfig {
+    /// Version of the config format. Configs written for an older version are migrated when
+    /// they are loaded.
+    #[serde(default = "serde_default_version")]
+    pub version: u32,
     /// Global settings applied across all windows.
     #[serde(rename = "global", default = "serde_default_global")]
     pub global_rule: GlobalRuleConfig,
     /// Specific rules for individual windows.
     #[serde(default)]
     pub window_rules: Vec<WindowRuleConfig>,
+    /// Settings that override `global` for windows on a monitor, keyed by the monitor's index
+    /// (starting at 1), device name (e.g. `\\.\DISPLAY2`) or `primary`. Window rules are
+    /// applied on top.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub monitors: BTreeMap<String, MonitorConfig>,
     /// Application keybindings.
     #[serde(default)]
     pub keybindings: Keybindings,
@@ -256,49 +726,124 @@ pub struct UserConfig {
     #[serde(default)]
     pub monitor_config_changes: bool,
     /// Enable custom predefined theme
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub theme: Option<String>,
+    /// Named values that any string in the config can reference as `$name` or `${name}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, Variable>,
+    /// Same as `variables`, meant for naming colors. `variables` wins if both define a name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub palette: BTreeMap<String, Variable>,
+    /// Other config files to merge into this one, as paths or glob patterns relative to this
+    /// file. Their `window_rules` are added before this file's and their other keys are
+    /// overridden by it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Named sets of overrides for `global`, `window_rules`, `monitors` and `theme`, selectable
+    /// from the tray menu or with the `cycle_profile` keybinding.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// Every file the configuration was loaded from. Filled in while loading.
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+    /// The profile applied on top of the config, if any. Filled in while loading.
+    #[serde(skip)]
+    pub active_profile: Option<String>,
 }
 
 /// Methods for managing the configuration, including loading, saving, and reloading.
 impl UserConfig {
     /// Attempts to create a new configuration instance by reading from the config file.
+    ///
+    /// If the configuration is valid, the files it was loaded from are saved as the last
+    /// known-good config.
     pub fn create() -> AnyResult<Self> {
         let config_dir = UserConfig::get_config_dir().unwrap_or_default();
-        let config_file = UserConfig::detect_config_file(&config_dir)
-            .unwrap_or(Self::create_default_config(&config_dir).unwrap_or_default());
-        let config_format = UserConfig::detect_config_format(&config_dir).unwrap_or_default();
+        let config_file = UserConfig::get_config_file()
+            .unwrap_or_else(|_| Self::create_default_config(&config_dir).unwrap_or_default());
         let contents = read_to_string(&config_file)
             .with_context(|| format!("failed to read config file: {}", config_file.display()))?;
 
-        *CONFIG_FORMAT.write().unwrap() = config_format.clone();
-        let config = Self::deserialize(contents)?;
+        let config = Self::parse(
+            &config_file,
+            &contents,
+            Self::get_active_profile().as_deref(),
+        )?;
+
+        save_snapshot(&config_file, &config.sources)
+            .log_if_err_message("could not save last known-good config", false);
 
         Ok(config)
     }
 
-    /// Deserializes configuration content into a `Config` instance based on the file format.
-    fn deserialize(contents: String) -> AnyResult<Self> {
-        let config_format = &*CONFIG_FORMAT
-            .read()
-            .map_err(|_| anyhow!("config format lock poisoned"))?;
+    /// Reads and validates the given config file with `profile` applied, without any side
+    /// effects, such as writing a default config or saving a last known-good copy.
+    pub fn from_file(config_file: &Path, profile: Option<&str>) -> AnyResult<Self> {
+        let contents = read_to_string(config_file)
+            .with_context(|| format!("failed to read config file: {}", config_file.display()))?;
 
-        #[cfg(feature = "json")]
-        if matches!(config_format, ConfigFormat::Json | ConfigFormat::Jsonc) {
-            return serde_jsonc2::from_str(&contents).with_context(|| "failed to deserialize JSON");
-        }
+        Self::parse(config_file, &contents, profile)
+    }
+
+    /// Loads the last configuration that was applied successfully.
+    pub fn create_last_good() -> AnyResult<Self> {
+        let config_file = Self::get_config_file()?;
+        let snapshot = Snapshot::latest(&config_file);
+        let last_good_file = snapshot
+            .copy_of(&absolute(&config_file)?)
+            .ok_or_else(|| anyhow!("no last known-good config"))?;
+        let contents = read_to_string(&last_good_file).with_context(|| {
+            format!(
+                "failed to read last known-good config: {}",
+                last_good_file.display()
+            )
+        })?;
 
-        #[cfg(feature = "yml")]
-        if matches!(config_format, ConfigFormat::Yaml) {
-            return serde_yml::from_str(&contents).with_context(|| "failed to deserialize YAML");
+        let mut config = Self::parse(
+            &last_good_file,
+            &contents,
+            Self::get_active_profile().as_deref(),
+        )?;
+
+        // Watch the real files rather than the copies, so fixing them triggers a reload.
+        for source in config.sources.iter_mut() {
+            if let Some(original) = snapshot.original_of(source) {
+                *source = original;
+            }
         }
 
-        Err(anyhow!("unsupported configuration format"))
+        Ok(config)
     }
 
-    /// Detects the configuration file in the given directory or creates a default config file if none exists.
+    /// Parses and validates configuration content, using the format given by the file extension.
+    fn parse(config_file: &Path, contents: &str, profile: Option<&str>) -> AnyResult<Self> {
+        let config_format = ConfigFormat::from_path(config_file);
+        *CONFIG_FORMAT.write().unwrap() = config_format.clone();
+
+        ConfigReport::new(config_file, contents, &config_format, profile).into_result()
+    }
+
+    /// Reads the configuration and compiles its window rules.
+    ///
+    /// Invalid window rules (e.g. a malformed regex) make the whole configuration invalid.
+    pub fn create_with_rule_engine() -> AnyResult<(Self, RuleEngine)> {
+        let config = Self::create()?;
+        let rule_engine = RuleEngine::new(&config)?;
+
+        Ok((config, rule_engine))
+    }
+
+    /// Detects the configuration file in the given directory.
     pub fn detect_config_file(config_dir: &Path) -> AnyResult<PathBuf> {
-        let candidates = [
-            "json",
+        Self::config_files(config_dir)
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("config file not found"))
+    }
+
+    /// Returns every config file in the given directory, in the order they are looked up.
+    pub fn config_files(config_dir: &Path) -> Vec<PathBuf> {
+        let candidates: &[&str] = &[
             #[cfg(feature = "json")]
             "json",
             #[cfg(feature = "json")]
@@ -307,114 +852,121 @@ impl UserConfig {
             "yaml",
             #[cfg(feature = "yml")]
             "yml",
+            #[cfg(feature = "toml")]
+            "toml",
+            #[cfg(feature = "json5")]
+            "json5",
         ];
 
-        for ext in candidates {
-            let file_path = config_dir.join("config").with_extension(ext);
-            if exists(file_path.clone())? {
-                return Ok(file_path);
-            }
-        }
-
-        Err(anyhow!("config file not found"))
+        candidates
+            .iter()
+            .map(|ext| config_dir.join("config").with_extension(ext))
+            .filter(|file_path| file_path.is_file())
+            .collect()
     }
 
-    /// Creates a default configuration file in the specified directory.
+    /// Creates a default configuration file in the specified directory, in the preferred
+    /// enabled format.
     pub fn create_default_config(config_dir: &Path) -> AnyResult<PathBuf> {
-        let path = config_dir.join("config.yaml");
-        write(path.clone(), DEFAULT_CONFIG.as_bytes())
+        Self::write_default_config(config_dir, &ConfigFormat::preferred())
+    }
+
+    /// Writes the default configuration in the given format, replacing any existing file of
+    /// the same name.
+    pub fn write_default_config(config_dir: &Path, format: &ConfigFormat) -> AnyResult<PathBuf> {
+        let contents = format
+            .default_config()
+            .ok_or_else(|| anyhow!("no config format is enabled"))?;
+        let path = config_dir.join("config").with_extension(format.extension());
+
+        write(&path, contents)
             .with_context(|| format!("failed to write default config to {}", path.display()))?;
 
-        Ok(path.clone())
+        Ok(path)
     }
 
-    pub fn detect_config_format(config_dir: &Path) -> AnyResult<ConfigFormat> {
-        let candidates = [
-            #[cfg(feature = "json")]
-            ("json", ConfigFormat::Json),
-            #[cfg(feature = "json")]
-            ("jsonc", ConfigFormat::Jsonc),
-            #[cfg(feature = "yml")]
-            ("yaml", ConfigFormat::Yaml),
-            #[cfg(feature = "yml")]
-            ("yml", ConfigFormat::Yaml),
-        ];
+    /// Overrides the config file lookup with a file or directory, as given with `--config`.
+    ///
+    /// Must be called before the config is first loaded; later calls are ignored.
+    pub fn set_config_path(path: PathBuf) {
+        let _ = CONFIG_PATH_OVERRIDE.set(path);
+    }
 
-        for (ext, config_type) in candidates {
-            let file_path = config_dir.join("config").with_extension(ext);
-            if exists(file_path.clone())? {
-                return Ok(config_type);
-            }
+    /// Returns where the config lives, from `--config` if given or else from the environment.
+    ///
+    /// See [`resolve_config_location`] for the order the locations are searched in.
+    pub fn get_config_location() -> AnyResult<ConfigLocation> {
+        if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+            return Ok(ConfigLocation::from_path(path, &RealFileSystem));
         }
 
-        #[cfg(all(feature = "yml", not(feature = "json")))]
-        {
-            Ok(ConfigFormat::Yaml)
-        }
+        let env: HashMap<String, String> = env::vars().collect();
+        #[cfg(windows)]
+        let home_dir = WindowsApi::home_dir().ok();
+        #[cfg(not(windows))]
+        let home_dir = env.get("HOME").map(PathBuf::from);
 
-        #[cfg(all(feature = "json", not(feature = "yml")))]
-        {
-            Ok(ConfigFormat::Json)
-        }
+        resolve_config_location(&env, home_dir.as_deref(), &RealFileSystem)
+            .ok_or_else(|| anyhow!("could not determine the config directory"))
+    }
 
-        #[cfg(all(feature = "json", feature = "yml"))]
-        {
-            Ok(ConfigFormat::Json) // Priority is YAML
-        }
+    /// Returns the config file to load: the one given explicitly, or else the one detected in
+    /// the config directory.
+    ///
+    /// Unlike [`UserConfig::get_config_dir`], this never creates the config directory.
+    pub fn get_config_file() -> AnyResult<PathBuf> {
+        let location = Self::get_config_location()?;
 
-        #[cfg(not(any(feature = "json", feature = "yml")))]
-        {
-            Err(anyhow::anyhow!("No supported config format found"))
+        match location.file {
+            Some(file) => Ok(file),
+            None => Self::detect_config_file(&location.dir),
         }
     }
 
     /// Retrieves the configuration directory, creating it if necessary.
     pub fn get_config_dir() -> AnyResult<PathBuf> {
-        let home_dir = WindowsApi::home_dir()?;
-        let config_dir = home_dir.join(".config").join("tacky-borders");
-        let fallback_dir = home_dir.join(".tacky-borders");
+        let location = Self::get_config_location()?;
 
-        if exists(config_dir.clone())
-            .with_context(|| format!("could not find directory: {}", config_dir.display()))?
-        {
-            return Ok(config_dir);
-        } else if exists(fallback_dir.clone())
-            .with_context(|| format!("could not find directory: {}", fallback_dir.display()))?
-        {
-            return Ok(fallback_dir);
+        if !location.exists {
+            DirBuilder::new()
+                .recursive(true)
+                .create(&location.dir)
+                .with_context(|| {
+                    format!(
+                        "could not create config directory: {}",
+                        location.dir.display()
+                    )
+                })?;
         }
 
-        DirBuilder::new()
-            .recursive(true)
-            .create(&config_dir)
-            .map_err(|_| anyhow!("could not create config directory"))?;
-
-        Ok(config_dir)
+        Ok(location.dir)
     }
 
     /// Update the configuration by reinitializing it from the configuration file.
     ///
     /// This method replaces the current configuration with a newly loaded one.
-    /// If loading fails, it falls back to the default configuration and logs an error.
+    /// If loading fails, the current configuration is kept and the errors are logged.
     pub fn update() {
-        let new_config = match Self::create() {
-            Ok(config) => {
+        let (new_config, new_rule_engine) = match Self::create_with_rule_engine() {
+            Ok((config, rule_engine)) => {
                 let mut config_watcher = APP_STATE.config_watcher.write().unwrap();
+                config_watcher.set_paths(config.sources.clone());
 
                 if config.monitor_config_changes && !config_watcher.is_running() {
                     config_watcher.start().log_if_err();
                 } else if !config.monitor_config_changes && config_watcher.is_running() {
                     config_watcher.stop().log_if_err();
                 }
-                config
+                (config, rule_engine)
             }
             Err(e) => {
-                error!("could not reload config: {e}");
-                UserConfig::default()
+                error!("could not reload config, keeping the current one: {e:#}");
+                return;
             }
         };
 
         *APP_STATE.config.write().unwrap() = new_config;
+        *APP_STATE.rule_engine.write().unwrap() = new_rule_engine;
     }
 
     /// Reloads the application configuration and restarts the borders.
@@ -428,21 +980,32 @@ impl UserConfig {
     /// - The borders are reloaded, which may involve reinitializing UI components.
     /// - If a keyboard hook is available, the keybindings are refreshed and applied.
     pub fn reload() -> bool {
-        debug!("reloading application configuration and restarting borders.");
+        debug!("reloading application configuration and updating borders.");
         let old_config = (*APP_STATE.config.read().unwrap()).clone();
         Self::update();
-        let new_config = APP_STATE.config.read().unwrap();
+        let new_config = (*APP_STATE.config.read().unwrap()).clone();
+
+        if old_config == new_config {
+            return false;
+        }
 
-        if old_config != *new_config {
-            reload_borders();
+        update_borders();
+
+        if old_config.profiles != new_config.profiles
+            || old_config.active_profile != new_config.active_profile
+        {
+            refresh_profiles_menu();
+        }
+
+        if old_config.keybindings != new_config.keybindings {
             if let Some(hook) = KEYBOARD_HOOK.get() {
                 if let Ok(bindings) = create_keybindings() {
                     hook.update(&bindings);
                 }
             }
-            return true;
         }
-        false
+
+        true
     }
 
     /// Opens the configuration file in the default editor.
@@ -450,24 +1013,107 @@ impl UserConfig {
     /// This method determines the configuration file's path based on the current config type
     /// (e.g., JSON, YAML, JSONC) and attempts to open it using the default file association on the system.
     pub fn open() {
-        match Self::get_config_dir() {
-            Ok(dir) => {
-                let config_file_res = Self::detect_config_file(dir.as_path());
-                match config_file_res {
-                    Ok(config_file) => {
-                        win_open::that(config_file).log_if_err();
-                    }
-                    Err(e) => {
-                        error!("{e}");
-                    }
-                }
+        match Self::get_config_file() {
+            Ok(config_file) => {
+                win_open::that(config_file).log_if_err();
             }
             Err(err) => error!("{err}"),
         }
     }
+
+    /// Restores the last known-good configuration over the config files and reloads it.
+    ///
+    /// The newest known-good config that differs from the files on disk is restored, so this
+    /// also undoes a change that is valid. Replaced files are kept as `.bak` files.
+    pub fn revert() {
+        match Self::get_config_file().and_then(|file| restore_snapshot(&file)) {
+            Ok(backups) => {
+                for backup in backups {
+                    info!("kept the replaced config file as {}", backup.display());
+                }
+                info!("restored the last known-good config");
+                Self::reload();
+            }
+            Err(err) => error!("could not revert config: {err:#}"),
+        }
+    }
+
+    /// Returns the name of the profile that was last switched to, if any.
+    pub fn get_active_profile() -> Option<String> {
+        read_active_profile(&Self::get_config_location().ok()?.dir)
+    }
+
+    /// Switches to `profile`, or back to no profile, and reloads the config with it.
+    ///
+    /// The choice is saved in the config directory so it is kept across restarts.
+    pub fn set_profile(profile: Option<&str>) {
+        let config_dir = match Self::get_config_dir() {
+            Ok(config_dir) => config_dir,
+            Err(err) => {
+                error!("could not switch profile: {err:#}");
+                return;
+            }
+        };
+
+        if let Err(err) = write_active_profile(&config_dir, profile) {
+            error!(
+                "could not save active profile to {}: {err}",
+                config_dir.join(ACTIVE_PROFILE_FILE_NAME).display()
+            );
+            return;
+        }
+
+        info!("switched to profile: {}", profile.unwrap_or("none"));
+        Self::reload();
+    }
+
+    /// Switches to the profile after the active one, in the order they are listed in the tray
+    /// menu. After the last profile, it switches back to no profile.
+    pub fn cycle_profile() {
+        let (profiles, active_profile) = {
+            let config = APP_STATE.config.read().unwrap();
+            let profiles: Vec<String> = config.profiles.keys().cloned().collect();
+            (profiles, config.active_profile.clone())
+        };
+
+        let next = match active_profile {
+            Some(active) => profiles
+                .iter()
+                .position(|profile| *profile == active)
+                .and_then(|index| profiles.get(index + 1)),
+            None => profiles.first(),
+        };
+
+        Self::set_profile(next.map(String::as_str));
+    }
 }
 
 // Helpers
+/// Returns the profile saved in `config_dir`, if any.
+fn read_active_profile(config_dir: &Path) -> Option<String> {
+    let file = config_dir.join(ACTIVE_PROFILE_FILE_NAME);
+    let profile = read_to_string(file).ok()?.trim().to_string();
+
+    (!profile.is_empty()).then_some(profile)
+}
+
+/// Saves `profile` as the active profile in `config_dir`, or clears it.
+fn write_active_profile(config_dir: &Path, profile: Option<&str>) -> std::io::Result<()> {
+    let file = config_dir.join(ACTIVE_PROFILE_FILE_NAME);
+
+    match profile {
+        Some(profile) => write(&file, profile),
+        None => remove_file(&file).or_else(|err| match err.kind() {
+            ErrorKind::NotFound => Ok(()),
+            _ => Err(err),
+        }),
+    }
+}
+
+fn serde_default_version() -> u32 {
+    CONFIG_VERSION
+}
+
 fn serde_default_u64<const V: u64>() -> u64 {
     V
 }
@@ -475,3 +1121,195 @@ fn serde_default_u64<const V: u64>() -> u64 {
 fn serde_default_i32<const V: i32>() -> i32 {
     V
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Resolves the given rules, as if they all matched, on top of the default settings.
+    fn resolve(window_rules: &str) -> EffectiveRule {
+        let rules: Vec<WindowRuleConfig> = serde_jsonc2::from_str(window_rules).unwrap();
+        EffectiveRule::resolve(EffectiveRule::default(), &rules)
+    }
+
+    #[test]
+    fn rules_only_override_what_they_set() {
+        let effective = resolve(
+            r#"[
+                { "match": { "kind": "Process", "value": "a", "border_width": 3, "enabled": true } },
+                { "match": { "kind": "Process", "value": "a", "border_offset": 5 } }
+            ]"#,
+        );
+
+        assert_eq!(effective.border_width, 3);
+        assert_eq!(effective.border_offset, 5);
+        assert_eq!(effective.enabled, Some(true));
+    }
+
+    #[test]
+    fn later_rules_win_ties() {
+        let effective = resolve(
+            r#"[
+                { "match": { "kind": "Process", "value": "a", "border_width": 3 }, "priority": 1 },
+                { "match": { "kind": "Process", "value": "a", "border_width": 5 }, "priority": 1 }
+            ]"#,
+        );
+
+        assert_eq!(effective.border_width, 5);
+    }
+
+    #[test]
+    fn higher_priorities_win() {
+        let effective = resolve(
+            r#"[
+                { "match": { "kind": "Process", "value": "a", "border_width": 3 }, "priority": 1 },
+                { "match": { "kind": "Process", "value": "a", "border_width": 5 } },
+                { "match": { "kind": "Process", "value": "a", "border_offset": 4 }, "priority": -1 },
+                { "match": { "kind": "Process", "value": "a", "border_offset": 6 }, "priority": -2 }
+            ]"#,
+        );
+
+        assert_eq!(effective.border_width, 3);
+        assert_eq!(effective.border_offset, 4);
+    }
+
+    #[test]
+    fn stop_skips_the_rules_applied_after_it() {
+        let effective = resolve(
+            r#"[
+                { "match": { "kind": "Process", "value": "a", "border_width": 3 }, "stop": true },
+                { "match": { "kind": "Process", "value": "a", "border_width": 5, "enabled": false } },
+                { "match": { "kind": "Process", "value": "a", "border_offset": 4 }, "priority": 1 }
+            ]"#,
+        );
+
+        assert_eq!(effective.border_width, 3);
+        assert_eq!(effective.border_offset, 0);
+        assert_eq!(effective.enabled, None);
+    }
+
+    #[test]
+    fn stop_keeps_the_rules_applied_before_it() {
+        let effective = resolve(
+            r#"[
+                { "match": { "kind": "Process", "value": "a", "border_width": 3 }, "stop": true },
+                { "match": { "kind": "Process", "value": "a", "border_width": 5, "border_offset": 4 }, "priority": -1 }
+            ]"#,
+        );
+
+        assert_eq!(effective.border_width, 3);
+        assert_eq!(effective.border_offset, 4);
+    }
+
+    #[test]
+    fn unchanged_rules_rebuild_nothing() {
+        let current = resolve(
+            r#"[{ "match": { "kind": "Process", "value": "a", "animations": { "fps": 60 } } }]"#,
+        );
+
+        assert_eq!(
+            current.changes_from(&current.clone()),
+            RuleChanges::default()
+        );
+
+        // The delays and enablement are only read when a border is created or shown.
+        let delayed = EffectiveRule {
+            enabled: Some(true),
+            initialize_delay: current.initialize_delay + 100,
+            unminimize_delay: current.unminimize_delay + 100,
+            ..current.clone()
+        };
+        assert_eq!(delayed.changes_from(&current), RuleChanges::default());
+    }
+
+    #[test]
+    fn rule_changes_only_cover_what_changed() {
+        let current = EffectiveRule::default();
+
+        let recolored = EffectiveRule {
+            inactive_color: GlobalColor::String("#ffffff".to_string()),
+            ..current.clone()
+        };
+        let animated = EffectiveRule {
+            animations: AnimationsConfig {
+                fps: Some(30),
+                ..Default::default()
+            },
+            ..current.clone()
+        };
+        let reshaped = EffectiveRule {
+            border_offset: current.border_offset + 1,
+            ..current.clone()
+        };
+
+        assert_eq!(
+            recolored.changes_from(&current),
+            RuleChanges {
+                colors: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            animated.changes_from(&current),
+            RuleChanges {
+                animations: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            reshaped.changes_from(&current),
+            RuleChanges {
+                shape: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn only_enablement_decides_whether_a_border_exists() {
+        let enabled = |enabled| EffectiveRule {
+            enabled,
+            ..Default::default()
+        };
+
+        // An explicit decision wins without asking for the window's style.
+        assert!(enabled(Some(true)).is_enabled(|| panic!("style was checked")));
+        assert!(!enabled(Some(false)).is_enabled(|| panic!("style was checked")));
+
+        assert!(enabled(None).is_enabled(|| false));
+        assert!(!enabled(None).is_enabled(|| true));
+
+        // Changing anything else keeps the border, which is then updated in place.
+        let recolored = EffectiveRule {
+            active_color: GlobalColor::String("#ff0000".to_string()),
+            border_width: 8,
+            ..enabled(None)
+        };
+        assert!(recolored.is_enabled(|| false));
+    }
+
+    #[test]
+    fn active_profile_is_saved_and_cleared() {
+        let config_dir =
+            std::env::temp_dir().join(format!("tacky-borders-{}-profile", std::process::id()));
+        DirBuilder::new()
+            .recursive(true)
+            .create(&config_dir)
+            .unwrap();
+
+        assert_eq!(read_active_profile(&config_dir), None);
+
+        write_active_profile(&config_dir, Some("focus")).unwrap();
+        assert_eq!(read_active_profile(&config_dir).as_deref(), Some("focus"));
+
+        write_active_profile(&config_dir, None).unwrap();
+        assert_eq!(read_active_profile(&config_dir), None);
+        // Clearing it again is not an error.
+        write_active_profile(&config_dir, None).unwrap();
+
+        write(config_dir.join(ACTIVE_PROFILE_FILE_NAME), " \n").unwrap();
+        assert_eq!(read_active_profile(&config_dir), None);
+
+        let _ = std::fs::remove_dir_all(&config_dir);
+    }
+}
@@ -137,6 +137,9 @@ impl WindowEventHook {
                     )
                     .with_context(|| "EVENT_OBJECT_LOCATIONCHANGE")
                     .log_if_err();
+                } else if APP_STATE.rule_engine.read().unwrap().uses_window_state() {
+                    // Rules testing the window's state may enable a border it didn't have.
+                    Border::show_for_window_state(handle.0.as_int());
                 }
             }
             EVENT_OBJECT_REORDER => {
@@ -201,6 +204,12 @@ impl WindowEventHook {
             // TODO this is called an unnecessary number of times which may hurt performance?
             EVENT_OBJECT_DESTROY => {
                 if id_child == CHILDID_SELF as i32 {
+                    APP_STATE
+                        .rule_engine
+                        .read()
+                        .unwrap()
+                        .forget_window(handle.0.as_int());
+
                     if let Some(border) = window_border(handle.0.as_int()) {
                         border.destroy();
                     }
@@ -0,0 +1,81 @@
+use crate::user_config::MatchKind;
+use std::fmt;
+
+/// Everything about a window that window rules can match on by name, collected once per
+/// lookup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct WindowIdentity {
+    pub title: String,
+    pub class: String,
+    /// File name of the executable up to the first `.`, e.g. `firefox`. This is what `Process`
+    /// rules have always matched, so it is kept as is.
+    pub process: String,
+    /// File name of the executable, e.g. `Code - Insiders.exe`.
+    pub process_full_name: String,
+    /// Full path of the executable.
+    pub exe_path: String,
+    /// App user model ID of the packaged app the window belongs to, or empty for unpackaged
+    /// apps. For UWP apps this is the app hosted by `ApplicationFrameHost`, not the host.
+    pub app_user_model_id: String,
+    /// File name of the executable that started the window's process, without its extension,
+    /// e.g. `explorer`.
+    pub parent_process: String,
+}
+
+impl WindowIdentity {
+    /// Returns the value that rules of the given kind are matched against.
+    pub fn value(&self, kind: &MatchKind) -> &str {
+        match kind {
+            MatchKind::Title => &self.title,
+            MatchKind::Class => &self.class,
+            MatchKind::Process => &self.process,
+            MatchKind::ProcessFullName => &self.process_full_name,
+            MatchKind::ExePath => &self.exe_path,
+            MatchKind::AppUserModelId => &self.app_user_model_id,
+            MatchKind::ParentProcess => &self.parent_process,
+            // The monitor can change while the window is open, so it is matched against the
+            // window's state instead.
+            MatchKind::Monitor => "",
+        }
+    }
+}
+
+impl fmt::Display for WindowIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let process = match self.process_full_name.is_empty() {
+            true => "unknown",
+            false => &self.process_full_name,
+        };
+
+        match self.app_user_model_id.is_empty() {
+            true => write!(f, "{process} ({:?})", self.title),
+            false => write!(
+                f,
+                "{process} [{}] ({:?})",
+                self.app_user_model_id, self.title
+            ),
+        }
+    }
+}
+
+/// Returns the process name `Process` rules match for an executable path, i.e. the file name
+/// up to the first `.`.
+pub fn process_name(exe_path: &str) -> String {
+    file_name(exe_path)
+        .split('.')
+        .next()
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Returns the file name of a Windows path without its extension, e.g. `Foo.Bar` for
+/// `Foo.Bar.exe`.
+pub fn file_stem(path: &str) -> &str {
+    let name = file_name(path);
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+/// Returns the file name part of a Windows path.
+pub fn file_name(path: &str) -> &str {
+    path.rsplit('\\').next().unwrap_or("")
+}
@@ -0,0 +1,345 @@
+use schema_jsonrs::json_schema;
+use schema_jsonrs::JsonSchema;
+use schema_jsonrs::Schema;
+use schema_jsonrs::SchemaGenerator;
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+/// How far (relative to the expected ratio) a window may be off and still match an exact
+/// `aspect_ratio`, since window sizes are whole pixels.
+const ASPECT_RATIO_TOLERANCE: f32 = 0.01;
+
+/// The parts of a window's state that window rules can match on.
+///
+/// Unlike the title, class and process, these change while the window is open, so rules
+/// using them are re-evaluated whenever the window moves or resizes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowState {
+    pub maximized: bool,
+    /// Whether the window covers its whole monitor, taskbar included.
+    pub fullscreen: bool,
+    pub topmost: bool,
+    /// Width in pixels at 100% scaling.
+    pub width: i32,
+    /// Height in pixels at 100% scaling.
+    pub height: i32,
+    /// The monitor the window is on, or `None` if it couldn't be determined.
+    pub monitor: Option<Monitor>,
+}
+
+impl WindowState {
+    /// Width divided by height, or `None` for a window without height.
+    pub fn aspect_ratio(&self) -> Option<f32> {
+        (self.height > 0).then(|| self.width as f32 / self.height as f32)
+    }
+}
+
+/// A display that windows can be on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Monitor {
+    /// Position of the monitor in the system's list of monitors, starting at 1.
+    pub index: usize,
+    /// Device name, e.g. `\\.\DISPLAY1`.
+    pub device_name: String,
+    pub primary: bool,
+}
+
+impl Monitor {
+    /// Returns every name the monitor can be matched by: its index, its device name and, for
+    /// the primary monitor, `primary`.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = vec![self.index.to_string(), self.device_name.clone()];
+        if self.primary {
+            names.push("primary".to_string());
+        }
+
+        names
+    }
+}
+
+/// A single test on a window's state, taken from the state fields of a `MatchCondition`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatePredicate {
+    Maximized(bool),
+    Fullscreen(bool),
+    Topmost(bool),
+    MinWidth(i32),
+    MinHeight(i32),
+    AspectRatio(AspectRatio),
+}
+
+impl StatePredicate {
+    pub fn is_match(&self, state: &WindowState) -> bool {
+        match self {
+            StatePredicate::Maximized(maximized) => state.maximized == *maximized,
+            StatePredicate::Fullscreen(fullscreen) => state.fullscreen == *fullscreen,
+            StatePredicate::Topmost(topmost) => state.topmost == *topmost,
+            StatePredicate::MinWidth(width) => state.width >= *width,
+            StatePredicate::MinHeight(height) => state.height >= *height,
+            StatePredicate::AspectRatio(aspect_ratio) => state
+                .aspect_ratio()
+                .is_some_and(|ratio| aspect_ratio.is_match(ratio)),
+        }
+    }
+}
+
+/// How a window's aspect ratio is compared with the one in the config.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Operators in the order they are tried when parsing, longest first.
+    const OPERATORS: [(&'static str, Comparison); 5] = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+        ("=", Comparison::Equal),
+    ];
+
+    fn operator(self) -> &'static str {
+        Self::OPERATORS
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .map(|(operator, _)| *operator)
+            .unwrap_or("=")
+    }
+}
+
+/// An aspect ratio condition such as `"16:9"`, `"> 2"` or `"<= 1"`.
+///
+/// Without an operator the window's ratio has to be within 1% of the given one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AspectRatio {
+    pub comparison: Comparison,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl AspectRatio {
+    pub fn ratio(&self) -> f32 {
+        self.width / self.height
+    }
+
+    pub fn is_match(&self, ratio: f32) -> bool {
+        let expected = self.ratio();
+
+        match self.comparison {
+            Comparison::Less => ratio < expected,
+            Comparison::LessOrEqual => ratio <= expected,
+            Comparison::Equal => (ratio - expected).abs() <= expected * ASPECT_RATIO_TOLERANCE,
+            Comparison::GreaterOrEqual => ratio >= expected,
+            Comparison::Greater => ratio > expected,
+        }
+    }
+}
+
+impl FromStr for AspectRatio {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (comparison, ratio) = Comparison::OPERATORS
+            .iter()
+            .find_map(|(operator, comparison)| {
+                s.strip_prefix(operator).map(|ratio| (*comparison, ratio))
+            })
+            .unwrap_or((Comparison::Equal, s));
+
+        let parse_number = |number: &str| {
+            number
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|number| number.is_finite() && *number > 0.0)
+                .ok_or_else(|| {
+                    format!("invalid aspect ratio `{s}`; expected e.g. '16:9' or '> 1.5'")
+                })
+        };
+
+        let (width, height) = match ratio.split_once(':') {
+            Some((width, height)) => (parse_number(width)?, parse_number(height)?),
+            None => (parse_number(ratio)?, 1.0),
+        };
+
+        Ok(Self {
+            comparison,
+            width,
+            height,
+        })
+    }
+}
+
+impl fmt::Display for AspectRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.comparison != Comparison::Equal {
+            write!(f, "{} ", self.comparison.operator())?;
+        }
+
+        match self.height == 1.0 {
+            true => write!(f, "{}", self.width),
+            false => write!(f, "{}:{}", self.width, self.height),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AspectRatio {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Number(f64),
+            String(String),
+        }
+
+        match Value::deserialize(deserializer)? {
+            Value::Number(number) => number.to_string().parse(),
+            Value::String(s) => s.parse(),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for AspectRatio {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl JsonSchema for AspectRatio {
+    fn schema_name() -> Cow<'static, str> {
+        "AspectRatio".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Width divided by height, optionally compared with '<', '<=', '>' or '>='. Without an operator the window's ratio has to be within 1%.",
+            "oneOf": [
+                {
+                    "type": "number",
+                    "exclusiveMinimum": 0,
+                    "description": "An exact ratio, e.g. 1.5."
+                },
+                {
+                    "type": "string",
+                    "pattern": "^\\s*(<=|>=|<|>|=)?\\s*\\d+(\\.\\d+)?(\\s*:\\s*\\d+(\\.\\d+)?)?\\s*$",
+                    "description": "A ratio as a number or 'width:height', e.g. '16:9', '> 2' or '<= 1'."
+                }
+            ]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(width: i32, height: i32) -> WindowState {
+        WindowState {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    fn aspect_ratio(s: &str) -> AspectRatio {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn aspect_ratio_without_operator_is_exact() {
+        let ratio = aspect_ratio("16:9");
+
+        assert_eq!(ratio.comparison, Comparison::Equal);
+        assert_eq!((ratio.width, ratio.height), (16.0, 9.0));
+    }
+
+    #[test]
+    fn aspect_ratio_parses_operators() {
+        assert_eq!(aspect_ratio("> 2").comparison, Comparison::Greater);
+        assert_eq!(aspect_ratio(">=2").comparison, Comparison::GreaterOrEqual);
+        assert_eq!(aspect_ratio("<= 1").comparison, Comparison::LessOrEqual);
+        assert_eq!(aspect_ratio(" < 4 : 3 ").comparison, Comparison::Less);
+        assert_eq!(aspect_ratio("= 1.5").comparison, Comparison::Equal);
+    }
+
+    #[test]
+    fn aspect_ratio_without_height_is_over_one() {
+        let ratio = aspect_ratio("> 2.5");
+
+        assert_eq!((ratio.width, ratio.height), (2.5, 1.0));
+        assert_eq!(ratio.to_string(), "> 2.5");
+    }
+
+    #[test]
+    fn invalid_aspect_ratios_are_rejected() {
+        for s in [
+            "", ">", "16:", ":9", "16:0", "-1", "wide", "16/9", "16:9:1", "<> 1",
+        ] {
+            assert!(s.parse::<AspectRatio>().is_err(), "{s:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn exact_aspect_ratio_allows_one_percent() {
+        let predicate = StatePredicate::AspectRatio(aspect_ratio("16:9"));
+
+        assert!(predicate.is_match(&window(1920, 1080)));
+        assert!(predicate.is_match(&window(1366, 768)));
+        assert!(!predicate.is_match(&window(1920, 1200)));
+    }
+
+    #[test]
+    fn aspect_ratio_comparisons() {
+        let wide = StatePredicate::AspectRatio(aspect_ratio("> 2"));
+        let square = StatePredicate::AspectRatio(aspect_ratio("<= 1"));
+
+        assert!(wide.is_match(&window(3440, 1440)));
+        assert!(!wide.is_match(&window(2000, 1000)));
+        assert!(square.is_match(&window(1000, 1000)));
+        assert!(!square.is_match(&window(1001, 1000)));
+    }
+
+    #[test]
+    fn window_without_height_has_no_aspect_ratio() {
+        let predicate = StatePredicate::AspectRatio(aspect_ratio("> 0.1"));
+
+        assert!(!predicate.is_match(&window(100, 0)));
+    }
+
+    #[test]
+    fn flags_and_minimum_sizes() {
+        let state = WindowState {
+            maximized: true,
+            topmost: true,
+            ..window(1280, 720)
+        };
+
+        assert!(StatePredicate::Maximized(true).is_match(&state));
+        assert!(!StatePredicate::Maximized(false).is_match(&state));
+        assert!(StatePredicate::Fullscreen(false).is_match(&state));
+        assert!(StatePredicate::Topmost(true).is_match(&state));
+        assert!(StatePredicate::MinWidth(1280).is_match(&state));
+        assert!(!StatePredicate::MinWidth(1281).is_match(&state));
+        assert!(StatePredicate::MinHeight(720).is_match(&state));
+        assert!(!StatePredicate::MinHeight(721).is_match(&state));
+    }
+}
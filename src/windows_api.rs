@@ -4,14 +4,20 @@ use crate::border_manager::Border;
 use crate::core::app_state::APP_STATE;
 use crate::core::rect::Rect;
 use crate::error::LogIfErr;
+use crate::user_config::EffectiveRule;
 use crate::user_config::MatchKind;
-use crate::user_config::MatchStrategy;
-use crate::user_config::WindowRuleConfig;
+use crate::window_identity::file_name;
+use crate::window_identity::file_stem;
+use crate::window_identity::process_name;
+use crate::window_identity::WindowIdentity;
+use crate::window_state::Monitor;
+use crate::window_state::WindowState;
+use crate::windows_callback::enum_child_windows;
+use crate::windows_callback::enum_monitors;
 use crate::windows_callback::enum_windows;
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result as AnyResult;
-use regex::Regex;
 use std::ffi::c_void;
 use std::ffi::OsStr;
 use std::ffi::OsString;
@@ -21,6 +27,7 @@ use std::os::windows::ffi::OsStringExt;
 use std::os::windows::io::AsRawHandle;
 use std::path::PathBuf;
 use std::ptr;
+use std::sync::Mutex;
 use std::thread::JoinHandle;
 use windows::core::w;
 use windows::core::Param;
@@ -51,8 +58,24 @@ use windows::Win32::Graphics::Dwm::DWM_CLOAKED_APP;
 use windows::Win32::Graphics::Dwm::DWM_CLOAKED_INHERITED;
 use windows::Win32::Graphics::Dwm::DWM_CLOAKED_SHELL;
 use windows::Win32::Graphics::Dwm::DWM_WINDOW_CORNER_PREFERENCE;
+use windows::Win32::Graphics::Gdi::EnumDisplayMonitors;
+use windows::Win32::Graphics::Gdi::GetMonitorInfoW;
+use windows::Win32::Graphics::Gdi::MonitorFromWindow;
+use windows::Win32::Graphics::Gdi::MONITORINFO;
+use windows::Win32::Graphics::Gdi::MONITORINFOEXW;
+use windows::Win32::Graphics::Gdi::MONITORINFOF_PRIMARY;
+use windows::Win32::Graphics::Gdi::MONITOR_DEFAULTTONEAREST;
+use windows::Win32::Storage::Packaging::Appx::GetApplicationUserModelId;
 use windows::Win32::System::Com::CoTaskMemFree;
+use windows::Win32::System::Console::AttachConsole;
+use windows::Win32::System::Console::ATTACH_PARENT_PROCESS;
+use windows::Win32::System::Diagnostics::ToolHelp::CreateToolhelp32Snapshot;
+use windows::Win32::System::Diagnostics::ToolHelp::Process32FirstW;
+use windows::Win32::System::Diagnostics::ToolHelp::Process32NextW;
+use windows::Win32::System::Diagnostics::ToolHelp::PROCESSENTRY32W;
+use windows::Win32::System::Diagnostics::ToolHelp::TH32CS_SNAPPROCESS;
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Threading::GetCurrentThreadId;
 use windows::Win32::System::Threading::GetThreadId;
 use windows::Win32::System::Threading::OpenProcess;
 use windows::Win32::System::Threading::QueryFullProcessImageNameW;
@@ -67,6 +90,7 @@ use windows::Win32::UI::Shell::SHGetKnownFolderPath;
 use windows::Win32::UI::Shell::KNOWN_FOLDER_FLAG;
 use windows::Win32::UI::WindowsAndMessaging::CreateWindowExW;
 use windows::Win32::UI::WindowsAndMessaging::DispatchMessageW;
+use windows::Win32::UI::WindowsAndMessaging::EnumChildWindows;
 use windows::Win32::UI::WindowsAndMessaging::EnumWindows;
 use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
 use windows::Win32::UI::WindowsAndMessaging::GetMessageW;
@@ -128,6 +152,15 @@ pub const WM_APP_HIDECLOAKED: u32 = WM_APP + 4;
 pub const WM_APP_MINIMIZESTART: u32 = WM_APP + 5;
 pub const WM_APP_MINIMIZEEND: u32 = WM_APP + 6;
 pub const WM_APP_TIMER: u32 = WM_APP + 7;
+pub const WM_APP_RELOADCONFIG: u32 = WM_APP + 8;
+pub const WM_APP_PROFILESCHANGED: u32 = WM_APP + 9;
+
+/// File name of the process that hosts the windows of UWP apps.
+const APPLICATION_FRAME_HOST: &str = "ApplicationFrameHost.exe";
+
+/// Handles of the display monitors in the system's order. Enumerating them is too slow to do on
+/// every location change, so the list is kept until the displays change.
+static MONITORS: Mutex<Vec<isize>> = Mutex::new(Vec::new());
 
 pub trait PointerConversion {
     fn as_int(&self) -> isize;
@@ -188,6 +221,19 @@ impl WindowsApi {
         unsafe { PostMessageW(hwnd, msg, wparam, lparam) }
     }
 
+    pub fn post_thread_message_w(
+        thread_id: u32,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> WinResult<()> {
+        unsafe { PostThreadMessageW(thread_id, msg, wparam, lparam) }
+    }
+
+    pub fn get_current_thread_id() -> u32 {
+        unsafe { GetCurrentThreadId() }
+    }
+
     pub fn send_notify_message_w(
         hwnd: HWND,
         msg: u32,
@@ -218,6 +264,12 @@ impl WindowsApi {
         unsafe { PostQuitMessage(nexitcode) }
     }
 
+    /// Attaches to the console of the parent process (e.g. the terminal the app was started
+    /// from), so command line modes can print output from a GUI subsystem build.
+    pub fn attach_parent_console() -> WinResult<()> {
+        unsafe { AttachConsole(ATTACH_PARENT_PROCESS) }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn create_window_ex_w<P1, P2>(
         dwexstyle: WINDOW_EX_STYLE,
@@ -326,6 +378,68 @@ impl WindowsApi {
         }
     }
 
+    /// Returns the monitor the window is on (or nearest to) together with its rect.
+    pub fn get_monitor(hwnd: isize) -> AnyResult<(Monitor, Rect)> {
+        let hmonitor = unsafe { MonitorFromWindow(hwnd.as_hwnd(), MONITOR_DEFAULTTONEAREST) };
+
+        let mut monitor_info = MONITORINFOEXW {
+            monitorInfo: MONITORINFO {
+                cbSize: std::mem::size_of::<MONITORINFOEXW>() as u32,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let info_ptr = &mut monitor_info as *mut MONITORINFOEXW as *mut MONITORINFO;
+        if !unsafe { GetMonitorInfoW(hmonitor, info_ptr) }.as_bool() {
+            return Err(anyhow!("could not retrieve monitor info for {hwnd:?}"));
+        }
+
+        let index = Self::monitor_index(hmonitor.0 as isize);
+
+        let device_name = String::from_utf16_lossy(&monitor_info.szDevice)
+            .trim_end_matches('\0')
+            .to_string();
+
+        let monitor = Monitor {
+            index,
+            device_name,
+            primary: monitor_info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+        };
+
+        Ok((monitor, Rect::from(monitor_info.monitorInfo.rcMonitor)))
+    }
+
+    /// Returns the position of a monitor in the system's list of monitors, starting at 1, or 0
+    /// if it isn't in the list.
+    fn monitor_index(hmonitor: isize) -> usize {
+        let mut monitors = MONITORS.lock().unwrap();
+
+        // A monitor we haven't seen means the cached list is out of date.
+        if !monitors.contains(&hmonitor) {
+            monitors.clear();
+            let _ = unsafe {
+                EnumDisplayMonitors(
+                    None,
+                    None,
+                    Some(enum_monitors),
+                    LPARAM(&mut *monitors as *mut Vec<isize> as isize),
+                )
+            };
+        }
+
+        monitors
+            .iter()
+            .position(|&monitor| monitor == hmonitor)
+            .map(|position| position + 1)
+            .unwrap_or_default()
+    }
+
+    /// Drops the cached list of monitors, e.g. after a display was added, removed or rearranged.
+    pub fn forget_monitors() {
+        MONITORS.lock().unwrap().clear();
+    }
+
     pub fn is_window_visible(hwnd: isize) -> bool {
         unsafe { IsWindowVisible(hwnd.as_hwnd()) }.into()
     }
@@ -341,6 +455,18 @@ impl WindowsApi {
         style.contains(WS_MINIMIZE)
     }
 
+    pub fn is_window_maximized(hwnd: isize) -> bool {
+        let style = Self::get_window_style(hwnd);
+
+        style.contains(WS_MAXIMIZE)
+    }
+
+    pub fn is_window_topmost(hwnd: isize) -> bool {
+        let ex_style = Self::get_window_ex_style(hwnd);
+
+        ex_style.contains(WS_EX_TOPMOST)
+    }
+
     pub fn is_window_visible_on_screen(hwnd: isize) -> bool {
         Self::is_window_visible(hwnd) && !Self::is_window_cloaked(hwnd)
     }
@@ -413,12 +539,16 @@ impl WindowsApi {
         Ok(buffer.to_string_lossy().trim_end_matches('\0').to_string())
     }
 
-    pub fn get_process_name(hwnd: isize) -> AnyResult<String> {
+    pub fn get_window_process_id(hwnd: isize) -> u32 {
         let mut process_id = 0u32;
         unsafe {
             GetWindowThreadProcessId(hwnd.as_hwnd(), Some(&mut process_id));
         }
 
+        process_id
+    }
+
+    pub fn get_process_exe_path(process_id: u32) -> AnyResult<String> {
         let process_handle =
             unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id) };
 
@@ -430,7 +560,7 @@ impl WindowsApi {
             }
         };
 
-        let mut buffer = [0u16; 256];
+        let mut buffer = [0u16; 1024];
         let mut length = buffer.len() as u32;
 
         let result = unsafe {
@@ -452,20 +582,120 @@ impl WindowsApi {
             CloseHandle(process_handle).ok(); // Ensure the handle is closed, ignoring the result
         }
 
-        let exe_path = String::from_utf16_lossy(&buffer[..length as usize]);
+        Ok(String::from_utf16_lossy(&buffer[..length as usize]))
+    }
+
+    /// Returns the app user model ID of a packaged process, or `None` if it isn't packaged.
+    pub fn get_process_app_user_model_id(process_id: u32) -> Option<String> {
+        let process_handle =
+            unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id) }.ok()?;
 
-        let process_name = exe_path
-            .split('\\')
-            .last()
-            .and_then(|file_name| file_name.split('.').next()) // Extract the file name without extension
-            .unwrap_or("") // Fallback to empty string if parsing fails
-            .trim_end_matches('\0')
-            .to_string();
+        let mut buffer = [0u16; 256];
+        let mut length = buffer.len() as u32;
 
-        Ok(process_name)
+        let result = unsafe {
+            GetApplicationUserModelId(
+                process_handle,
+                &mut length,
+                Some(PWSTR(buffer.as_mut_ptr())),
+            )
+        };
+
+        unsafe { CloseHandle(process_handle).ok() };
+
+        // The length includes the terminating null.
+        (result == ERROR_SUCCESS).then(|| {
+            String::from_utf16_lossy(&buffer[..length as usize])
+                .trim_end_matches('\0')
+                .to_string()
+        })
+    }
+
+    /// Returns the executable file name of the process that started `process_id`, or `None` if
+    /// it has already exited.
+    pub fn get_parent_process_name(process_id: u32) -> Option<String> {
+        let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) }.ok()?;
+
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+
+        // (process id, parent process id, executable file name)
+        let mut processes = Vec::new();
+        let mut next = unsafe { Process32FirstW(snapshot, &mut entry) };
+        while next.is_ok() {
+            let length = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            processes.push((
+                entry.th32ProcessID,
+                entry.th32ParentProcessID,
+                entry.szExeFile[..length].to_string_lossy(),
+            ));
+
+            next = unsafe { Process32NextW(snapshot, &mut entry) };
+        }
+
+        unsafe { CloseHandle(snapshot).ok() };
+
+        let parent_id = processes
+            .iter()
+            .find(|(id, _, _)| *id == process_id)
+            .map(|(_, parent_id, _)| *parent_id)?;
+
+        processes
+            .into_iter()
+            .find(|(id, _, _)| *id == parent_id)
+            .map(|(_, _, name)| name)
+    }
+
+    /// Returns the process of the app hosted in a UWP frame window, or `process_id` if none
+    /// was found (e.g. while the app is suspended).
+    fn get_hosted_process_id(hwnd: isize, process_id: u32) -> u32 {
+        let mut children: Vec<isize> = Vec::new();
+        let _ = unsafe {
+            EnumChildWindows(
+                Some(hwnd.as_hwnd()),
+                Some(enum_child_windows),
+                LPARAM(&mut children as *mut Vec<isize> as isize),
+            )
+        };
+
+        children
+            .into_iter()
+            .map(Self::get_window_process_id)
+            .find(|&child_process_id| child_process_id != process_id)
+            .unwrap_or(process_id)
+    }
+
+    /// Collects everything window rules can match on by name.
+    pub fn get_window_identity(hwnd: isize) -> WindowIdentity {
+        Self::collect_window_identity(hwnd, |_| true)
+    }
+
+    /// Like `get_window_identity`, but the app user model ID and the parent process are left
+    /// empty unless some window rule matches on them, since they take a lot more calls.
+    pub fn get_window_identity_for_rules(hwnd: isize) -> WindowIdentity {
+        let rule_engine = APP_STATE.rule_engine.read().unwrap();
+        let uses_app_user_model_id = rule_engine.uses_match_kind(&MatchKind::AppUserModelId);
+        let uses_parent_process = rule_engine.uses_match_kind(&MatchKind::ParentProcess);
+        drop(rule_engine);
+
+        Self::collect_window_identity(hwnd, |kind| match kind {
+            MatchKind::AppUserModelId => uses_app_user_model_id,
+            MatchKind::ParentProcess => uses_parent_process,
+            _ => true,
+        })
     }
 
-    pub fn get_window_rule(hwnd: isize) -> WindowRuleConfig {
+    /// Collects the window's identity, skipping the slow fields `include` returns `false` for.
+    fn collect_window_identity(
+        hwnd: isize,
+        include: impl Fn(&MatchKind) -> bool,
+    ) -> WindowIdentity {
         let title = match Self::get_window_title(hwnd) {
             Ok(val) => val,
             Err(err) => {
@@ -482,7 +712,9 @@ impl WindowsApi {
             }
         };
 
-        let process = match Self::get_process_name(hwnd) {
+        let process_id = Self::get_window_process_id(hwnd);
+
+        let exe_path = match Self::get_process_exe_path(process_id) {
             Ok(val) => val,
             Err(err) => {
                 error!("could not retrieve process name for {hwnd:?}: {err}");
@@ -490,43 +722,88 @@ impl WindowsApi {
             }
         };
 
-        let config = (*APP_STATE.config.read().unwrap()).clone();
+        let process_full_name = file_name(&exe_path).to_string();
+
+        let app_user_model_id = match include(&MatchKind::AppUserModelId) {
+            true => {
+                // UWP windows belong to ApplicationFrameHost, which shows the app in a child
+                // window.
+                let app_process_id =
+                    match process_full_name.eq_ignore_ascii_case(APPLICATION_FRAME_HOST) {
+                        true => Self::get_hosted_process_id(hwnd, process_id),
+                        false => process_id,
+                    };
+                Self::get_process_app_user_model_id(app_process_id).unwrap_or_default()
+            }
+            false => "".to_string(),
+        };
 
-        for rule in config.window_rules.iter() {
-            let window_name = match rule.match_window.match_kind {
-                Some(MatchKind::Title) => &title,
-                Some(MatchKind::Process) => &process,
-                Some(MatchKind::Class) => &class,
-                None => {
-                    error!("expected 'kind' for window rule but none found!");
-                    continue;
-                }
-            };
+        let parent_process = match include(&MatchKind::ParentProcess) {
+            true => Self::get_parent_process_name(process_id)
+                .map(|name| file_stem(&name).to_string())
+                .unwrap_or_default(),
+            false => "".to_string(),
+        };
 
-            let Some(match_value) = &rule.match_window.match_value else {
-                error!("expected `value` for window rule but non found!");
-                continue;
-            };
+        WindowIdentity {
+            title,
+            class,
+            process: process_name(&exe_path),
+            process_full_name,
+            app_user_model_id,
+            parent_process,
+            exe_path,
+        }
+    }
 
-            let has_match = match rule.match_window.match_strategy {
-                Some(MatchStrategy::Equals) | None => {
-                    window_name.to_lowercase().eq(&match_value.to_lowercase())
-                }
-                Some(MatchStrategy::Contains) => window_name
-                    .to_lowercase()
-                    .contains(&match_value.to_lowercase()),
-                Some(MatchStrategy::Regex) => Regex::new(match_value)
-                    .unwrap()
-                    .captures(window_name)
-                    .is_some(),
-            };
+    /// Returns the state that window rules can match on, with sizes scaled to 100%.
+    pub fn get_window_state(hwnd: isize) -> WindowState {
+        let rect = Self::window_rect(hwnd).unwrap_or_default();
 
-            if has_match {
-                return rule.clone();
+        let (monitor, fullscreen) = match Self::get_monitor(hwnd) {
+            Ok((monitor, monitor_rect)) => {
+                let fullscreen = rect.left <= monitor_rect.left
+                    && rect.top <= monitor_rect.top
+                    && rect.right >= monitor_rect.right
+                    && rect.bottom >= monitor_rect.bottom;
+                (Some(monitor), fullscreen)
             }
+            Err(err) => {
+                error!("could not retrieve monitor for {hwnd:?}: {err}");
+                (None, false)
+            }
+        };
+
+        let scale = match Self::get_dpi_for_window(hwnd) {
+            0 => 1.0,
+            dpi => 96.0 / dpi as f32,
+        };
+
+        WindowState {
+            maximized: Self::is_window_maximized(hwnd),
+            fullscreen,
+            topmost: Self::is_window_topmost(hwnd),
+            width: (rect.width() as f32 * scale).round() as i32,
+            height: (rect.height() as f32 * scale).round() as i32,
+            monitor,
         }
+    }
+
+    pub fn get_window_rule(hwnd: isize) -> EffectiveRule {
+        Self::get_window_rule_for(hwnd, &Self::get_window_identity_for_rules(hwnd))
+    }
+
+    /// Like `get_window_rule`, for a window whose identity was already collected.
+    pub fn get_window_rule_for(hwnd: isize, identity: &WindowIdentity) -> EffectiveRule {
+        let rule_engine = APP_STATE.rule_engine.read().unwrap();
+
+        // Reading the state takes a few more calls, so skip it if no rule needs it.
+        let state = match rule_engine.uses_window_state() {
+            true => Self::get_window_state(hwnd),
+            false => WindowState::default(),
+        };
 
-        WindowRuleConfig::default()
+        rule_engine.resolve(identity, &state)
     }
 
     pub fn collect_window_handles() -> AnyResult<Vec<isize>> {
@@ -535,18 +812,16 @@ impl WindowsApi {
         Ok(handles)
     }
 
-    pub fn process_window_handles(callback: &dyn Fn(isize, WindowRuleConfig)) -> AnyResult<()> {
+    pub fn process_window_handles(callback: &dyn Fn(isize, EffectiveRule)) -> AnyResult<()> {
         let handles = Self::collect_window_handles()?;
 
         handles.iter().for_each(|&hwnd| {
             if Self::is_window_visible_on_screen(hwnd) {
                 let window_rule = Self::get_window_rule(hwnd);
 
-                if window_rule.match_window.enabled == Some(false) {
+                if window_rule.enabled == Some(false) {
                     info!("border is disabled for {:?}", hwnd.as_hwnd());
-                } else if window_rule.match_window.enabled == Some(true)
-                    || !Self::has_filtered_style(hwnd)
-                {
+                } else if window_rule.is_enabled(|| Self::has_filtered_style(hwnd)) {
                     callback(hwnd, window_rule);
                 }
             }
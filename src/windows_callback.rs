@@ -1,6 +1,9 @@
 use windows::Win32::Foundation::BOOL;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Foundation::RECT;
+use windows::Win32::Graphics::Gdi::HDC;
+use windows::Win32::Graphics::Gdi::HMONITOR;
 
 use crate::windows_api::WindowsApi;
 
@@ -12,3 +15,22 @@ pub extern "system" fn enum_windows(hwnd: HWND, lparam: LPARAM) -> BOOL {
 
     true.into()
 }
+
+pub extern "system" fn enum_child_windows(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let windows = unsafe { &mut *(lparam.0 as *mut Vec<isize>) };
+    windows.push(hwnd.0 as isize);
+
+    true.into()
+}
+
+pub extern "system" fn enum_monitors(
+    hmonitor: HMONITOR,
+    _hdc: HDC,
+    _rect: *mut RECT,
+    lparam: LPARAM,
+) -> BOOL {
+    let monitors = unsafe { &mut *(lparam.0 as *mut Vec<isize>) };
+    monitors.push(hmonitor.0 as isize);
+
+    true.into()
+}